@@ -0,0 +1,325 @@
+use crate::quiz::export;
+use crate::quiz::import::{self, Format};
+use crate::quiz::lint::{self, Problem, Severity};
+use crate::quiz::{QuizDraft, QuizFile};
+#[cfg(feature = "replay")]
+use crate::ws::filter::WordList;
+#[cfg(feature = "replay")]
+use crate::ws::replay;
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+// `clap` turns the structs below into a command line parser
+use clap::{Parser, Subcommand, ValueEnum};
+
+use serde::Serialize;
+
+/// The address the server listens on unless told otherwise.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:3001";
+
+/// The kahoot clone server.
+///
+/// Starts the server when run without a command. Every command exits with 0
+/// if it worked, 1 if it checked something and found it wanting, and 2 if it
+/// couldn't run at all, eg. because a file couldn't be read.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Starts the server.
+    Serve {
+        #[arg(long, default_value = DEFAULT_ADDRESS)]
+        address: SocketAddr,
+        /// Keep game results in memory instead of writing them to disk, so
+        /// they're gone once the server stops.
+        #[arg(long)]
+        memory_results: bool,
+        /// Record everything sent and received in every room, so games can be
+        /// replayed with `replay`.
+        #[arg(long)]
+        record: bool,
+        /// Don't keep snapshots of rooms on disk, so rooms are gone for good
+        /// if the server stops.
+        #[arg(long)]
+        no_snapshots: bool,
+    },
+    /// Checks that a quiz JSON file can be played.
+    ///
+    /// The file can be a saved quiz or a `createRoom` message, and goes
+    /// through the same checks as creating a room. Exits with 0 if the quiz
+    /// can be played, 1 if it can't, and 2 if the file couldn't be checked.
+    Validate {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = Output::Human)]
+        output: Output,
+    },
+    /// Checks a quiz JSON file for problems and likely mistakes.
+    ///
+    /// Runs the same checks as `validate`, and also warns about duplicate
+    /// choices, very short timers, answers that are always in the same place
+    /// and text too long to fit on screen. Exits with 0 if there are no
+    /// errors, 1 if there are, and 2 if the file couldn't be checked.
+    Lint {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = Output::Human)]
+        output: Output,
+        /// Exit with 1 if there are warnings, too.
+        #[arg(long)]
+        deny_warnings: bool,
+    },
+    /// Converts a quiz from GIFT, Aiken, CSV or QTI into a quiz JSON file.
+    ///
+    /// The quiz is written to stdout, and anything that couldn't be imported
+    /// is listed on stderr. Exits with 0 if the quiz was converted, even if
+    /// parts of it were left out, and 2 if it couldn't be.
+    Import {
+        /// One of `gift`, `aiken`, `csv` or `qti`.
+        format: Format,
+        file: PathBuf,
+        /// The quiz's title, which defaults to the file name.
+        #[arg(long)]
+        title: Option<String>,
+    },
+    /// Converts a quiz JSON file into GIFT, Aiken, CSV or QTI.
+    ///
+    /// The file can be a saved quiz or a `createRoom` message, and has to
+    /// pass `validate`. The result is written to stdout. Exits with 0 if the
+    /// quiz was converted, and 2 if it couldn't be.
+    Export {
+        /// One of `gift`, `aiken`, `csv` or `qti`.
+        format: Format,
+        file: PathBuf,
+    },
+    /// Plays a recorded room again, and checks the server sends the same
+    /// messages.
+    ///
+    /// Usernames are checked against the same word list as the server's.
+    /// Exits with 0 if the replay matches the recording, 1 if it doesn't, and
+    /// 2 if the recording couldn't be replayed.
+    ///
+    /// Only built with the `replay` feature.
+    #[cfg(feature = "replay")]
+    Replay {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = Output::Human)]
+        output: Output,
+    },
+}
+
+/// How to print the results of a check or replay.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Output {
+    /// One line per problem.
+    Human,
+    /// A JSON object, for other tools to read.
+    Json,
+}
+
+/// What `validate` and `lint` print with `--output json`.
+#[derive(Serialize)]
+struct Report<'a> {
+    file: &'a Path,
+    errors: usize,
+    warnings: usize,
+    problems: &'a [Problem],
+}
+
+/// The exit code for when a command couldn't run at all, eg. because a file
+/// couldn't be read. 1 is kept for checks that ran and failed.
+fn not_run() -> ExitCode {
+    ExitCode::from(2)
+}
+
+/// Reads a quiz JSON file, printing why if it can't.
+fn read_quiz(file: &Path) -> Option<QuizFile> {
+    let json = match std::fs::read_to_string(file) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("error: couldn't read `{}`: {e}", file.display());
+            return None;
+        }
+    };
+
+    match serde_json::from_str(&json) {
+        Ok(quiz) => Some(quiz),
+        Err(e) => {
+            eprintln!("error: `{}` isn't a quiz: {e}", file.display());
+            None
+        }
+    }
+}
+
+/// Checks that a quiz can be played.
+pub fn validate(file: PathBuf, output: Output) -> ExitCode {
+    let Some(quiz) = read_quiz(&file) else { return not_run() };
+
+    report(&file, &lint::validate(&quiz.questions, &quiz.settings), output, false)
+}
+
+/// Checks a quiz for problems and likely mistakes.
+pub fn lint(file: PathBuf, output: Output, deny_warnings: bool) -> ExitCode {
+    let Some(quiz) = read_quiz(&file) else { return not_run() };
+
+    report(&file, &lint::lint(&quiz.questions, &quiz.settings), output, deny_warnings)
+}
+
+/// Prints the problems found in a file, and works out the exit code.
+fn report(file: &Path, problems: &[Problem], output: Output, deny_warnings: bool) -> ExitCode {
+    let errors = problems.iter().filter(|problem| problem.severity == Severity::Error).count();
+    let warnings = problems.len() - errors;
+
+    match output {
+        Output::Human => {
+            for problem in problems {
+                let severity = match problem.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                println!("{}: {severity}: {problem}", file.display());
+            }
+
+            println!("{}: {} and {}", file.display(), count(errors, "error"), count(warnings, "warning"));
+        }
+        Output::Json => {
+            let report = Report { file, errors, warnings, problems };
+            // Only fails for types that can't be JSON
+            println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+        }
+    }
+
+    if errors > 0 || (deny_warnings && warnings > 0) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// eg. `1 error`, `2 errors`
+fn count(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {noun}"),
+        _ => format!("{count} {noun}s"),
+    }
+}
+
+/// Converts a quiz file, printing the result as JSON.
+pub fn import(format: Format, file: PathBuf, title: Option<String>) -> ExitCode {
+    let source = match std::fs::read_to_string(&file) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("error: couldn't read `{}`: {e}", file.display());
+            return not_run();
+        }
+    };
+
+    let imported = match import::import(format, &source) {
+        Ok(imported) => imported,
+        Err(reason) => {
+            eprintln!("error: {reason}");
+            return not_run();
+        }
+    };
+
+    for warning in &imported.warnings {
+        eprintln!("warning: {warning}");
+    }
+
+    let title = title.unwrap_or_else(|| {
+        file.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+    let draft = QuizDraft { title, questions: imported.questions };
+
+    match serde_json::to_string_pretty(&draft) {
+        Ok(json) => {
+            println!("{json}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            not_run()
+        }
+    }
+}
+
+/// Converts a quiz JSON file, printing the result.
+pub fn export(format: Format, file: PathBuf) -> ExitCode {
+    let Some(quiz) = read_quiz(&file) else { return not_run() };
+
+    // The formats can only hold quizzes that can be played
+    if let Some(problem) = lint::validate(&quiz.questions, &quiz.settings).first() {
+        eprintln!("error: {problem}");
+        return not_run();
+    }
+
+    print!("{}", export::export(format, &quiz.title, &quiz.questions));
+    ExitCode::SUCCESS
+}
+
+/// Replays a recorded room, printing where it went differently.
+#[cfg(feature = "replay")]
+pub fn replay(file: PathBuf, output: Output) -> ExitCode {
+    let entries = match replay::read(&file) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("error: couldn't read `{}`: {e}", file.display());
+            return not_run();
+        }
+    };
+
+    // Replays run on their own runtime, which can't be started from this one
+    let replayed = std::thread::spawn(|| replay::replay(entries, WordList::from_env())).join();
+    let report = match replayed {
+        Ok(Ok(report)) => report,
+        Ok(Err(reason)) => {
+            eprintln!("error: couldn't replay `{}`: {reason}", file.display());
+            return not_run();
+        }
+        Err(_) => {
+            eprintln!("error: the replay of `{}` crashed", file.display());
+            return not_run();
+        }
+    };
+
+    match output {
+        Output::Human => {
+            for mismatch in &report.mismatches {
+                let show = |message: &Option<serde_json::Value>| {
+                    message
+                        .as_ref()
+                        .map_or_else(|| String::from("nothing"), |message| message.to_string())
+                };
+                println!(
+                    "{}: connection {}, message {}: recorded {}, replayed {}",
+                    file.display(),
+                    mismatch.connection,
+                    mismatch.index + 1,
+                    show(&mismatch.recorded),
+                    show(&mismatch.replayed),
+                );
+            }
+
+            println!(
+                "{}: {} replayed, {}",
+                file.display(),
+                count(report.messages, "message"),
+                count(report.mismatches.len(), "difference"),
+            );
+        }
+        Output::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default()),
+    }
+
+    if report.mismatches.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
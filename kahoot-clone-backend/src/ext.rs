@@ -41,4 +41,4 @@ where
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -1,14 +1,32 @@
-/// Module for handling the websocket api.
-mod ws;
+/// Contains the command line interface.
+mod cli;
 /// Contains convenience extension methods.
 ///
 /// Relevant: https://rust-lang.github.io/rfcs/0445-extension-trait-conventions.html
 mod ext;
+/// Contains the quiz library.
+mod quiz;
+/// Contains game result records and where they're kept.
+mod results;
+/// Module for handling the websocket api.
+mod ws;
 
 use std::net::SocketAddr;
+use std::process::ExitCode;
+use std::sync::Arc;
 
 // `axum` is a Rust web server framework
-use axum::Router;
+use axum::{Extension, Router};
+
+use clap::Parser;
+
+use cli::{Cli, Command};
+use quiz::store::QuizStore;
+use results::store::{DirectoryResults, MemoryResults};
+use ws::filter::WordList;
+use ws::recording::Recordings;
+use ws::snapshot::Snapshots;
+use ws::state::{SharedState, State};
 
 // `tracing` is an async logging library
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -44,31 +62,91 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 // You need to use a library for async in Rust because it doesn't provide
 // an "official" one out of the box which can be a pro or a con depending on
 // how you look at it.
+//
+// The note above is kept as it was written, blank line and all.
+#[allow(clippy::empty_line_after_outer_attr)]
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
+    match Cli::parse().command {
+        Some(Command::Serve { address, memory_results, record, no_snapshots }) => {
+            serve(address, memory_results, record, !no_snapshots).await;
+            ExitCode::SUCCESS
+        }
+        Some(Command::Validate { file, output }) => cli::validate(file, output),
+        Some(Command::Lint { file, output, deny_warnings }) => cli::lint(file, output, deny_warnings),
+        Some(Command::Import { format, file, title }) => cli::import(format, file, title),
+        Some(Command::Export { format, file }) => cli::export(format, file),
+        #[cfg(feature = "replay")]
+        Some(Command::Replay { file, output }) => cli::replay(file, output),
+        None => {
+            // `DEFAULT_ADDRESS` is always a valid address
+            serve(cli::DEFAULT_ADDRESS.parse().unwrap(), false, false, true).await;
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+/// Starts the server on the given address.
+async fn serve(addr: SocketAddr, memory_results: bool, record: bool, snapshots: bool) {
     // Logging stuff, can ignore
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "kahoot-server=trace".into())
+            std::env::var("RUST_LOG").unwrap_or_else(|_| "kahoot-server=trace".into()),
         ))
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    // Set the host address to `localhost:3000`
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3001));
-
     tracing::debug!("Listening on {addr}");
 
     // Start the server
     axum::Server::bind(&addr)
-        .serve(app().into_make_service())
+        .serve(app(memory_results, record, snapshots).into_make_service())
         .await
         .unwrap();
 }
 
 /// The server router
-fn app() -> Router {
+///
+/// Usernames are checked against the word list from `WordList::from_env`,
+/// quizzes are kept in the directory from `QuizStore::from_env`, and game
+/// results in the directory from `DirectoryResults::from_env` unless they're
+/// only kept in memory. Rooms are recorded into the directory from
+/// `Recordings::from_env` if `record` is set.
+///
+/// If `snapshots` is set, rooms are kept in the directory from
+/// `Snapshots::from_env`, and the rooms left there are brought back.
+fn app(memory_results: bool, record: bool, snapshots: bool) -> Router {
+    let name_filter = WordList::from_env();
+    let quizzes = QuizStore::from_env();
+    let recordings = record.then(Recordings::from_env);
+    let snapshots = snapshots.then(Snapshots::from_env);
+
+    let state = if memory_results {
+        State::new(name_filter, quizzes, MemoryResults::default(), recordings, snapshots)
+    } else {
+        State::new(name_filter, quizzes, DirectoryResults::from_env(), recordings, snapshots)
+    };
+
+    ws::snapshot::restore_all(&state);
+    tokio::spawn(ws::snapshot::keep_snapshots(Arc::clone(&state)));
+
+    app_with_state(state)
+}
+
+/// The server router, using the given shared state.
+fn app_with_state(state: SharedState) -> Router {
     Router::new()
         // GET /ws
         .nest("/ws", ws::router())
+        // GET /challenges/:id
+        .nest("/challenges", ws::challenge::router())
+        // GET, POST /quizzes
+        // GET, PUT, DELETE /quizzes/:id
+        .nest("/quizzes", quiz::router())
+        // GET /games
+        // GET /games/:id
+        // GET /games/:id/export/:format
+        .nest("/games", results::router())
+        // Includes the shared state in routes
+        .layer(Extension(state))
 }
@@ -0,0 +1,26 @@
+//! Writes the Aiken format.
+
+use crate::ws::api::Question;
+
+pub fn export(questions: &[Question]) -> String {
+    let mut aiken = String::new();
+
+    for question in questions {
+        // Everything has to fit on one line
+        aiken.push_str(&one_line(&question.question));
+        aiken.push('\n');
+
+        for (letter, choice) in ('A'..='Z').zip(&question.choices) {
+            aiken.push_str(&format!("{letter}. {}\n", one_line(choice)));
+        }
+
+        let answer = ('A'..='Z').nth(question.answer).unwrap_or('?');
+        aiken.push_str(&format!("ANSWER: {answer}\n\n"));
+    }
+
+    aiken
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,36 @@
+//! Writes CSV, with the columns the CSV importer reads.
+
+use crate::ws::api::Question;
+
+pub fn export(questions: &[Question]) -> String {
+    let choices = questions.iter().map(|question| question.choices.len()).max().unwrap_or(0);
+
+    let mut header = vec![String::from("Question")];
+    header.extend((1..=choices).map(|number| format!("Answer {number}")));
+    header.extend(["Correct", "Time", "Points", "Id", "Tags", "Difficulty"].map(String::from));
+
+    let mut writer = ::csv::Writer::from_writer(Vec::new());
+    // Writing to a `Vec` can't fail
+    let _ = writer.write_record(&header);
+
+    for question in questions {
+        let mut record = vec![question.question.clone()];
+        record.extend((0..choices).map(|index| question.choices.get(index).cloned().unwrap_or_default()));
+        record.extend([
+            (question.answer + 1).to_string(),
+            question.time.to_string(),
+            question.points.to_string(),
+            question.id.clone().unwrap_or_default(),
+            question.tags.join("; "),
+            question
+                .difficulty
+                .map(|difficulty| format!("{difficulty:?}").to_lowercase())
+                .unwrap_or_default(),
+        ]);
+
+        let _ = writer.write_record(&record);
+    }
+
+    let bytes = writer.into_inner().unwrap_or_default();
+    String::from_utf8(bytes).unwrap_or_default()
+}
@@ -0,0 +1,58 @@
+//! Writes Moodle's GIFT format.
+
+use crate::quiz::import::gift::{SPECIAL, TOP_CATEGORY};
+use crate::ws::api::Question;
+
+pub fn export(questions: &[Question]) -> String {
+    let mut gift = String::new();
+    let mut category = None;
+
+    for question in questions {
+        // Questions after a category line are in that category, until the next one
+        let tag = question.tags.first();
+        if tag != category {
+            let path = tag.map_or_else(|| String::from(TOP_CATEGORY), |tag| format!("{TOP_CATEGORY}/{tag}"));
+            gift.push_str(&format!("$CATEGORY: {path}\n\n"));
+            category = tag;
+        }
+
+        if let Some(id) = &question.id {
+            gift.push_str(&format!("::{}::", escape(id)));
+        }
+        gift.push_str(&escape(&question.question));
+
+        let true_false = question.choices == ["True", "False"];
+        if true_false {
+            gift.push_str(if question.answer == 0 { " {T}" } else { " {F}" });
+        } else {
+            gift.push_str(" {\n");
+            for (index, choice) in question.choices.iter().enumerate() {
+                let marker = if index == question.answer { '=' } else { '~' };
+                gift.push_str(&format!("    {marker}{}\n", escape(choice)));
+            }
+            gift.push('}');
+        }
+
+        gift.push_str("\n\n");
+    }
+
+    gift
+}
+
+/// Escapes the characters GIFT gives a meaning to.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            c if SPECIAL.contains(&c) => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
@@ -0,0 +1,120 @@
+/// The Aiken format.
+mod aiken;
+/// CSV, with the columns the CSV importer reads.
+mod csv;
+/// Moodle's GIFT format.
+mod gift;
+/// IMS QTI 2 XML.
+mod qti;
+
+use super::import::Format;
+
+use crate::ws::api::Question;
+
+/// Writes questions in another format.
+///
+/// Formats can't all hold everything a question has, so some of it is lost:
+///
+/// - GIFT keeps ids (as titles) and the first tag (as the category), but not
+///   times or points
+/// - Aiken only keeps the text, choices and answer
+/// - CSV keeps everything except point multipliers and wagers
+/// - QTI keeps ids (as identifiers)
+pub fn export(format: Format, title: &str, questions: &[Question]) -> String {
+    match format {
+        Format::Gift => gift::export(questions),
+        Format::Aiken => aiken::export(questions),
+        Format::Csv => csv::export(questions),
+        Format::Qti => qti::export(title, questions),
+    }
+}
+
+impl Format {
+    /// The content type of the format, for HTTP responses.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Gift | Format::Aiken => "text/plain; charset=utf-8",
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Qti => "application/xml",
+        }
+    }
+}
+
+/// Checks that exporting and importing again gives back the same questions,
+/// for every kind of question the formats can hold.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quiz::import::import;
+    use crate::ws::api::Difficulty;
+
+    /// A question with only the parts every format keeps.
+    fn question(text: &str, choices: &[&str], answer: usize) -> Question {
+        Question {
+            question: String::from(text),
+            choices: choices.iter().map(|choice| String::from(*choice)).collect(),
+            answer,
+            ..Question::default()
+        }
+    }
+
+    fn multiple_choice() -> Question {
+        question("What colour is a goldfish?", &["Blue", "Orange", "Green"], 1)
+    }
+
+    fn true_false() -> Question {
+        question("Goldfish can live in salt water.", &["True", "False"], 1)
+    }
+
+    /// Text with characters the formats treat specially.
+    fn special_characters() -> Question {
+        question("Is 1 = 1 {or} ~2? <b>&amp;</b> #\"quoted\", too: yes", &["A. one", "B) \"two\", 2"], 0)
+    }
+
+    fn round_trip(format: Format, questions: &[Question]) {
+        let exported = export(format, "Fish", questions);
+        let imported = import(format, &exported).unwrap();
+
+        assert_eq!(imported.questions, questions, "{format} round trip of:\n{exported}");
+        assert!(imported.warnings.is_empty(), "{format} round trip warned {:?}", imported.warnings);
+    }
+
+    #[test]
+    fn gift() {
+        let mut tagged = multiple_choice();
+        tagged.id = Some(String::from("Colour: goldfish"));
+        tagged.tags = vec![String::from("Fish")];
+
+        round_trip(Format::Gift, &[tagged, true_false(), special_characters()]);
+    }
+
+    #[test]
+    fn aiken() {
+        round_trip(Format::Aiken, &[multiple_choice(), true_false(), special_characters()]);
+    }
+
+    #[test]
+    fn csv() {
+        let everything = Question {
+            time: 30,
+            points: 2000,
+            id: Some(String::from("colour")),
+            tags: vec![String::from("fish"), String::from("colours")],
+            difficulty: Some(Difficulty::Hard),
+            ..multiple_choice()
+        };
+
+        round_trip(Format::Csv, &[everything, true_false(), special_characters()]);
+    }
+
+    #[test]
+    fn qti() {
+        let questions: Vec<_> = [multiple_choice(), true_false(), special_characters()]
+            .into_iter()
+            .enumerate()
+            .map(|(index, question)| Question { id: Some(format!("item{}", index + 1)), ..question })
+            .collect();
+
+        round_trip(Format::Qti, &questions);
+    }
+}
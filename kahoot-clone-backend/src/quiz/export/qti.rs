@@ -0,0 +1,67 @@
+//! Writes IMS QTI 2.1 items.
+//!
+//! A file can only have one root element, so the items are wrapped in an
+//! `assessmentItems` element. Packaging them up for an LMS is left to the LMS's
+//! own tools.
+
+use crate::ws::api::Question;
+
+use quick_xml::escape::escape;
+
+pub fn export(title: &str, questions: &[Question]) -> String {
+    let mut qti = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    qti.push_str(&format!("<assessmentItems title=\"{}\">\n", escape(title)));
+
+    for (index, question) in questions.iter().enumerate() {
+        let identifier = question
+            .id
+            .clone()
+            .filter(|id| is_identifier(id))
+            .unwrap_or_else(|| format!("item{}", index + 1));
+
+        qti.push_str(&format!(
+            "  <assessmentItem xmlns=\"http://www.imsglobal.org/xsd/imsqti_v2p1\" identifier=\"{identifier}\" \
+             title=\"{identifier}\" adaptive=\"false\" timeDependent=\"false\">\n"
+        ));
+        qti.push_str(&format!(
+            "    <responseDeclaration identifier=\"RESPONSE\" cardinality=\"single\" baseType=\"identifier\">\n\
+             \x20     <correctResponse><value>choice{}</value></correctResponse>\n\
+             \x20   </responseDeclaration>\n",
+            question.answer + 1,
+        ));
+        qti.push_str(
+            "    <outcomeDeclaration identifier=\"SCORE\" cardinality=\"single\" baseType=\"float\">\n\
+             \x20     <defaultValue><value>0</value></defaultValue>\n\
+             \x20   </outcomeDeclaration>\n",
+        );
+
+        qti.push_str("    <itemBody>\n");
+        qti.push_str("      <choiceInteraction responseIdentifier=\"RESPONSE\" shuffle=\"false\" maxChoices=\"1\">\n");
+        qti.push_str(&format!("        <prompt>{}</prompt>\n", escape(&question.question)));
+        for (index, choice) in question.choices.iter().enumerate() {
+            qti.push_str(&format!(
+                "        <simpleChoice identifier=\"choice{}\">{}</simpleChoice>\n",
+                index + 1,
+                escape(choice),
+            ));
+        }
+        qti.push_str("      </choiceInteraction>\n");
+        qti.push_str("    </itemBody>\n");
+
+        qti.push_str(
+            "    <responseProcessing template=\"http://www.imsglobal.org/question/qti_v2p1/rp/match_correct\"/>\n",
+        );
+        qti.push_str("  </assessmentItem>\n");
+    }
+
+    qti.push_str("</assessmentItems>\n");
+    qti
+}
+
+/// Whether the id can be used as a QTI identifier.
+fn is_identifier(id: &str) -> bool {
+    let mut chars = id.chars();
+
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
@@ -0,0 +1,106 @@
+//! Reads the Aiken format.
+//!
+//! ```text
+//! What colour is a goldfish?
+//! A. Blue
+//! B. Orange
+//! ANSWER: B
+//! ```
+//!
+//! Relevant: https://docs.moodle.org/en/Aiken_format
+
+use super::{collapse_whitespace, question, Imported};
+
+pub fn import(source: &str) -> Imported {
+    let mut imported = Imported::default();
+    let mut item = 0;
+
+    let mut text = String::new();
+    // The letter and text of every choice so far
+    let mut choices: Vec<(char, String)> = Vec::new();
+
+    for line in source.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(letter) = line.strip_prefix("ANSWER:") {
+            item += 1;
+
+            let letter = letter.trim();
+            let answer = choices
+                .iter()
+                .position(|(choice, _)| letter.len() == 1 && letter.starts_with(*choice));
+
+            match answer {
+                _ if text.is_empty() => imported.warn(item, "The question is missing, so the item was skipped"),
+                _ if choices.len() < 2 => {
+                    imported.warn(item, "The question needs at least two choices, so the item was skipped")
+                }
+                Some(answer) => {
+                    let choices = choices.drain(..).map(|(_, choice)| choice).collect();
+                    imported.questions.push(question(collapse_whitespace(&text), choices, answer));
+                }
+                None => imported.warn(item, format!("`{letter}` isn't one of the choices, so the item was skipped")),
+            }
+
+            text.clear();
+            choices.clear();
+        } else if let Some(choice) = choice(line) {
+            choices.push(choice);
+        } else if choices.is_empty() {
+            // Questions can run over several lines
+            text.push(' ');
+            text.push_str(line);
+        } else {
+            item += 1;
+            imported.warn(item, "The item has no `ANSWER:` line after its choices, so it was skipped");
+
+            text = String::from(line);
+            choices.clear();
+        }
+    }
+
+    if !text.is_empty() || !choices.is_empty() {
+        imported.warn(item + 1, "The item has no `ANSWER:` line after its choices, so it was skipped");
+    }
+
+    imported
+}
+
+/// Reads a choice line like `A. Blue` or `B) Orange`.
+fn choice(line: &str) -> Option<(char, String)> {
+    let mut chars = line.chars();
+    let letter = chars.next().filter(char::is_ascii_uppercase)?;
+    let rest = chars.as_str().strip_prefix(['.', ')'])?;
+
+    rest.starts_with(' ').then(|| (letter, String::from(rest.trim())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_questions() {
+        let source = "
+            What colour is a goldfish?
+            A. Blue
+            B) Orange
+            ANSWER: B
+
+            Which fish is a bird?
+            A. Trout
+            B. Salmon
+            ANSWER: C
+            Which fish has no answer?
+            A. Trout
+            B. Salmon
+        ";
+
+        let imported = import(source);
+        assert_eq!(imported.questions.len(), 1);
+        assert_eq!(imported.questions[0].question, "What colour is a goldfish?");
+        assert_eq!(imported.questions[0].choices, ["Blue", "Orange"]);
+        assert_eq!(imported.questions[0].answer, 1);
+
+        let items: Vec<_> = imported.warnings.iter().map(|warning| warning.item).collect();
+        assert_eq!(items, [Some(2), Some(3)]);
+    }
+}
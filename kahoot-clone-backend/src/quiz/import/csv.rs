@@ -0,0 +1,233 @@
+//! Reads spreadsheets saved as CSV.
+//!
+//! The first row names the columns, in any order and any case:
+//!
+//! - `question`
+//! - `answer 1`, `answer 2`, ... (or `choice 1`, `option 1`, ...)
+//! - `correct`, the number or letter of the correct choice, or its text
+//! - `time`, in seconds (optional)
+//! - `points`, `id`, `tags` and `difficulty` (optional)
+//!
+//! This also covers the columns of Kahoot's own spreadsheet template, like
+//! `Time limit (sec)` and `Correct answer(s)`.
+
+use super::{question, Imported};
+
+use crate::ws::api::Difficulty;
+
+/// What a column holds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Column {
+    Question,
+    /// The 1-indexed choice number.
+    Choice(usize),
+    Correct,
+    Time,
+    Points,
+    Id,
+    Tags,
+    Difficulty,
+    Unknown,
+}
+
+impl Column {
+    fn from_header(header: &str) -> Self {
+        let header = header.trim().to_lowercase();
+        // eg. `time limit (sec)` => `time limit`
+        let header = header.split('(').next().unwrap_or_default().trim();
+
+        let choice = ["answer", "choice", "option"]
+            .into_iter()
+            .find_map(|prefix| header.strip_prefix(prefix)?.trim().parse().ok());
+        if let Some(number) = choice {
+            return Column::Choice(number);
+        }
+
+        match header {
+            "question" | "question text" => Column::Question,
+            "correct" | "correct answer" | "answer" => Column::Correct,
+            "time" | "time limit" => Column::Time,
+            "points" => Column::Points,
+            "id" => Column::Id,
+            "tags" => Column::Tags,
+            "difficulty" => Column::Difficulty,
+            _ => Column::Unknown,
+        }
+    }
+}
+
+pub fn import(source: &str) -> Result<Imported, String> {
+    let mut imported = Imported::default();
+
+    let mut reader = ::csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(::csv::Trim::All)
+        .from_reader(source.as_bytes());
+
+    let headers = reader.headers().map_err(|e| format!("Couldn't read the header row: {e}"))?;
+    let columns: Vec<Column> = headers.iter().map(Column::from_header).collect();
+
+    if !columns.contains(&Column::Question) {
+        return Err(String::from("There is no `question` column"));
+    }
+    if !columns.contains(&Column::Correct) {
+        return Err(String::from("There is no `correct` column"));
+    }
+    if !columns.iter().any(|column| matches!(column, Column::Choice(_))) {
+        return Err(String::from("There are no `answer 1`, `answer 2`, ... columns"));
+    }
+
+    for (column, header) in columns.iter().zip(headers) {
+        if *column == Column::Unknown {
+            imported.warn(None, format!("The `{header}` column isn't supported and was ignored"));
+        }
+    }
+
+    for (index, record) in reader.records().enumerate() {
+        let item = index + 1;
+
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                imported.warn(item, format!("The row couldn't be read ({e}), so it was skipped"));
+                continue;
+            }
+        };
+
+        // Skip rows left blank
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+
+        let mut text = String::new();
+        let mut choices: Vec<(usize, String)> = Vec::new();
+        let mut correct = String::new();
+        let mut fields: Vec<(Column, &str)> = Vec::new();
+
+        for (column, value) in columns.iter().zip(&record) {
+            match column {
+                Column::Question => text = String::from(value),
+                // Spreadsheets often leave the last few choices empty
+                Column::Choice(number) if !value.is_empty() => choices.push((*number, String::from(value))),
+                Column::Correct => correct = String::from(value),
+                _ => fields.push((*column, value)),
+            }
+        }
+        choices.sort_by_key(|(number, _)| *number);
+
+        if text.is_empty() {
+            imported.warn(item, "The question is empty, so the row was skipped");
+            continue;
+        }
+        if choices.len() < 2 {
+            imported.warn(item, "The question needs at least two choices, so the row was skipped");
+            continue;
+        }
+
+        let Some(answer) = correct_choice(&correct, &choices) else {
+            let message = if correct.contains([',', ';']) {
+                String::from("Questions with more than one correct choice aren't supported, so the row was skipped")
+            } else {
+                format!("`{correct}` isn't one of the choices, so the row was skipped")
+            };
+            imported.warn(item, message);
+            continue;
+        };
+
+        let choices = choices.into_iter().map(|(_, choice)| choice).collect();
+        let mut question = question(text, choices, answer);
+
+        for (column, value) in fields.into_iter().filter(|(_, value)| !value.is_empty()) {
+            match column {
+                Column::Time => match value.parse() {
+                    Ok(time) => question.time = time,
+                    Err(_) => {
+                        imported.warn(item, format!("`{value}` isn't a time in seconds, so the usual time is used"))
+                    }
+                },
+                Column::Points => match value.parse() {
+                    Ok(points) => question.points = points,
+                    Err(_) => {
+                        imported.warn(item, format!("`{value}` isn't a number of points, so the usual points are used"))
+                    }
+                },
+                Column::Id => question.id = Some(String::from(value)),
+                Column::Tags => {
+                    question.tags = value
+                        .split([';', ','])
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(String::from)
+                        .collect()
+                }
+                Column::Difficulty => match Difficulty::ALL
+                    .into_iter()
+                    .find(|difficulty| format!("{difficulty:?}").eq_ignore_ascii_case(value))
+                {
+                    Some(difficulty) => question.difficulty = Some(difficulty),
+                    None => imported.warn(item, format!("`{value}` isn't easy, medium or hard, so it was ignored")),
+                },
+                _ => (),
+            }
+        }
+
+        imported.questions.push(question);
+    }
+
+    Ok(imported)
+}
+
+/// Works out which choice the `correct` column means.
+///
+/// It can be the choice's number, its letter, or its text.
+fn correct_choice(correct: &str, choices: &[(usize, String)]) -> Option<usize> {
+    let number = correct.parse::<usize>().ok().or_else(|| {
+        let mut chars = correct.chars();
+        let letter = chars.next().filter(char::is_ascii_alphabetic)?;
+
+        chars
+            .as_str()
+            .is_empty()
+            .then(|| usize::from(letter.to_ascii_uppercase() as u8 - b'A') + 1)
+    });
+
+    number
+        .and_then(|number| choices.iter().position(|(choice, _)| *choice == number))
+        .or_else(|| choices.iter().position(|(_, choice)| choice == correct))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_rows() {
+        let source = "\
+Question,Answer 1,Answer 2,Answer 3,Answer 4,Time limit (sec),Correct answer(s),Notes
+What colour is a goldfish?,Blue,Orange,,,30,2,
+Which fish is a bird?,Trout,Salmon,,,,C,
+Which are fish?,Trout,Salmon,Crow,,,\"1,2\",
+Which fish is orange?,Trout,Goldfish,,,soon,Goldfish,
+";
+
+        let imported = import(source).unwrap();
+        let [colour, orange] = &imported.questions[..] else {
+            panic!("Expected 2 questions, got {:?}", imported.questions);
+        };
+
+        assert_eq!(colour.choices, ["Blue", "Orange"]);
+        assert_eq!(colour.answer, 1);
+        assert_eq!(colour.time, 30);
+
+        assert_eq!(orange.answer, 1);
+        assert_eq!(orange.time, crate::ws::api::DEFAULT_TIME);
+
+        let items: Vec<_> = imported.warnings.iter().map(|warning| warning.item).collect();
+        assert_eq!(items, [None, Some(2), Some(3), Some(4)]);
+    }
+
+    #[test]
+    fn needs_columns() {
+        assert!(import("Question,Correct\nFish?,1\n").is_err());
+    }
+}
@@ -0,0 +1,390 @@
+//! Reads Moodle's GIFT format.
+//!
+//! Multiple choice, true/false and missing word questions are imported. Short
+//! answer, numerical, matching and essay questions can't be played, so they
+//! are skipped.
+//!
+//! Relevant: https://docs.moodle.org/en/GIFT_format
+
+use super::{collapse_whitespace, question, Imported};
+
+use crate::ws::api::Question;
+
+/// The category questions go in when they don't say otherwise.
+pub const TOP_CATEGORY: &str = "$course$/top";
+
+/// Characters which have to be escaped with `\` to be used literally.
+pub const SPECIAL: [char; 7] = ['~', '=', '#', '{', '}', ':', '\\'];
+
+pub fn import(source: &str) -> Imported {
+    let mut imported = Imported::default();
+    let mut category = None;
+    let mut item = 0;
+
+    for block in blocks(source) {
+        if let Some(path) = block.strip_prefix("$CATEGORY:") {
+            // eg. `$course$/top/Fish`, which tags the questions after it with
+            // `Fish`. The course's top category doesn't tag them.
+            let path = path.trim();
+            category = match path {
+                TOP_CATEGORY | "$course$" => None,
+                _ => path.rsplit('/').next().filter(|name| !name.is_empty()).map(String::from),
+            };
+            continue;
+        }
+
+        item += 1;
+        if let Some(mut question) = import_item(&block, item, &mut imported) {
+            question.tags.extend(category.clone());
+            imported.questions.push(question);
+        }
+    }
+
+    imported
+}
+
+/// Splits the source into items, which are separated by blank lines.
+///
+/// Comment lines are dropped along the way.
+fn blocks(source: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut block = String::new();
+
+    for line in source.lines() {
+        let line = line.trim();
+
+        if line.starts_with("//") {
+            continue;
+        }
+
+        if line.is_empty() {
+            if !block.is_empty() {
+                blocks.push(std::mem::take(&mut block));
+            }
+            continue;
+        }
+
+        if !block.is_empty() {
+            block.push('\n');
+        }
+        block.push_str(line);
+    }
+
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+
+    blocks
+}
+
+fn import_item(block: &str, item: usize, imported: &mut Imported) -> Option<Question> {
+    let mut rest = block;
+
+    // `::Title::`
+    let mut title = None;
+    if let Some(after) = rest.strip_prefix("::") {
+        let Some(end) = find_unescaped(after, "::") else {
+            imported.warn(item, "The title is missing a closing `::`, so the item was skipped");
+            return None;
+        };
+        title = Some(unescape(after[..end].trim()));
+        rest = &after[end + 2..];
+    }
+
+    let rest = rest.trim_start();
+    let rest = match strip_markup(rest) {
+        Some(("html", text)) => {
+            imported.warn(item, "HTML formatting was imported as plain text");
+            text
+        }
+        Some((_, text)) => text,
+        None => rest,
+    };
+
+    let Some(open) = find_unescaped(rest, "{") else {
+        imported.warn(item, "Descriptions without answers aren't supported, so the item was skipped");
+        return None;
+    };
+    let Some(close) = find_unescaped(&rest[open..], "}").map(|close| open + close) else {
+        imported.warn(item, "The answers are missing a closing `}`, so the item was skipped");
+        return None;
+    };
+
+    let before = unescape(rest[..open].trim());
+    let after = unescape(rest[close + 1..].trim());
+    let text = if after.is_empty() {
+        before
+    } else {
+        // Missing word questions put the answers where the word goes
+        format!("{before} _____ {after}")
+    };
+    let text = collapse_whitespace(&text);
+
+    let answers = rest[open + 1..close].trim();
+
+    let (choices, answer) = match answers {
+        "" => {
+            imported.warn(item, "Essay questions aren't supported, so the item was skipped");
+            return None;
+        }
+        _ if answers.starts_with('#') => {
+            imported.warn(item, "Numerical questions aren't supported, so the item was skipped");
+            return None;
+        }
+        _ if is_true_false(answers) => {
+            let (value, feedback) = split_unescaped(answers, '#');
+            if feedback.is_some() {
+                imported.warn(item, "Feedback isn't supported and was dropped");
+            }
+
+            let answer = if matches!(value.trim(), "T" | "TRUE") { 0 } else { 1 };
+            (vec![String::from("True"), String::from("False")], answer)
+        }
+        _ => multiple_choice(answers, item, imported)?,
+    };
+
+    let mut question = question(text, choices, answer);
+    question.id = title;
+
+    Some(question)
+}
+
+/// Reads the answers of a multiple choice question.
+fn multiple_choice(answers: &str, item: usize, imported: &mut Imported) -> Option<(Vec<String>, usize)> {
+    let mut choices = Vec::new();
+    let mut correct = Vec::new();
+    let mut any_wrong = false;
+    let mut feedback = false;
+    let mut partial = false;
+
+    for (marker, answer) in split_answers(answers) {
+        let (answer, answer_feedback) = split_unescaped(answer, '#');
+        feedback |= answer_feedback.is_some();
+
+        let mut answer = answer.trim();
+        let mut weight = None;
+        if let Some(after) = answer.strip_prefix('%') {
+            if let Some(end) = after.find('%') {
+                weight = after[..end].parse::<f64>().ok();
+                answer = after[end + 1..].trim();
+            }
+        }
+
+        if find_unescaped(answer, "->").is_some() {
+            imported.warn(item, "Matching questions aren't supported, so the item was skipped");
+            return None;
+        }
+
+        let full_credit = match (marker, weight) {
+            ('=', _) => true,
+            (_, Some(weight)) => {
+                if weight > 0.0 && weight < 100.0 {
+                    partial = true;
+                }
+                weight >= 100.0
+            }
+            _ => false,
+        };
+        any_wrong |= marker == '~';
+
+        if full_credit {
+            correct.push(choices.len());
+        }
+        choices.push(unescape(answer));
+    }
+
+    if !any_wrong {
+        imported.warn(item, "Short answer questions aren't supported, so the item was skipped");
+        return None;
+    }
+
+    if choices.len() < 2 {
+        imported.warn(item, "The question needs at least two choices, so the item was skipped");
+        return None;
+    }
+
+    let answer = match correct[..] {
+        [answer] => answer,
+        [] => {
+            imported.warn(item, "None of the choices is fully correct, so the item was skipped");
+            return None;
+        }
+        _ => {
+            imported
+                .warn(item, "Questions with more than one correct choice aren't supported, so the item was skipped");
+            return None;
+        }
+    };
+
+    if partial {
+        imported.warn(item, "Partial credit isn't supported, so those choices count as wrong");
+    }
+    if feedback {
+        imported.warn(item, "Feedback isn't supported and was dropped");
+    }
+
+    Some((choices, answer))
+}
+
+fn is_true_false(answers: &str) -> bool {
+    let (value, _) = split_unescaped(answers, '#');
+
+    matches!(value.trim(), "T" | "F" | "TRUE" | "FALSE")
+}
+
+/// Strips a `[html]`, `[moodle]`, `[markdown]` or `[plain]` prefix, returning
+/// the markup it was.
+fn strip_markup(text: &str) -> Option<(&str, &str)> {
+    let after = text.strip_prefix('[')?;
+    let end = after.find(']')?;
+    let markup = &after[..end];
+
+    matches!(markup, "html" | "moodle" | "markdown" | "plain").then(|| (markup, &after[end + 1..]))
+}
+
+/// Splits the inside of `{...}` into answers, each starting with `=` or `~`.
+fn split_answers(answers: &str) -> Vec<(char, &str)> {
+    let mut split = Vec::new();
+    let mut start: Option<(char, usize)> = None;
+    let mut escaped = false;
+
+    for (index, c) in answers.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match c {
+            '\\' => escaped = true,
+            '=' | '~' => {
+                if let Some((marker, from)) = start {
+                    split.push((marker, &answers[from..index]));
+                }
+                start = Some((c, index + 1));
+            }
+            _ => (),
+        }
+    }
+
+    if let Some((marker, from)) = start {
+        split.push((marker, &answers[from..]));
+    }
+
+    split
+}
+
+/// Finds the first occurrence of a pattern which isn't escaped.
+fn find_unescaped(text: &str, pattern: &str) -> Option<usize> {
+    let mut escaped = false;
+
+    for (index, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if text[index..].starts_with(pattern) {
+            return Some(index);
+        }
+    }
+
+    None
+}
+
+/// Splits the text at the first unescaped `separator`.
+fn split_unescaped(text: &str, separator: char) -> (&str, Option<&str>) {
+    match find_unescaped(text, separator.encode_utf8(&mut [0; 4])) {
+        Some(index) => (&text[..index], Some(&text[index + separator.len_utf8()..])),
+        None => (text, None),
+    }
+}
+
+/// Turns escapes like `\=` back into the characters they stand for.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next)) if SPECIAL.contains(&next) => {
+                unescaped.push(next);
+                chars.next();
+            }
+            ('\\', Some('n')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_supported_kinds() {
+        let source = "
+            // A comment
+            $CATEGORY: $course$/top/Fish
+
+            ::Colour::What colour is a goldfish? {
+                ~Blue#Nope
+                =Orange
+                ~Green
+            }
+
+            Goldfish can live in salt water.{F}
+
+            A goldfish has a memory of {~three seconds =months ~one day} at least.
+
+            Which fish is 1\\=1? {=a \\{trout\\} ~a salmon}
+        ";
+
+        let imported = import(source);
+        let [colour, salt, memory, trout] = &imported.questions[..] else {
+            panic!("Expected 4 questions, got {:?}", imported.questions);
+        };
+
+        assert_eq!(colour.id.as_deref(), Some("Colour"));
+        assert_eq!(colour.choices, ["Blue", "Orange", "Green"]);
+        assert_eq!(colour.answer, 1);
+        assert_eq!(colour.tags, ["Fish"]);
+
+        assert_eq!(salt.choices, ["True", "False"]);
+        assert_eq!(salt.answer, 1);
+
+        assert_eq!(memory.question, "A goldfish has a memory of _____ at least.");
+        assert_eq!(memory.answer, 1);
+
+        assert_eq!(trout.question, "Which fish is 1=1?");
+        assert_eq!(trout.choices, ["a {trout}", "a salmon"]);
+
+        // The feedback on `Blue`
+        assert_eq!(imported.warnings.len(), 1);
+        assert_eq!(imported.warnings[0].item, Some(1));
+    }
+
+    #[test]
+    fn skips_unsupported_kinds() {
+        let source = "
+            Who's buried in Grant's tomb?{=Grant =Ulysses S. Grant}
+
+            When was Ulysses S. Grant born?{#1822:5}
+
+            Match the fish.{=goldfish -> orange ~salmon -> pink}
+
+            Write about fish.{}
+
+            Which are fish?{~%50%Trout ~%50%Salmon ~%-100%Crow}
+        ";
+
+        let imported = import(source);
+        assert!(imported.questions.is_empty());
+
+        let items: Vec<_> = imported.warnings.iter().map(|warning| warning.item).collect();
+        assert_eq!(items, [Some(1), Some(2), Some(3), Some(4), Some(5)]);
+    }
+}
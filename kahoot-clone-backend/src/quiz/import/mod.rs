@@ -0,0 +1,110 @@
+/// The Aiken format, a plain text list of multiple choice questions.
+mod aiken;
+/// Spreadsheets saved as CSV, with a header row.
+mod csv;
+/// Moodle's GIFT format.
+pub mod gift;
+/// IMS QTI 2 XML.
+mod qti;
+
+use crate::ws::api::Question;
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// A quiz format other tools use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Gift,
+    Aiken,
+    Csv,
+    Qti,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Gift, Format::Aiken, Format::Csv, Format::Qti];
+
+    /// The format's name, as used in urls and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Gift => "gift",
+            Format::Aiken => "aiken",
+            Format::Csv => "csv",
+            Format::Qti => "qti",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown format `{s}`, expected one of gift, aiken, csv or qti"))
+    }
+}
+
+/// The questions that could be imported, and what was lost along the way.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Imported {
+    pub questions: Vec<Question>,
+    pub warnings: Vec<ImportWarning>,
+}
+
+/// Something in the source that couldn't be imported as it was.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImportWarning {
+    /// The 1-indexed position of the item in the source, or `None` if the
+    /// warning is about the whole file.
+    pub item: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ImportWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.item {
+            Some(item) => write!(f, "item {item}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl Imported {
+    fn warn(&mut self, item: impl Into<Option<usize>>, message: impl Into<String>) {
+        self.warnings.push(ImportWarning { item: item.into(), message: message.into() });
+    }
+}
+
+/// Converts a quiz in another format into questions.
+///
+/// Items that can't be played are skipped with a warning. Returns an error if
+/// the source can't be read at all.
+pub fn import(format: Format, source: &str) -> Result<Imported, String> {
+    match format {
+        Format::Gift => Ok(gift::import(source)),
+        Format::Aiken => Ok(aiken::import(source)),
+        Format::Csv => csv::import(source),
+        Format::Qti => qti::import(source),
+    }
+}
+
+/// Creates a question with the usual settings.
+fn question(question: String, choices: Vec<String>, answer: usize) -> Question {
+    Question { question, choices, answer, ..Question::default() }
+}
+
+/// Collapses runs of whitespace into single spaces.
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
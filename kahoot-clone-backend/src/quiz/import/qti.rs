@@ -0,0 +1,303 @@
+//! Reads IMS QTI 2 items.
+//!
+//! The source can be a single `assessmentItem`, or any document with several
+//! of them inside. Only single-answer `choiceInteraction`s can be played, so
+//! items with any other interaction are skipped.
+//!
+//! Relevant: https://www.imsglobal.org/question/qtiv2p1/imsqti_infov2p1.html
+
+use super::{collapse_whitespace, question, Imported};
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+/// What is known about the item being read.
+#[derive(Default)]
+struct Item {
+    identifier: Option<String>,
+    /// Whether more than one choice can be correct.
+    multiple: bool,
+    correct: Vec<String>,
+    prompt: String,
+    /// The identifier and text of every choice.
+    choices: Vec<(String, String)>,
+    /// Interactions other than `choiceInteraction`.
+    unsupported: Vec<String>,
+    interactions: usize,
+    feedback: bool,
+    media: bool,
+}
+
+/// Where the text being read goes.
+#[derive(Clone, Copy, PartialEq)]
+enum Text {
+    Ignored,
+    Prompt,
+    Choice,
+    Correct,
+}
+
+pub fn import(source: &str) -> Result<Imported, String> {
+    let mut imported = Imported::default();
+    let mut reader = Reader::from_str(source);
+
+    let mut item: Option<Item> = None;
+    let mut count = 0;
+    // Where text goes, for each open element
+    let mut text_stack = vec![Text::Ignored];
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Couldn't read the XML at byte {}: {e}", reader.buffer_position()))?;
+
+        match event {
+            Event::Start(element) => {
+                let name = local_name(&element);
+
+                if name == "questestinterop" {
+                    return Err(String::from("QTI 1.2 isn't supported, only QTI 2 items are"));
+                }
+
+                if name == "assessmentItem" {
+                    count += 1;
+                    item = Some(Item { identifier: attribute(&element, "identifier")?, ..Item::default() });
+                }
+
+                if is_block(&name) {
+                    push_text(&mut item, text_stack.last(), " ");
+                }
+
+                let parent = *text_stack.last().unwrap_or(&Text::Ignored);
+                let text = match &mut item {
+                    Some(item) => start_element(item, &name, &element, parent)?,
+                    None => Text::Ignored,
+                };
+                text_stack.push(text);
+            }
+            Event::Empty(element) => {
+                let name = local_name(&element);
+
+                if is_block(&name) {
+                    push_text(&mut item, text_stack.last(), " ");
+                }
+
+                if let Some(item) = &mut item {
+                    start_element(item, &name, &element, Text::Ignored)?;
+                }
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(|e| format!("Couldn't read the XML: {e}"))?;
+                push_text(&mut item, text_stack.last(), &text);
+            }
+            Event::CData(text) => {
+                push_text(&mut item, text_stack.last(), &String::from_utf8_lossy(&text));
+            }
+            Event::End(element) => {
+                if is_block(&local_name_of(element.local_name().as_ref())) {
+                    push_text(&mut item, text_stack.last(), " ");
+                }
+                text_stack.pop();
+
+                if element.local_name().as_ref() == b"assessmentItem" {
+                    if let Some(item) = item.take() {
+                        finish_item(item, count, &mut imported);
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    if count == 0 {
+        return Err(String::from("There are no `assessmentItem`s"));
+    }
+
+    Ok(imported)
+}
+
+/// Notes down what an element means for the item, and returns where the text
+/// inside it goes.
+fn start_element(item: &mut Item, name: &str, element: &BytesStart, parent: Text) -> Result<Text, String> {
+    let text = match name {
+        "responseDeclaration" => {
+            let cardinality = attribute(element, "cardinality")?;
+            item.multiple |= cardinality.is_some_and(|cardinality| cardinality != "single");
+            Text::Ignored
+        }
+        "value" if parent == Text::Correct => Text::Correct,
+        "correctResponse" => Text::Correct,
+        "itemBody" | "prompt" => Text::Prompt,
+        "choiceInteraction" => {
+            item.interactions += 1;
+            Text::Ignored
+        }
+        "simpleChoice" => {
+            let identifier = attribute(element, "identifier")?.unwrap_or_default();
+            item.choices.push((identifier, String::new()));
+            Text::Choice
+        }
+        "feedbackInline" | "feedbackBlock" | "modalFeedback" => {
+            item.feedback = true;
+            Text::Ignored
+        }
+        "img" | "object" | "audio" | "video" | "math" => {
+            item.media = true;
+            Text::Ignored
+        }
+        _ if name.ends_with("Interaction") => {
+            item.unsupported.push(String::from(name));
+            Text::Ignored
+        }
+        // Formatting like `<p>` or `<b>` keeps its text
+        _ => parent,
+    };
+
+    Ok(text)
+}
+
+fn push_text(item: &mut Option<Item>, text: Option<&Text>, value: &str) {
+    let Some(item) = item else { return };
+
+    let target = match text {
+        Some(Text::Prompt) => &mut item.prompt,
+        Some(Text::Choice) => match item.choices.last_mut() {
+            Some((_, choice)) => choice,
+            None => return,
+        },
+        Some(Text::Correct) => {
+            if !value.trim().is_empty() {
+                item.correct.push(String::from(value.trim()));
+            }
+            return;
+        }
+        _ => return,
+    };
+
+    target.push_str(value);
+}
+
+/// Whether the element starts on a new line, so its text needs to be kept
+/// apart from the text around it.
+fn is_block(name: &str) -> bool {
+    matches!(name, "p" | "div" | "br" | "li" | "prompt" | "simpleChoice")
+}
+
+fn finish_item(item: Item, number: usize, imported: &mut Imported) {
+    if let Some(interaction) = item.unsupported.first() {
+        imported.warn(number, format!("`{interaction}` isn't supported, so the item was skipped"));
+        return;
+    }
+
+    if item.interactions != 1 {
+        imported.warn(number, "Items need exactly one `choiceInteraction`, so the item was skipped");
+        return;
+    }
+
+    if item.multiple || item.correct.len() > 1 {
+        imported.warn(number, "Questions with more than one correct choice aren't supported, so the item was skipped");
+        return;
+    }
+
+    if item.choices.len() < 2 {
+        imported.warn(number, "The question needs at least two choices, so the item was skipped");
+        return;
+    }
+
+    let Some(answer) = item
+        .correct
+        .first()
+        .and_then(|correct| item.choices.iter().position(|(identifier, _)| identifier == correct))
+    else {
+        imported.warn(number, "The correct response isn't one of the choices, so the item was skipped");
+        return;
+    };
+
+    if item.feedback {
+        imported.warn(number, "Feedback isn't supported and was dropped");
+    }
+    if item.media {
+        imported.warn(number, "Images, media and maths aren't supported and were dropped");
+    }
+
+    let choices = item
+        .choices
+        .into_iter()
+        .map(|(_, choice)| collapse_whitespace(&choice))
+        .collect();
+    let mut question = question(collapse_whitespace(&item.prompt), choices, answer);
+    question.id = item.identifier;
+
+    imported.questions.push(question);
+}
+
+fn local_name(element: &BytesStart) -> String {
+    local_name_of(element.local_name().as_ref())
+}
+
+fn local_name_of(name: &[u8]) -> String {
+    String::from_utf8_lossy(name).into_owned()
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>, String> {
+    let attribute = element
+        .try_get_attribute(name)
+        .map_err(|e| format!("Couldn't read the XML: {e}"))?;
+
+    attribute
+        .map(|attribute| attribute.unescape_value().map(String::from))
+        .transpose()
+        .map_err(|e| format!("Couldn't read the XML: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_items() {
+        let source = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <items>
+                <assessmentItem xmlns="http://www.imsglobal.org/xsd/imsqti_v2p1" identifier="colour" title="Colour">
+                    <responseDeclaration identifier="RESPONSE" cardinality="single" baseType="identifier">
+                        <correctResponse><value>B</value></correctResponse>
+                    </responseDeclaration>
+                    <itemBody>
+                        <choiceInteraction responseIdentifier="RESPONSE" maxChoices="1">
+                            <prompt>What colour is a <b>goldfish</b>?</prompt>
+                            <simpleChoice identifier="A">Blue</simpleChoice>
+                            <simpleChoice identifier="B">Orange &amp; gold</simpleChoice>
+                        </choiceInteraction>
+                    </itemBody>
+                </assessmentItem>
+                <assessmentItem identifier="name">
+                    <responseDeclaration identifier="RESPONSE" cardinality="single" baseType="string"/>
+                    <itemBody>
+                        <p>Name a fish.</p>
+                        <textEntryInteraction responseIdentifier="RESPONSE"/>
+                    </itemBody>
+                </assessmentItem>
+            </items>
+        "#;
+
+        let imported = import(source).unwrap();
+        assert_eq!(imported.questions.len(), 1);
+
+        let question = &imported.questions[0];
+        assert_eq!(question.id.as_deref(), Some("colour"));
+        assert_eq!(question.question, "What colour is a goldfish?");
+        assert_eq!(question.choices, ["Blue", "Orange & gold"]);
+        assert_eq!(question.answer, 1);
+
+        assert_eq!(imported.warnings.len(), 1);
+        assert_eq!(imported.warnings[0].item, Some(2));
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(import("<questestinterop></questestinterop>").is_err());
+        assert!(import("<html></html>").is_err());
+        assert!(import("<unclosed").is_err());
+    }
+}
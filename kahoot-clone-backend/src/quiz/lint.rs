@@ -0,0 +1,274 @@
+use crate::ws::api::{JoinFailCode, Question, RoomSettings};
+use crate::ws::{pool, username};
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Timers shorter than this, in seconds, barely leave time to read the question.
+pub const SHORT_TIME: u16 = 5;
+/// The longest question, in characters, that fits on the host's screen.
+pub const LONG_QUESTION: usize = 120;
+/// The longest choice, in characters, that fits on a player's button.
+pub const LONG_CHOICE: usize = 75;
+/// The most points a question can be worth, before its multiplier.
+pub const MAX_POINTS: u32 = 10_000;
+/// The biggest multiplier a question can have.
+pub const MAX_MULTIPLIER: u32 = 10;
+/// The most choices a question can have, one for each letter, so every
+/// export format can hold them.
+pub const MAX_CHOICES: usize = 26;
+/// Quizzes with fewer questions than this can have every answer in the same
+/// place by chance.
+const SAME_POSITION_QUESTIONS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    /// The quiz can't be played.
+    Error,
+    /// The quiz can be played, but probably isn't what was meant.
+    Warning,
+}
+
+/// Something wrong with a quiz.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Problem {
+    pub severity: Severity,
+    /// The 1-indexed question the problem is in, or `None` if it's about the
+    /// whole quiz.
+    pub question: Option<usize>,
+    pub message: String,
+}
+
+impl Problem {
+    fn error(question: impl Into<Option<usize>>, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Error, question: question.into(), message: message.into() }
+    }
+
+    fn warning(question: impl Into<Option<usize>>, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, question: question.into(), message: message.into() }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.question {
+            Some(question) => write!(f, "Question {question}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Finds the problems that stop a room from being created with these
+/// questions and settings.
+pub fn validate(questions: &[Question], settings: &RoomSettings) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    if questions.is_empty() {
+        problems.push(Problem::error(None, "There are no questions"));
+    }
+
+    for (index, question) in questions.iter().enumerate() {
+        let number = index + 1;
+
+        if question.choices.len() < 2 {
+            problems.push(Problem::error(number, "There need to be at least two choices"));
+        } else if question.choices.len() > MAX_CHOICES {
+            problems.push(Problem::error(number, format!("There can be at most {MAX_CHOICES} choices")));
+        }
+
+        if question.answer >= question.choices.len() {
+            problems.push(Problem::error(number, "The answer isn't one of the choices"));
+        }
+
+        if question.time == 0 {
+            problems.push(Problem::error(number, "There is no time to answer"));
+        }
+
+        if question.points > MAX_POINTS {
+            problems.push(Problem::error(number, format!("Questions can be worth at most {MAX_POINTS} points")));
+        }
+
+        if question.multiplier > MAX_MULTIPLIER {
+            problems.push(Problem::error(number, format!("The multiplier can be at most {MAX_MULTIPLIER}")));
+        }
+    }
+
+    // Roster names join like typed names, so they have to pass the same checks
+    let mut names: Vec<(String, String)> = Vec::new();
+    for entry in settings.roster.iter().flatten() {
+        let name = match username::normalize(&entry.name) {
+            Ok(name) => name,
+            Err(code) => {
+                let message = format!("Roster entry `{}`: {}", entry.name, code.reason());
+                problems.push(Problem::error(None, message));
+                continue;
+            }
+        };
+        let skeleton = username::skeleton(&name);
+
+        for (taken, taken_skeleton) in &names {
+            match username::check_clash(&name, &skeleton, taken, taken_skeleton) {
+                Err(JoinFailCode::DuplicateUser) => {
+                    problems.push(Problem::error(None, format!("`{name}` is on the roster more than once")));
+                }
+                Err(_) => {
+                    problems.push(Problem::error(None, format!("Roster names `{taken}` and `{name}` look too alike")));
+                }
+                Ok(()) => (),
+            }
+        }
+
+        names.push((name, skeleton));
+    }
+
+    if settings.adaptive.is_some() && settings.selection.is_some() {
+        problems.push(Problem::error(None, "Adaptive games can't use a selection"));
+    } else if let Some(selection) = &settings.selection {
+        // Whether there are enough questions doesn't depend on the seed
+        if let Err(reason) = pool::draw(questions, selection, 0) {
+            problems.push(Problem::error(None, reason));
+        }
+    }
+
+    problems
+}
+
+/// Finds the problems that stop a room from being created, along with things
+/// that are probably mistakes.
+pub fn lint(questions: &[Question], settings: &RoomSettings) -> Vec<Problem> {
+    let mut problems = validate(questions, settings);
+
+    for (index, question) in questions.iter().enumerate() {
+        let number = index + 1;
+
+        let mut seen = HashSet::new();
+        for choice in &question.choices {
+            let normalized = choice.trim().to_lowercase();
+            if !seen.insert(normalized) {
+                problems.push(Problem::warning(number, format!("The choice `{choice}` is there more than once")));
+            }
+        }
+
+        if question.time > 0 && question.time < SHORT_TIME {
+            problems.push(Problem::warning(
+                number,
+                format!("{} seconds is barely enough time to read the question", question.time),
+            ));
+        }
+
+        let length = question.question.chars().count();
+        if length > LONG_QUESTION {
+            problems.push(Problem::warning(
+                number,
+                format!("The question is {length} characters long, and only {LONG_QUESTION} fit on screen"),
+            ));
+        }
+
+        for (choice_number, choice) in question.choices.iter().enumerate().map(|(index, choice)| (index + 1, choice)) {
+            let length = choice.chars().count();
+            if length > LONG_CHOICE {
+                problems.push(Problem::warning(
+                    number,
+                    format!(
+                        "Choice {choice_number} is {length} characters long, and only {LONG_CHOICE} fit on a button"
+                    ),
+                ));
+            }
+        }
+    }
+
+    // Players catch on when the answer is always in the same place, unless
+    // they each see the choices in their own order
+    let answers: HashSet<usize> = questions.iter().map(|question| question.answer).collect();
+    if questions.len() >= SAME_POSITION_QUESTIONS && answers.len() == 1 && !settings.shuffle_choices {
+        let choice = answers.into_iter().next().unwrap_or_default() + 1;
+        problems.push(Problem::warning(None, format!("Every answer is choice {choice}")));
+    }
+
+    problems.sort_by_key(|problem| (problem.question, problem.severity));
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::api::RosterEntry;
+
+    fn question(choices: &[&str], answer: usize, time: u16) -> Question {
+        Question {
+            question: String::from("Fish?"),
+            choices: choices.iter().map(|choice| String::from(*choice)).collect(),
+            answer,
+            time,
+            ..Question::default()
+        }
+    }
+
+    #[test]
+    fn finds_errors() {
+        let settings = RoomSettings::default();
+        assert_eq!(validate(&[], &settings).len(), 1);
+
+        let questions = [question(&["a"], 1, 0), question(&["a", "b"], 1, 10)];
+        let problems = validate(&questions, &settings);
+
+        // Too few choices, an answer that isn't a choice, and no time
+        assert_eq!(problems.len(), 3);
+        assert!(problems
+            .iter()
+            .all(|problem| problem.severity == Severity::Error && problem.question == Some(1)));
+
+        // Points that would overflow the scores
+        let questions = [Question { points: u32::MAX, multiplier: u32::MAX, ..question(&["a", "b"], 1, 10) }];
+        assert_eq!(validate(&questions, &settings).len(), 2);
+
+        // More choices than there are letters to export them with
+        let choices = vec!["a"; MAX_CHOICES + 1];
+        let problems = validate(&[question(&choices, MAX_CHOICES, 10)], &settings);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].message, "There can be at most 26 choices");
+    }
+
+    #[test]
+    fn checks_rosters() {
+        let entry = |name: &str| RosterEntry { name: String::from(name), id: None, code: None, time_multiplier: None };
+        let questions = [question(&["a", "b"], 1, 10)];
+
+        // Names are cleaned up like typed names before they're compared
+        let roster = vec![entry("  Alice\u{200B} "), entry("Bob")];
+        let settings = RoomSettings { roster: Some(roster), ..RoomSettings::default() };
+        assert_eq!(validate(&questions, &settings), []);
+
+        // Too long, the same name twice and look-alike names
+        let roster =
+            vec![entry(&"a".repeat(username::MAX_USERNAME_LEN + 1)), entry("Bob"), entry("bob"), entry("B\u{43E}b")];
+        let settings = RoomSettings { roster: Some(roster), ..RoomSettings::default() };
+        let problems: Vec<_> = validate(&questions, &settings)
+            .into_iter()
+            .map(|problem| problem.message)
+            .collect();
+        assert_eq!(problems.len(), 4, "{problems:?}");
+        assert!(problems[0].ends_with("Username is too long"));
+        assert_eq!(problems[1], "`bob` is on the roster more than once");
+    }
+
+    #[test]
+    fn finds_warnings() {
+        let long = "x".repeat(LONG_CHOICE + 1);
+        let questions =
+            [question(&["a", "b", "A "], 1, 10), question(&["a", "b"], 1, 2), question(&["a", &long], 1, 10)];
+
+        let problems = lint(&questions, &RoomSettings::default());
+        assert!(problems.iter().all(|problem| problem.severity == Severity::Warning));
+
+        let questions: Vec<_> = problems.iter().map(|problem| problem.question).collect();
+        assert_eq!(questions, [None, Some(1), Some(2), Some(3)]);
+
+        // Shuffled choices move the answers around
+        let settings = RoomSettings { shuffle_choices: true, ..RoomSettings::default() };
+        assert_eq!(lint(&vec![question(&["a", "b"], 1, 10); 3], &settings), []);
+    }
+}
@@ -0,0 +1,221 @@
+/// Contains converters to other quiz formats.
+pub mod export;
+/// Contains converters from other quiz formats.
+pub mod import;
+/// Contains the checks quizzes go through before they're played.
+pub mod lint;
+/// Contains the directory-backed quiz store.
+pub mod store;
+
+use import::{Format, Imported};
+
+use crate::ws::api::{Question, RoomSettings};
+use crate::ws::state::SharedState;
+
+use std::io;
+
+use axum::extract::{Path, Query};
+use axum::http::{header, StatusCode};
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+
+use serde::{Deserialize, Serialize};
+
+pub type QuizId = u32;
+
+/// A saved quiz.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quiz {
+    pub id: QuizId,
+    /// Starts at 1 and goes up every time the quiz is updated.
+    pub version: u32,
+    pub title: String,
+    pub questions: Vec<Question>,
+    /// When this version was saved, in milliseconds since the Unix epoch.
+    pub updated_at: u64,
+}
+
+/// The parts of a quiz that clients write.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuizDraft {
+    pub title: String,
+    pub questions: Vec<Question>,
+}
+
+/// A quiz read from a JSON file or request body.
+///
+/// Anything with a `questions` field will do, like a saved quiz or a
+/// `createRoom` message.
+#[derive(Clone, Debug, Deserialize)]
+pub struct QuizFile {
+    #[serde(default)]
+    pub title: String,
+    pub questions: Vec<Question>,
+    #[serde(default)]
+    pub settings: RoomSettings,
+}
+
+/// A quiz in the quiz list, without its questions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuizSummary {
+    pub id: QuizId,
+    pub version: u32,
+    pub title: String,
+    pub question_count: usize,
+    pub updated_at: u64,
+}
+
+impl Quiz {
+    pub fn summary(&self) -> QuizSummary {
+        QuizSummary {
+            id: self.id,
+            version: self.version,
+            title: self.title.clone(),
+            question_count: self.questions.len(),
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+impl QuizDraft {
+    /// Checks that the quiz can be played.
+    pub fn check(&self) -> Result<(), String> {
+        if self.title.trim().is_empty() {
+            return Err(String::from("The quiz needs a title"));
+        }
+
+        match lint::validate(&self.questions, &RoomSettings::default()).first() {
+            Some(problem) => Err(problem.to_string()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// An error response with a reason in the body.
+type ApiError = (StatusCode, String);
+
+/// Quiz library api router.
+///
+/// The shared state has to be included by whoever uses this router.
+pub fn router() -> Router {
+    Router::new()
+        // GET /, POST /
+        .route("/", get(list_quizzes).post(create_quiz))
+        // GET /:id, PUT /:id, DELETE /:id
+        .route("/:id", get(get_quiz).put(update_quiz).delete(delete_quiz))
+        // GET /:id/versions/:version
+        .route("/:id/versions/:version", get(get_quiz_version))
+        // GET /:id/export/:format
+        .route("/:id/export/:format", get(export_quiz))
+        // POST /import/:format
+        .route("/import/:format", post(import_quiz))
+        // POST /export/:format
+        .route("/export/:format", post(export_questions))
+}
+
+/// Logs a storage error, without telling clients about the server's files.
+fn storage_error(e: io::Error) -> ApiError {
+    tracing::error!("Quiz storage failed: {e}");
+
+    (StatusCode::INTERNAL_SERVER_ERROR, String::from("Quiz storage failed"))
+}
+
+fn not_found() -> ApiError {
+    (StatusCode::NOT_FOUND, String::from("Quiz does not exist"))
+}
+
+/// Returns the latest version of every quiz, without their questions.
+async fn list_quizzes(Extension(state): Extension<SharedState>) -> Result<Json<Vec<QuizSummary>>, ApiError> {
+    state.quizzes.list().map(Json).map_err(storage_error)
+}
+
+/// Saves a new quiz.
+async fn create_quiz(
+    Extension(state): Extension<SharedState>,
+    Json(draft): Json<QuizDraft>,
+) -> Result<(StatusCode, Json<Quiz>), ApiError> {
+    draft.check().map_err(|reason| (StatusCode::BAD_REQUEST, reason))?;
+
+    let quiz = state.quizzes.create(draft).map_err(storage_error)?;
+    tracing::debug!("Created quiz `{}`", quiz.id);
+
+    Ok((StatusCode::CREATED, Json(quiz)))
+}
+
+/// Returns the latest version of a quiz.
+async fn get_quiz(Path(id): Path<QuizId>, Extension(state): Extension<SharedState>) -> Result<Json<Quiz>, ApiError> {
+    let quiz = state.quizzes.get(id, None).map_err(storage_error)?;
+
+    quiz.map(Json).ok_or_else(not_found)
+}
+
+/// Returns a specific version of a quiz.
+async fn get_quiz_version(
+    Path((id, version)): Path<(QuizId, u32)>,
+    Extension(state): Extension<SharedState>,
+) -> Result<Json<Quiz>, ApiError> {
+    let quiz = state.quizzes.get(id, Some(version)).map_err(storage_error)?;
+
+    quiz.map(Json).ok_or_else(not_found)
+}
+
+/// Saves a new version of a quiz.
+async fn update_quiz(
+    Path(id): Path<QuizId>,
+    Extension(state): Extension<SharedState>,
+    Json(draft): Json<QuizDraft>,
+) -> Result<Json<Quiz>, ApiError> {
+    draft.check().map_err(|reason| (StatusCode::BAD_REQUEST, reason))?;
+
+    let quiz = state.quizzes.update(id, draft).map_err(storage_error)?;
+
+    quiz.map(Json).ok_or_else(not_found)
+}
+
+/// Converts a quiz in another format into questions, without saving it.
+async fn import_quiz(Path(format): Path<Format>, source: String) -> Result<Json<Imported>, ApiError> {
+    import::import(format, &source)
+        .map(Json)
+        .map_err(|reason| (StatusCode::BAD_REQUEST, reason))
+}
+
+/// Which version of a quiz to export.
+#[derive(Deserialize)]
+struct ExportQuery {
+    /// Defaults to the latest version.
+    version: Option<u32>,
+}
+
+/// The exported quiz, with the format's content type.
+type Exported = ([(header::HeaderName, &'static str); 1], String);
+
+/// Converts a saved quiz into another format.
+async fn export_quiz(
+    Path((id, format)): Path<(QuizId, Format)>,
+    Query(query): Query<ExportQuery>,
+    Extension(state): Extension<SharedState>,
+) -> Result<Exported, ApiError> {
+    let quiz = state
+        .quizzes
+        .get(id, query.version)
+        .map_err(storage_error)?
+        .ok_or_else(not_found)?;
+
+    Ok(([(header::CONTENT_TYPE, format.content_type())], export::export(format, &quiz.title, &quiz.questions)))
+}
+
+/// Converts questions sent in the request, like a `createRoom` message, into
+/// another format.
+async fn export_questions(Path(format): Path<Format>, Json(quiz): Json<QuizFile>) -> Exported {
+    ([(header::CONTENT_TYPE, format.content_type())], export::export(format, &quiz.title, &quiz.questions))
+}
+
+/// Deletes a quiz and all of its versions.
+async fn delete_quiz(Path(id): Path<QuizId>, Extension(state): Extension<SharedState>) -> Result<StatusCode, ApiError> {
+    match state.quizzes.delete(id).map_err(storage_error)? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(not_found()),
+    }
+}
@@ -0,0 +1,253 @@
+use super::{Quiz, QuizDraft, QuizId, QuizSummary};
+
+use crate::ws::now_millis;
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The directory used when `KAHOOT_QUIZ_DIR` isn't set.
+pub const DEFAULT_QUIZ_DIR: &str = "quizzes";
+
+/// Keeps quizzes as JSON files in a directory.
+///
+/// Every quiz gets its own directory named after its id, with one file per
+/// version, so older versions can still be looked up after an update:
+/// ```text
+/// quizzes/
+///     1234/
+///         1.json
+///         2.json
+/// ```
+pub struct QuizStore {
+    /// `None` for stores that never have any quizzes (see `empty`).
+    dir: Option<PathBuf>,
+    /// Stops two updates from picking the same version number.
+    writing: Mutex<()>,
+}
+
+impl QuizStore {
+    /// Creates a store backed by the given directory.
+    ///
+    /// The directory is created when the first quiz is saved.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: Some(dir.into()), writing: Mutex::new(()) }
+    }
+
+    /// Creates a store that has no quizzes and can't save any, for when the
+    /// quiz library shouldn't be used at all (eg. in replays).
+    #[cfg(any(test, feature = "replay"))]
+    pub fn empty() -> Self {
+        Self { dir: None, writing: Mutex::new(()) }
+    }
+
+    /// Uses the directory at `KAHOOT_QUIZ_DIR`, or `DEFAULT_QUIZ_DIR` if it
+    /// isn't set.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("KAHOOT_QUIZ_DIR").unwrap_or_else(|_| DEFAULT_QUIZ_DIR.into()))
+    }
+
+    /// Lists the latest version of every quiz, by id.
+    pub fn list(&self) -> io::Result<Vec<QuizSummary>> {
+        let Some(dir) = &self.dir else {
+            return Ok(Vec::new());
+        };
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            // Nothing has been saved yet
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut summaries = Vec::new();
+        for entry in entries {
+            let id = entry?.file_name().to_str().and_then(|name| name.parse().ok());
+
+            if let Some(quiz) = id.map(|id| self.get(id, None)).transpose()?.flatten() {
+                summaries.push(quiz.summary());
+            }
+        }
+        summaries.sort_by_key(|summary| summary.id);
+
+        Ok(summaries)
+    }
+
+    /// Loads a version of a quiz, or the latest one if no version is given.
+    pub fn get(&self, id: QuizId, version: Option<u32>) -> io::Result<Option<Quiz>> {
+        if self.dir.is_none() {
+            return Ok(None);
+        }
+
+        let version = match version {
+            Some(version) => version,
+            None => match self.latest_version(id)? {
+                Some(version) => version,
+                None => return Ok(None),
+            },
+        };
+
+        match fs::read(self.version_path(id, version)?) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Saves a new quiz as version 1, giving it an unused id.
+    pub fn create(&self, draft: QuizDraft) -> io::Result<Quiz> {
+        let _writing = self.writing.lock().unwrap();
+
+        let id = loop {
+            let id: QuizId = rand::random();
+            if !self.quiz_dir(id)?.exists() {
+                break id;
+            }
+        };
+
+        let quiz = Quiz::new(id, 1, draft);
+        self.save(&quiz)?;
+
+        Ok(quiz)
+    }
+
+    /// Saves a new version of an existing quiz.
+    ///
+    /// Returns `None` if the quiz doesn't exist.
+    pub fn update(&self, id: QuizId, draft: QuizDraft) -> io::Result<Option<Quiz>> {
+        let _writing = self.writing.lock().unwrap();
+
+        let version = match self.latest_version(id)? {
+            Some(version) => version + 1,
+            None => return Ok(None),
+        };
+
+        let quiz = Quiz::new(id, version, draft);
+        self.save(&quiz)?;
+
+        Ok(Some(quiz))
+    }
+
+    /// Deletes a quiz along with all of its versions.
+    ///
+    /// Returns `false` if the quiz doesn't exist.
+    pub fn delete(&self, id: QuizId) -> io::Result<bool> {
+        let _writing = self.writing.lock().unwrap();
+
+        match fs::remove_dir_all(self.quiz_dir(id)?) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn save(&self, quiz: &Quiz) -> io::Result<()> {
+        fs::create_dir_all(self.quiz_dir(quiz.id)?)?;
+
+        // Write to a temporary file first so a crash can't leave half a quiz
+        // behind
+        let path = self.version_path(quiz.id, quiz.version)?;
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_vec_pretty(quiz)?)?;
+        fs::rename(temporary, path)
+    }
+
+    fn latest_version(&self, id: QuizId) -> io::Result<Option<u32>> {
+        let entries = match fs::read_dir(self.quiz_dir(id)?) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let mut latest = None;
+        for entry in entries {
+            let path = entry?.path();
+            let version = version_of(&path);
+            latest = latest.max(version);
+        }
+
+        Ok(latest)
+    }
+
+    fn quiz_dir(&self, id: QuizId) -> io::Result<PathBuf> {
+        let dir = self
+            .dir
+            .as_ref()
+            .ok_or_else(|| io::Error::new(ErrorKind::Unsupported, "Quizzes can't be saved"))?;
+
+        Ok(dir.join(id.to_string()))
+    }
+
+    fn version_path(&self, id: QuizId, version: u32) -> io::Result<PathBuf> {
+        Ok(self.quiz_dir(id)?.join(format!("{version}.json")))
+    }
+}
+
+impl Quiz {
+    fn new(id: QuizId, version: u32, draft: QuizDraft) -> Self {
+        Self { id, version, title: draft.title, questions: draft.questions, updated_at: now_millis() }
+    }
+}
+
+/// Gets the version number out of a path like `quizzes/1234/2.json`.
+fn version_of(path: &Path) -> Option<u32> {
+    if path.extension()? != "json" {
+        return None;
+    }
+
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::api::Question;
+
+    fn draft(title: &str) -> QuizDraft {
+        QuizDraft {
+            title: String::from(title),
+            questions: vec![Question {
+                question: String::from("?"),
+                choices: vec![String::from("a"), String::from("b")],
+                answer: 1,
+                time: 10,
+                ..Question::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn keeps_every_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = QuizStore::new(dir.path());
+        assert!(store.list().unwrap().is_empty());
+
+        let quiz = store.create(draft("First")).unwrap();
+        assert_eq!(quiz.version, 1);
+
+        let updated = store.update(quiz.id, draft("Second")).unwrap().unwrap();
+        assert_eq!(updated.version, 2);
+
+        assert_eq!(store.get(quiz.id, None).unwrap().unwrap().title, "Second");
+        assert_eq!(store.get(quiz.id, Some(1)).unwrap().unwrap(), quiz);
+        assert!(store.get(quiz.id, Some(3)).unwrap().is_none());
+
+        let list = store.list().unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].version, 2);
+
+        assert!(store.delete(quiz.id).unwrap());
+        assert!(!store.delete(quiz.id).unwrap());
+        assert!(store.get(quiz.id, None).unwrap().is_none());
+        assert!(store.update(quiz.id, draft("Third")).unwrap().is_none());
+    }
+
+    #[test]
+    fn empty_stores_have_nothing() {
+        let store = QuizStore::empty();
+        assert!(store.list().unwrap().is_empty());
+        assert!(store.get(1, None).unwrap().is_none());
+        assert!(store.create(draft("First")).is_err());
+        assert!(store.delete(1).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! Writes a gradebook as a single table, with the per-question columns after
+//! each player's totals.
+
+use super::Gradebook;
+
+/// The columns every question gets.
+const QUESTION_COLUMNS: [&str; 4] = ["choice", "correct", "time", "points"];
+
+pub fn export(gradebook: &Gradebook) -> Result<Vec<u8>, String> {
+    let mut header: Vec<String> = ["Player", "Student ID", "Team", "Points", "Correct"].map(String::from).into();
+    for number in 1..=gradebook.questions.len() {
+        header.extend(QUESTION_COLUMNS.map(|column| format!("Q{number} {column}")));
+    }
+
+    let mut writer = ::csv::Writer::from_writer(Vec::new());
+    writer.write_record(&header).map_err(|e| e.to_string())?;
+
+    for row in &gradebook.rows {
+        let mut record = vec![
+            text(&row.name),
+            row.student_id.as_deref().map(text).unwrap_or_default(),
+            row.team.as_deref().map(text).unwrap_or_default(),
+            row.total_points.to_string(),
+            row.correct.to_string(),
+        ];
+
+        for response in &row.responses {
+            record.extend([
+                response.choice.as_deref().map(text).unwrap_or_default(),
+                // Spreadsheets read these as booleans
+                String::from(if response.correct { "TRUE" } else { "FALSE" }),
+                response.time.map(|time| format!("{time:.3}")).unwrap_or_default(),
+                response.points.to_string(),
+            ]);
+        }
+
+        writer.write_record(&record).map_err(|e| e.to_string())?;
+    }
+
+    writer.into_inner().map_err(|e| e.to_string())
+}
+
+/// Stops spreadsheets from running text that players or hosts typed as a
+/// formula, by starting it with a `'`.
+fn text(cell: &str) -> String {
+    if cell.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{cell}")
+    } else {
+        String::from(cell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::record;
+    use super::super::Options;
+    use super::*;
+
+    #[test]
+    fn writes_a_row_per_player() {
+        let gradebook = Gradebook::new(&record(), Options::default());
+        let csv = String::from_utf8(export(&gradebook).unwrap()).unwrap();
+
+        assert_eq!(
+            csv,
+            "Player,Student ID,Team,Points,Correct,Q1 choice,Q1 correct,Q1 time,Q1 points\n\
+             Carol,s3,,0,0,,FALSE,,0\n\
+             Bob,s2,,900,1,bar,TRUE,2.500,900\n\
+             Alice,,,0,0,foo,FALSE,1.500,0\n"
+        );
+    }
+
+    #[test]
+    fn escapes_formulas() {
+        let mut gradebook = Gradebook::new(&record(), Options::default());
+        gradebook.rows[1].name = String::from("=1+1");
+        gradebook.rows[1].team = Some(String::from("@Team"));
+        gradebook.rows[1].responses[0].choice = Some(String::from("-2"));
+        gradebook.rows[1].total_points = -100;
+        let csv = String::from_utf8(export(&gradebook).unwrap()).unwrap();
+
+        assert_eq!(csv.lines().nth(2), Some("'=1+1,s2,'@Team,-100,1,'-2,TRUE,2.500,900"));
+    }
+}
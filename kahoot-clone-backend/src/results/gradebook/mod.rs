@@ -0,0 +1,264 @@
+/// Plain CSV, one row per player.
+mod csv;
+/// Excel workbooks.
+mod xlsx;
+
+use super::GameRecord;
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// A format gradebooks can import.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Csv,
+    Xlsx,
+}
+
+impl Format {
+    /// The content type of the format, for HTTP responses.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// The order players are listed in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Order {
+    /// The order of the room's roster, with anyone who isn't on it after by
+    /// name. Games without a roster are listed by name.
+    #[default]
+    Roster,
+    /// Alphabetical order.
+    Name,
+    /// Most points first.
+    Points,
+}
+
+/// How to write a gradebook.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Options {
+    /// Replaces names with `Player 1`, `Player 2`, ... in the chosen order,
+    /// and leaves out student ids.
+    #[serde(default)]
+    pub anonymize: bool,
+    #[serde(default)]
+    pub order: Order,
+}
+
+/// A game's results, one row per player.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradebook {
+    /// The text of every question played, in order.
+    pub questions: Vec<String>,
+    pub rows: Vec<Row>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub name: String,
+    pub student_id: Option<String>,
+    pub team: Option<String>,
+    pub total_points: i64,
+    /// How many questions the player got right.
+    pub correct: usize,
+    /// What the player did in every round, in order.
+    pub responses: Vec<Response>,
+}
+
+/// What a player did in a round.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    /// The text of the player's final choice, if they answered.
+    pub choice: Option<String>,
+    pub correct: bool,
+    /// How long the player took to give their final choice, in seconds.
+    pub time: Option<f64>,
+    /// The points the player gained or lost.
+    pub points: i64,
+}
+
+impl Gradebook {
+    /// Lays out a game's results.
+    ///
+    /// Everyone on the roster gets a row, even if they never joined.
+    pub fn new(record: &GameRecord, options: Options) -> Self {
+        let roster = record.roster.as_deref().unwrap_or_default();
+        let players: HashMap<_, _> = record.players.iter().map(|player| (player.username.as_str(), player)).collect();
+
+        let mut names: Vec<&str> = roster.iter().map(|entry| entry.name.as_str()).collect();
+        let mut others: Vec<&str> = players.keys().copied().filter(|name| !names.contains(name)).collect();
+        others.sort_by_key(|name| name.to_lowercase());
+        names.extend(others);
+
+        let total_points = |name: &str| players.get(name).map(|player| player.total_points).unwrap_or(0);
+        match options.order {
+            Order::Roster => (),
+            Order::Name => names.sort_by_key(|name| name.to_lowercase()),
+            // Stable, so ties stay in roster order
+            Order::Points => names.sort_by_key(|name| -total_points(name)),
+        }
+
+        let rows = names
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let player = players.get(name);
+                let responses: Vec<_> = record
+                    .rounds
+                    .iter()
+                    .map(|round| {
+                        // Players can change their answer, and only the last one counts
+                        let answer = round.answers.iter().rev().find(|answer| answer.username == name);
+
+                        Response {
+                            choice: answer.and_then(|answer| round.question.choices.get(answer.choice).cloned()),
+                            correct: round.correct.iter().any(|correct| correct == name),
+                            time: answer.map(|answer| answer.time_taken as f64 / 1000.0),
+                            points: round.point_gains.get(name).copied().unwrap_or(0),
+                        }
+                    })
+                    .collect();
+
+                let student_id = player.and_then(|player| player.student_id.clone()).or_else(|| {
+                    roster
+                        .iter()
+                        .find(|entry| entry.name == name)
+                        .and_then(|entry| entry.student_id.clone())
+                });
+
+                Row {
+                    name: match options.anonymize {
+                        true => format!("Player {}", index + 1),
+                        false => String::from(name),
+                    },
+                    student_id: student_id.filter(|_| !options.anonymize),
+                    team: player.and_then(|player| player.team.clone()),
+                    total_points: total_points(name),
+                    correct: responses.iter().filter(|response| response.correct).count(),
+                    responses,
+                }
+            })
+            .collect();
+
+        Self { questions: record.rounds.iter().map(|round| round.question.question.clone()).collect(), rows }
+    }
+
+    /// Writes the gradebook in a format spreadsheets and gradebooks can open.
+    pub fn export(&self, format: Format) -> Result<Vec<u8>, String> {
+        match format {
+            Format::Csv => csv::export(self),
+            Format::Xlsx => xlsx::export(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{AnswerRecord, Outcome, PlayerRecord, RosterRecord, RoundRecord};
+    use crate::ws::api::Question;
+
+    fn player(username: &str, total_points: i64) -> PlayerRecord {
+        PlayerRecord { username: String::from(username), student_id: None, team: None, total_points }
+    }
+
+    fn answer(username: &str, choice: usize, time_taken: u64) -> AnswerRecord {
+        AnswerRecord { username: String::from(username), choice, answered_at: 0, time_taken }
+    }
+
+    /// A game where Bob gets the question right after changing his mind, and
+    /// Alice gets it wrong. Carol is on the roster but never joined.
+    pub fn record() -> GameRecord {
+        let question = Question {
+            question: String::from("Fish?"),
+            choices: vec![String::from("foo"), String::from("bar")],
+            answer: 1,
+            time: 30,
+            ..Question::default()
+        };
+
+        GameRecord {
+            id: 1,
+            room_id: 1,
+            quiz: None,
+            started_at: 0,
+            ended_at: 1,
+            outcome: Outcome::Finished,
+            seed: 0,
+            host_token: String::new(),
+            players: vec![
+                player("Alice", 0),
+                PlayerRecord { student_id: Some(String::from("s2")), ..player("Bob", 900) },
+            ],
+            roster: Some(vec![
+                RosterRecord { name: String::from("Carol"), student_id: Some(String::from("s3")) },
+                RosterRecord { name: String::from("Bob"), student_id: Some(String::from("s2")) },
+                RosterRecord { name: String::from("Alice"), student_id: None },
+            ]),
+            rounds: vec![RoundRecord {
+                question_id: String::from("1"),
+                question,
+                started_at: 0,
+                answers: vec![answer("Bob", 0, 1000), answer("Alice", 0, 1500), answer("Bob", 1, 2500)],
+                point_gains: HashMap::from([(String::from("Bob"), 900), (String::from("Alice"), 0)]),
+                correct: vec![String::from("Bob")],
+            }],
+        }
+    }
+
+    fn names(gradebook: &Gradebook) -> Vec<&str> {
+        gradebook.rows.iter().map(|row| row.name.as_str()).collect()
+    }
+
+    #[test]
+    fn lays_out_results() {
+        let gradebook = Gradebook::new(&record(), Options::default());
+        assert_eq!(gradebook.questions, ["Fish?"]);
+        assert_eq!(names(&gradebook), ["Carol", "Bob", "Alice"]);
+
+        let bob = &gradebook.rows[1];
+        assert_eq!((bob.student_id.as_deref(), bob.total_points, bob.correct), (Some("s2"), 900, 1));
+        assert_eq!(
+            bob.responses,
+            [Response { choice: Some(String::from("bar")), correct: true, time: Some(2.5), points: 900 }]
+        );
+
+        // Carol never answered, but keeps her student id
+        let carol = &gradebook.rows[0];
+        assert_eq!(carol.student_id.as_deref(), Some("s3"));
+        assert_eq!(carol.responses, [Response { choice: None, correct: false, time: None, points: 0 }]);
+    }
+
+    #[test]
+    fn orders_and_anonymizes() {
+        let by_name = Gradebook::new(&record(), Options { order: Order::Name, ..Options::default() });
+        assert_eq!(names(&by_name), ["Alice", "Bob", "Carol"]);
+
+        let by_points = Gradebook::new(&record(), Options { order: Order::Points, ..Options::default() });
+        assert_eq!(names(&by_points), ["Bob", "Carol", "Alice"]);
+
+        let anonymous = Gradebook::new(&record(), Options { anonymize: true, order: Order::Points });
+        assert_eq!(names(&anonymous), ["Player 1", "Player 2", "Player 3"]);
+        assert!(anonymous.rows.iter().all(|row| row.student_id.is_none()));
+        assert_eq!(anonymous.rows[0].total_points, 900);
+
+        // Without a roster, players are listed by name
+        let record = GameRecord { roster: None, ..record() };
+        assert_eq!(names(&Gradebook::new(&record, Options::default())), ["Alice", "Bob"]);
+    }
+}
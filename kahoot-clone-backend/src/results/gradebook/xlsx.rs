@@ -0,0 +1,142 @@
+//! Writes a gradebook as a workbook with three sheets:
+//!
+//! - `Totals`, with every player's points and correct answers
+//! - `Answers`, with what every player did in every round, four columns per
+//!   question under a heading with the question's number
+//! - `Questions`, with the text of every question
+
+use super::Gradebook;
+
+use rust_xlsxwriter::{Format as CellFormat, FormatAlign, Workbook, Worksheet, XlsxError};
+
+/// The columns at the start of `Totals` and `Answers` that say who a row is.
+const PLAYER_COLUMNS: [&str; 3] = ["Player", "Student ID", "Team"];
+/// The columns every question gets in `Answers`.
+const QUESTION_COLUMNS: [&str; 4] = ["Choice", "Correct", "Time", "Points"];
+
+pub fn export(gradebook: &Gradebook) -> Result<Vec<u8>, String> {
+    write(gradebook).map_err(|e| e.to_string())
+}
+
+fn write(gradebook: &Gradebook) -> Result<Vec<u8>, XlsxError> {
+    let bold = CellFormat::new().set_bold();
+    let heading = CellFormat::new().set_bold().set_align(FormatAlign::Center);
+    let seconds = CellFormat::new().set_num_format("0.000");
+
+    let mut workbook = Workbook::new();
+
+    let totals = workbook.add_worksheet().set_name("Totals")?;
+    write_header(totals, 0, &[&PLAYER_COLUMNS[..], &["Points", "Correct"]].concat(), &bold)?;
+    for (row, player) in (1..).zip(&gradebook.rows) {
+        write_player(totals, row, player)?;
+        totals.write_number(row, 3, player.total_points as f64)?;
+        totals.write_number(row, 4, player.correct as f64)?;
+    }
+    totals.set_freeze_panes(1, 1)?;
+    totals.autofit();
+
+    let answers = workbook.add_worksheet().set_name("Answers")?;
+    let mut header = PLAYER_COLUMNS.to_vec();
+    for (index, _) in gradebook.questions.iter().enumerate() {
+        let first = column(index, 0);
+        answers.merge_range(
+            0,
+            first,
+            0,
+            first + QUESTION_COLUMNS.len() as u16 - 1,
+            &format!("Q{}", index + 1),
+            &heading,
+        )?;
+        header.extend(QUESTION_COLUMNS);
+    }
+    write_header(answers, 1, &header, &bold)?;
+
+    for (row, player) in (2..).zip(&gradebook.rows) {
+        write_player(answers, row, player)?;
+
+        for (index, response) in player.responses.iter().enumerate() {
+            if let Some(choice) = &response.choice {
+                answers.write_string(row, column(index, 0), choice)?;
+            }
+            answers.write_boolean(row, column(index, 1), response.correct)?;
+            if let Some(time) = response.time {
+                answers.write_number_with_format(row, column(index, 2), time, &seconds)?;
+            }
+            answers.write_number(row, column(index, 3), response.points as f64)?;
+        }
+    }
+    answers.set_freeze_panes(2, 1)?;
+    answers.autofit();
+
+    let questions = workbook.add_worksheet().set_name("Questions")?;
+    write_header(questions, 0, &["Number", "Question"], &bold)?;
+    for (row, question) in (1..).zip(&gradebook.questions) {
+        questions.write_number(row, 0, row)?;
+        questions.write_string(row, 1, question)?;
+    }
+    questions.autofit();
+
+    workbook.save_to_buffer()
+}
+
+/// The column of the `offset`th cell of a question in `Answers`.
+fn column(question: usize, offset: usize) -> u16 {
+    (PLAYER_COLUMNS.len() + question * QUESTION_COLUMNS.len() + offset) as u16
+}
+
+fn write_header(sheet: &mut Worksheet, row: u32, header: &[&str], format: &CellFormat) -> Result<(), XlsxError> {
+    for (column, name) in (0..).zip(header) {
+        sheet.write_string_with_format(row, column, *name, format)?;
+    }
+
+    Ok(())
+}
+
+/// Fills in the columns that say who a row is.
+fn write_player(sheet: &mut Worksheet, row: u32, player: &super::Row) -> Result<(), XlsxError> {
+    sheet.write_string(row, 0, &player.name)?;
+    if let Some(student_id) = &player.student_id {
+        sheet.write_string(row, 1, student_id)?;
+    }
+    if let Some(team) = &player.team {
+        sheet.write_string(row, 2, team)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::record;
+    use super::super::Options;
+    use super::*;
+
+    use std::io::Cursor;
+
+    use calamine::{Data, Reader, Xlsx};
+
+    #[test]
+    fn writes_a_workbook() {
+        let gradebook = Gradebook::new(&record(), Options::default());
+        let bytes = export(&gradebook).unwrap();
+
+        let mut workbook: Xlsx<_> = Xlsx::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(workbook.sheet_names(), ["Totals", "Answers", "Questions"]);
+
+        let totals = workbook.worksheet_range("Totals").unwrap();
+        assert_eq!(totals.get((0, 3)), Some(&Data::String(String::from("Points"))));
+        assert_eq!(totals.get((2, 0)), Some(&Data::String(String::from("Bob"))));
+        assert_eq!(totals.get((2, 3)), Some(&Data::Float(900.0)));
+        assert_eq!(totals.get((2, 4)), Some(&Data::Float(1.0)));
+
+        let answers = workbook.worksheet_range("Answers").unwrap();
+        assert_eq!(answers.get((0, 3)), Some(&Data::String(String::from("Q1"))));
+        assert_eq!(answers.get((3, 3)), Some(&Data::String(String::from("bar"))));
+        assert_eq!(answers.get((3, 4)), Some(&Data::Bool(true)));
+        assert_eq!(answers.get((3, 5)), Some(&Data::Float(2.5)));
+        assert_eq!(answers.get((3, 6)), Some(&Data::Float(900.0)));
+
+        let questions = workbook.worksheet_range("Questions").unwrap();
+        assert_eq!(questions.get((1, 1)), Some(&Data::String(String::from("Fish?"))));
+    }
+}
@@ -0,0 +1,364 @@
+/// Contains the spreadsheets game results can be exported as.
+pub mod gradebook;
+/// Contains the places game results can be kept.
+pub mod store;
+
+use gradebook::Gradebook;
+use store::ResultStore;
+
+use crate::quiz::QuizId;
+use crate::ws::api::{Question, RoomId};
+use crate::ws::now_millis;
+use crate::ws::state::{Room, SharedState};
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Path, Query};
+use axum::http::{header, StatusCode};
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+
+use serde::{Deserialize, Serialize};
+
+pub type GameId = u32;
+
+/// Everything that happened in a game.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameRecord {
+    pub id: GameId,
+    pub room_id: RoomId,
+    /// The quiz the game was created from, if it came from the quiz library.
+    pub quiz: Option<QuizRef>,
+    /// When the first round began, in milliseconds since the Unix epoch.
+    pub started_at: u64,
+    /// When the game finished or was aborted, in milliseconds since the Unix
+    /// epoch.
+    pub ended_at: u64,
+    pub outcome: Outcome,
+    /// The seed everything random in the game came from.
+    pub seed: u64,
+    /// The token the room's host was given, which has to be shown to see the
+    /// game. It's never sent back by the api.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub host_token: String,
+    /// Everyone who played, by name.
+    pub players: Vec<PlayerRecord>,
+    /// The room's roster in roster order, for rooms with one, including
+    /// players who never joined.
+    pub roster: Option<Vec<RosterRecord>>,
+    /// The rounds played, in order.
+    pub rounds: Vec<RoundRecord>,
+}
+
+/// Which quiz from the quiz library a game played.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuizRef {
+    pub id: QuizId,
+    pub version: u32,
+    pub title: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Outcome {
+    /// Every round was played.
+    Finished,
+    /// The host left before the game was over.
+    Aborted,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerRecord {
+    pub username: String,
+    /// The player's student id, if they are on the roster with one.
+    pub student_id: Option<String>,
+    pub team: Option<String>,
+    pub total_points: i64,
+}
+
+/// A player on a room's roster, without their join code.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RosterRecord {
+    pub name: String,
+    pub student_id: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundRecord {
+    /// The id the question is known by in game results.
+    pub question_id: String,
+    pub question: Question,
+    /// When the round began, in milliseconds since the Unix epoch.
+    pub started_at: u64,
+    /// Every answer given, including answers that were changed later.
+    pub answers: Vec<AnswerRecord>,
+    /// The points each player gained or lost.
+    pub point_gains: HashMap<String, i64>,
+    /// The players whose final answer was correct, fastest first.
+    pub correct: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnswerRecord {
+    pub username: String,
+    /// The index of the choice in the question, however it was shown.
+    pub choice: usize,
+    /// When the answer came in, in milliseconds since the Unix epoch.
+    pub answered_at: u64,
+    /// How long the player took, in milliseconds.
+    pub time_taken: u64,
+}
+
+/// A game in the list of past games, without its rounds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameSummary {
+    pub id: GameId,
+    pub room_id: RoomId,
+    pub quiz: Option<QuizRef>,
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub outcome: Outcome,
+    pub players: usize,
+    pub rounds: usize,
+}
+
+impl GameRecord {
+    pub fn summary(&self) -> GameSummary {
+        GameSummary {
+            id: self.id,
+            room_id: self.room_id,
+            quiz: self.quiz.clone(),
+            started_at: self.started_at,
+            ended_at: self.ended_at,
+            outcome: self.outcome,
+            players: self.players.len(),
+            rounds: self.rounds.len(),
+        }
+    }
+}
+
+/// Writes down what happens in a game as it's played.
+///
+/// Recorders are kept in room snapshots, so games restored after a restart
+/// carry on with the same record.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameRecorder {
+    record: GameRecord,
+    /// Everyone who answered at some point, even if they left.
+    answered: HashSet<String>,
+}
+
+impl GameRecorder {
+    /// Starts recording a game, as its first round is about to begin.
+    pub fn new(room_id: RoomId, quiz: Option<QuizRef>, seed: u64, host_token: String) -> Self {
+        Self {
+            record: GameRecord {
+                id: rand::random(),
+                room_id,
+                quiz,
+                started_at: now_millis(),
+                ended_at: 0,
+                outcome: Outcome::Aborted,
+                seed,
+                host_token,
+                players: Vec::new(),
+                roster: None,
+                rounds: Vec::new(),
+            },
+            answered: HashSet::new(),
+        }
+    }
+
+    pub fn begin_round(&mut self, question_id: String, question: Question) {
+        self.record.rounds.push(RoundRecord {
+            question_id,
+            question,
+            started_at: now_millis(),
+            answers: Vec::new(),
+            point_gains: HashMap::new(),
+            correct: Vec::new(),
+        });
+    }
+
+    pub fn answer(&mut self, username: &str, choice: usize, time_taken: Duration) {
+        self.answered.insert(String::from(username));
+
+        if let Some(round) = self.record.rounds.last_mut() {
+            round.answers.push(AnswerRecord {
+                username: String::from(username),
+                choice,
+                answered_at: now_millis(),
+                time_taken: time_taken.as_millis() as u64,
+            });
+        }
+    }
+
+    pub fn end_round(&mut self, point_gains: &HashMap<String, i64>, correct: &[String]) {
+        if let Some(round) = self.record.rounds.last_mut() {
+            round.point_gains = point_gains.clone();
+            round.correct = correct.to_vec();
+        }
+    }
+
+    /// Finishes the record with everyone who played.
+    pub fn finish(mut self, outcome: Outcome, room: &Room, totals: &HashMap<String, i64>) -> GameRecord {
+        let mut names: HashSet<String> = room.users.names().into_iter().collect();
+        names.extend(self.answered);
+        names.extend(totals.keys().cloned());
+
+        let roster = room.roster.as_ref().map(|roster| roster.entries());
+
+        let mut players: Vec<_> = names
+            .into_iter()
+            .map(|username| PlayerRecord {
+                student_id: roster
+                    .and_then(|entries| entries.iter().find(|entry| entry.name == username))
+                    .and_then(|entry| entry.id.clone()),
+                team: room.teams.as_ref().and_then(|teams| teams.team_of(&username)),
+                total_points: totals.get(&username).copied().unwrap_or(0),
+                username,
+            })
+            .collect();
+        players.sort_by(|a, b| a.username.cmp(&b.username));
+
+        self.record.players = players;
+        self.record.roster = roster.map(|entries| {
+            entries
+                .iter()
+                .map(|entry| RosterRecord { name: entry.name.clone(), student_id: entry.id.clone() })
+                .collect()
+        });
+        self.record.outcome = outcome;
+        self.record.ended_at = now_millis();
+
+        self.record
+    }
+}
+
+/// Keeps a game's record, logging instead of failing if it can't.
+pub async fn save(state: &SharedState, record: GameRecord) {
+    let id = record.id;
+
+    match on_store(state, move |results| results.save(&record)).await {
+        Ok(()) => tracing::debug!("Saved the results of game `{id}`"),
+        Err(e) => tracing::error!("Couldn't save the results of game `{id}`: {e}"),
+    }
+}
+
+/// Uses the results store on a blocking thread, as stores can be slow to get
+/// to (eg. files on disk).
+async fn on_store<T: Send + 'static>(
+    state: &SharedState,
+    f: impl FnOnce(&dyn ResultStore) -> io::Result<T> + Send + 'static,
+) -> io::Result<T> {
+    let state = Arc::clone(state);
+
+    tokio::task::spawn_blocking(move || f(state.results.as_ref()))
+        .await
+        .unwrap_or_else(|e| Err(io::Error::other(e)))
+}
+
+/// An error response with a reason in the body.
+type ApiError = (StatusCode, String);
+
+/// Game history api router.
+///
+/// Anyone can list past games, but only the host of a game can see or export
+/// it, by passing the room's host token as `?token=`.
+///
+/// The shared state has to be included by whoever uses this router.
+pub fn router() -> Router {
+    Router::new()
+        // GET /
+        .route("/", get(list_games))
+        // GET /:id
+        .route("/:id", get(get_game))
+        // GET /:id/export/:format
+        .route("/:id/export/:format", get(export_game))
+}
+
+/// Logs a storage error, without telling clients about the server's files.
+fn storage_error(e: io::Error) -> ApiError {
+    tracing::error!("Results storage failed: {e}");
+
+    (StatusCode::INTERNAL_SERVER_ERROR, String::from("Results storage failed"))
+}
+
+fn not_found() -> ApiError {
+    (StatusCode::NOT_FOUND, String::from("Game does not exist"))
+}
+
+/// Proof that the client hosted a game.
+#[derive(Deserialize)]
+struct Access {
+    /// The host token the game's room was created with.
+    #[serde(default)]
+    token: String,
+}
+
+/// Finds a game, if the client hosted it.
+async fn hosted_game(state: &SharedState, id: GameId, access: Access) -> Result<GameRecord, ApiError> {
+    let mut record = on_store(state, move |results| results.get(id))
+        .await
+        .map_err(storage_error)?
+        .ok_or_else(not_found)?;
+
+    // Games saved without a token can't be shown to anyone
+    if record.host_token.is_empty() || record.host_token != access.token {
+        return Err((StatusCode::FORBIDDEN, String::from("Only the game's host can see it")));
+    }
+    record.host_token.clear();
+
+    Ok(record)
+}
+
+/// Returns every past game, newest first.
+async fn list_games(Extension(state): Extension<SharedState>) -> Result<Json<Vec<GameSummary>>, ApiError> {
+    on_store(&state, |results| results.list())
+        .await
+        .map(Json)
+        .map_err(storage_error)
+}
+
+/// Returns everything that happened in a game.
+async fn get_game(
+    Path(id): Path<GameId>,
+    Query(access): Query<Access>,
+    Extension(state): Extension<SharedState>,
+) -> Result<Json<GameRecord>, ApiError> {
+    hosted_game(&state, id, access).await.map(Json)
+}
+
+/// The exported game, with the format's content type and a file name.
+type Exported = ([(header::HeaderName, String); 2], Vec<u8>);
+
+/// Converts a game's results into a spreadsheet for gradebooks.
+async fn export_game(
+    Path((id, format)): Path<(GameId, gradebook::Format)>,
+    Query(options): Query<gradebook::Options>,
+    Query(access): Query<Access>,
+    Extension(state): Extension<SharedState>,
+) -> Result<Exported, ApiError> {
+    let record = hosted_game(&state, id, access).await?;
+
+    let exported = Gradebook::new(&record, options).export(format).map_err(|e| {
+        tracing::error!("Couldn't export game `{id}`: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, String::from("Couldn't export the game"))
+    })?;
+
+    let headers = [
+        (header::CONTENT_TYPE, String::from(format.content_type())),
+        (header::CONTENT_DISPOSITION, format!("attachment; filename=\"game-{id}.{}\"", format.extension())),
+    ];
+
+    Ok((headers, exported))
+}
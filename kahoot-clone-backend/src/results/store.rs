@@ -0,0 +1,193 @@
+use super::{GameId, GameRecord, GameSummary};
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The directory used when `KAHOOT_RESULTS_DIR` isn't set.
+pub const DEFAULT_RESULTS_DIR: &str = "results";
+
+/// Somewhere to keep the records of past games.
+///
+/// Any type implementing this can be used as the server's results store, so
+/// results could just as well go in a database.
+pub trait ResultStore: Send + Sync {
+    fn save(&self, record: &GameRecord) -> io::Result<()>;
+
+    /// Lists every game, newest first.
+    fn list(&self) -> io::Result<Vec<GameSummary>>;
+
+    fn get(&self, id: GameId) -> io::Result<Option<GameRecord>>;
+}
+
+/// Keeps every game as a JSON file in a directory, named after its id.
+///
+/// Each game also gets a much smaller `{id}.summary.json` next to it, so
+/// listing games doesn't mean reading every round of every game.
+pub struct DirectoryResults {
+    dir: PathBuf,
+}
+
+impl DirectoryResults {
+    /// Creates a store backed by the given directory.
+    ///
+    /// The directory is created when the first game is saved.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Uses the directory at `KAHOOT_RESULTS_DIR`, or `DEFAULT_RESULTS_DIR` if
+    /// it isn't set.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("KAHOOT_RESULTS_DIR").unwrap_or_else(|_| DEFAULT_RESULTS_DIR.into()))
+    }
+
+    fn path(&self, id: GameId) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    fn summary_path(&self, id: GameId) -> PathBuf {
+        self.dir.join(format!("{id}.summary.json"))
+    }
+
+    /// Reads a game's summary, making it from the full record if it's missing
+    /// (eg. the game was saved before summaries were kept).
+    fn summary(&self, id: GameId) -> io::Result<Option<GameSummary>> {
+        match fs::read(self.summary_path(id)) {
+            Ok(bytes) => return Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let Some(summary) = self.get(id)?.map(|record| record.summary()) else {
+            return Ok(None);
+        };
+        write_atomically(&self.summary_path(id), &serde_json::to_vec(&summary)?)?;
+
+        Ok(Some(summary))
+    }
+}
+
+/// Writes to a temporary file first so a crash can't leave half a file behind.
+fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, bytes)?;
+    fs::rename(temporary, path)
+}
+
+impl ResultStore for DirectoryResults {
+    fn save(&self, record: &GameRecord) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        // The record goes first, as a summary without one is never listed
+        write_atomically(&self.path(record.id), &serde_json::to_vec(record)?)?;
+        write_atomically(&self.summary_path(record.id), &serde_json::to_vec(&record.summary())?)
+    }
+
+    fn list(&self) -> io::Result<Vec<GameSummary>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            // Nothing has been saved yet
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut summaries = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let id = path
+                .extension()
+                .filter(|extension| *extension == "json")
+                .and_then(|_| path.file_stem()?.to_str()?.parse().ok());
+
+            if let Some(summary) = id.map(|id| self.summary(id)).transpose()?.flatten() {
+                summaries.push(summary);
+            }
+        }
+        summaries.sort_by_key(|summary| Reverse(summary.started_at));
+
+        Ok(summaries)
+    }
+
+    fn get(&self, id: GameId) -> io::Result<Option<GameRecord>> {
+        match fs::read(self.path(id)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Keeps games in memory, so they're lost when the server stops.
+#[derive(Default)]
+pub struct MemoryResults {
+    games: Mutex<HashMap<GameId, GameRecord>>,
+}
+
+impl ResultStore for MemoryResults {
+    fn save(&self, record: &GameRecord) -> io::Result<()> {
+        self.games.lock().unwrap().insert(record.id, record.clone());
+        Ok(())
+    }
+
+    fn list(&self) -> io::Result<Vec<GameSummary>> {
+        let mut summaries: Vec<_> = self.games.lock().unwrap().values().map(GameRecord::summary).collect();
+        summaries.sort_by_key(|summary| Reverse(summary.started_at));
+
+        Ok(summaries)
+    }
+
+    fn get(&self, id: GameId) -> io::Result<Option<GameRecord>> {
+        Ok(self.games.lock().unwrap().get(&id).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::Outcome;
+
+    fn record(id: GameId, started_at: u64) -> GameRecord {
+        GameRecord {
+            id,
+            room_id: 1,
+            quiz: None,
+            started_at,
+            ended_at: started_at + 1,
+            outcome: Outcome::Finished,
+            seed: 0,
+            host_token: String::new(),
+            players: Vec::new(),
+            roster: None,
+            rounds: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn keeps_games_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DirectoryResults::new(dir.path());
+        assert!(store.list().unwrap().is_empty());
+
+        store.save(&record(1, 10)).unwrap();
+        store.save(&record(2, 20)).unwrap();
+
+        let ids: Vec<_> = store.list().unwrap().iter().map(|summary| summary.id).collect();
+        assert_eq!(ids, [2, 1]);
+        assert_eq!(store.get(1).unwrap(), Some(record(1, 10)));
+        assert_eq!(store.get(3).unwrap(), None);
+
+        // Listing only reads the summaries
+        fs::write(dir.path().join("2.json"), "not a record").unwrap();
+        assert_eq!(store.list().unwrap(), [record(2, 20).summary(), record(1, 10).summary()]);
+
+        // Games saved without a summary get one when they're listed
+        fs::remove_file(dir.path().join("1.summary.json")).unwrap();
+        assert_eq!(store.list().unwrap()[1], record(1, 10).summary());
+        assert!(dir.path().join("1.summary.json").exists());
+    }
+}
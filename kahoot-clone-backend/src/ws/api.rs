@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use axum::extract::ws::Message;
 // `serde` is a library used for serializing and deserializing Rust types into
@@ -7,6 +7,8 @@ use axum::extract::ws::Message;
 // Relevant: https://serde.rs/
 use serde::{Deserialize, Serialize};
 
+use crate::quiz::QuizId;
+
 /// Messages sent by the client to "do" something.
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 // `tag = "type"`:
@@ -22,16 +24,101 @@ use serde::{Deserialize, Serialize};
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum Action {
     // Initial message
-    CreateRoom { questions: Vec<Question> },
+    CreateRoom {
+        questions: Vec<Question>,
+        // Lets older clients leave out the settings
+        #[serde(default)]
+        settings: RoomSettings,
+    },
+    /// Creates a room that plays a quiz from the quiz library.
+    #[serde(rename_all = "camelCase")]
+    CreateRoomFromQuiz {
+        quiz_id: QuizId,
+        /// The version of the quiz to play, or the latest one if left out.
+        #[serde(default)]
+        version: Option<u32>,
+        #[serde(default)]
+        settings: RoomSettings,
+    },
     #[serde(rename_all = "camelCase")] // Renames fields as camelCase
-    JoinRoom { room_id: RoomId, username: String },
+    JoinRoom {
+        room_id: RoomId,
+        username: String,
+        /// The user's join code, for rooms with a roster.
+        #[serde(default)]
+        code: Option<String>,
+        /// The team the user picked, for team games where players pick.
+        #[serde(default)]
+        team: Option<String>,
+    },
+    /// Creates a self-paced challenge that stays open until the deadline.
+    ///
+    /// Results are kept for a week after the challenge closes.
+    CreateChallenge {
+        questions: Vec<Question>,
+        /// When the challenge closes, in milliseconds since the Unix epoch, at
+        /// most 30 days away.
+        deadline: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    JoinChallenge {
+        challenge_id: ChallengeId,
+        username: String,
+    },
+    /// Joins a room again as the player a token was given to, after losing
+    /// the connection or a server restart.
+    #[serde(rename_all = "camelCase")]
+    RejoinRoom {
+        room_id: RoomId,
+        token: String,
+    },
+    /// Takes back a room restored after a server restart, as its host.
+    #[serde(rename_all = "camelCase")]
+    ResumeRoom {
+        room_id: RoomId,
+        token: String,
+    },
 
     // Player only
-    Answer { choice: usize },
+    Answer {
+        choice: usize,
+    },
+    /// Bets some of the player's points during a wager round.
+    ///
+    /// Bets higher than the player's total are lowered to their total.
+    PlaceWager {
+        amount: u32,
+    },
+    /// Asks for a different friendly name while in the lobby.
+    RerollName,
+    /// Asks for the next question of a challenge.
+    NextQuestion,
 
     // Host only
     BeginRound,
     EndRound,
+    /// Lets a user waiting for approval into the room.
+    ApproveJoin {
+        username: String,
+    },
+    /// Turns away a user waiting for approval.
+    DenyJoin {
+        username: String,
+    },
+    /// Lets every user waiting for approval into the room.
+    ApproveAll,
+    /// Puts a user on a team.
+    AssignTeam {
+        username: String,
+        team: String,
+    },
+    /// Gives a user more time to answer, eg. `1.5` for 50% extra time.
+    ///
+    /// Takes effect from the next round.
+    SetTimeMultiplier {
+        username: String,
+        multiplier: f64,
+    },
 }
 
 /// Messages sent by the server to the room host.
@@ -42,27 +129,99 @@ pub enum HostEvent {
     #[serde(rename_all = "camelCase")]
     RoomCreated {
         room_id: RoomId,
+        /// Lets the host take the room back with a resume room action if the
+        /// server restarts.
+        token: String,
+    },
+    /// Sent after the host takes back a room restored after a server restart.
+    ///
+    /// If a round was going, it picks up where it left off with a wager begin
+    /// or round begin message.
+    #[serde(rename_all = "camelCase")]
+    RoomResumed {
+        room_id: RoomId,
+        /// The players who have rejoined so far.
+        players: Vec<String>,
+        /// The total points of every player who answered at least once.
+        totals: HashMap<String, i64>,
+        /// How many rounds were over when the server stopped.
+        rounds_played: usize,
     },
+    /// Sent after the client sends a create challenge message.
+    ///
+    /// The websocket connection will close after this message is sent, since
+    /// challenges don't need a host.
+    #[serde(rename_all = "camelCase")]
+    ChallengeCreated { challenge_id: ChallengeId },
+    /// Sent if a room or challenge couldn't be created.
+    ///
+    /// The websocket connection will close after this message is sent.
+    CreateFailed { reason: String },
+    /// Sent if a room couldn't be resumed.
+    ///
+    /// The websocket connection will close after this message is sent.
+    ResumeFailed { reason: String },
 
     /// Sent whenever a user joins the room.
-    UserJoined {
-        username: String,
-    },
+    UserJoined { username: String },
+    /// Sent when a user is waiting for the host to let them in.
+    ///
+    /// The host should reply with an approve join or deny join action.
+    JoinRequested { username: String, reason: JoinRequestReason },
+    /// Sent whenever a user gets a new friendly name.
+    #[serde(rename_all = "camelCase")]
+    UserRenamed { old_username: String, username: String },
     /// Sent whenever a user leaves the room.
-    UserLeft {
-        username: String,
+    UserLeft { username: String },
+    /// Sent whenever a user is put on a team.
+    TeamAssigned { username: String, team: String },
+    /// Sent after a user joins or leaves a room with a roster.
+    RosterUpdate {
+        /// The roster names that aren't in the room.
+        missing: Vec<String>,
+    },
+    /// Sent after the room is created if the game is scheduled.
+    #[serde(rename_all = "camelCase")]
+    Countdown {
+        /// Milliseconds until the game starts.
+        starts_in: u64,
     },
+    /// Sent when a scheduled game is due to start, but there aren't enough
+    /// players yet.
+    ///
+    /// The host can begin the round anyway, or keep waiting (rooms that
+    /// advance by themselves start once enough players join).
+    #[serde(rename_all = "camelCase")]
+    StartDelayed { players: usize, min_players: usize },
     /// Sent whenever a user answers a question.
     ///
     /// Duplicate answers are automatically handled by the server, so the host
     /// does not need to deal with it.
-    UserAnswered {
-        username: String,
-    },
+    UserAnswered { username: String },
+    /// Sent when a user is knocked out of an elimination game.
+    UserEliminated { username: String },
+    /// Sent once every member of a team in the room has answered.
+    TeamAnswered { team: String },
+
+    /// Sent before the round begins when the question is a wager round.
+    ///
+    /// Players have `time` seconds to bet, and the host can stop the betting
+    /// early with an end round action.
+    WagerBegin { time: u16 },
+    /// Sent whenever a user places a wager.
+    WagerPlaced { username: String },
 
     /// Sent when a new round begins.
+    #[serde(rename_all = "camelCase")]
     RoundBegin {
         question: Question,
+        /// Why the question was picked, for adaptive games.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pick: Option<QuestionPick>,
+        /// The seconds left to answer, for rounds picked back up after a
+        /// server restart.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time_left: Option<u16>,
     },
     /// Sent when the round ends.
     ///
@@ -72,9 +231,42 @@ pub enum HostEvent {
     RoundEnd {
         /// The amount of points each player gains.
         ///
-        /// If they aren't in the object, they got the question wrong or
-        /// didn't answer.
-        point_gains: HashMap<String, u32>,
+        /// Points are negative for players who lost points (from negative
+        /// marking or a lost wager). If they aren't in the object, their
+        /// total didn't change.
+        point_gains: HashMap<String, i64>,
+        /// The team rankings so far, for team games.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        team_standings: Option<Vec<TeamStanding>>,
+        /// How many players are still in, for elimination games.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        survivors: Option<usize>,
+        /// What each player bet and whether they won it, for wager rounds.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        wagers: Option<Vec<WagerOutcome>>,
+    },
+    /// Sent once there are no more questions, right before the game end
+    /// message.
+    #[serde(rename_all = "camelCase")]
+    GameResults {
+        /// The total points of every player who took part.
+        totals: HashMap<String, i64>,
+        /// The roster names that never joined the room.
+        absent: Vec<String>,
+        /// The final team rankings, for team games.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        teams: Option<Vec<TeamStanding>>,
+        /// The players who were never knocked out, for elimination games.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        survivors: Option<Vec<String>>,
+        /// The seed used to shuffle the game or draw its questions, so it can
+        /// be played the same way again.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seed: Option<u64>,
+        /// The ids of the questions drawn from the pool, in the order they
+        /// were played.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        question_ids: Option<Vec<String>>,
     },
     /// Sent if there are no more questions.
     ///
@@ -87,27 +279,334 @@ pub enum HostEvent {
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum UserEvent {
     /// Sent when the user successfully joins.
-    Joined,
+    ///
+    /// The username is the name the server settled on, which may be cleaned up
+    /// from the one the user typed. In rooms, the token lets the user rejoin
+    /// as the same player with a rejoin room action.
+    Joined {
+        username: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
     /// Sent when the user couldn't join.
-    JoinFailed { reason: String },
+    ///
+    /// The code can be used by the client to tell failures apart, and the
+    /// reason is a human-readable explanation.
+    JoinFailed { code: JoinFailCode, reason: String },
+    /// Sent when the user is given a new friendly name after asking for one.
+    #[serde(rename_all = "camelCase")]
+    Renamed { username: String, rerolls_left: u8 },
+    /// Sent when the user is put on a team.
+    TeamAssigned { team: String },
+    /// Sent after joining the lobby of a scheduled game.
+    #[serde(rename_all = "camelCase")]
+    Countdown {
+        /// Milliseconds until the game is due to start.
+        starts_in: u64,
+    },
+
+    /// Sent before the round begins when the question is a wager round.
+    ///
+    /// The user has `time` seconds to bet up to their total points.
+    #[serde(rename_all = "camelCase")]
+    WagerBegin { total_points: i64, time: u16 },
+    /// Sent at the end of a wager round to users who placed a wager.
+    #[serde(rename_all = "camelCase")]
+    WagerResult { wager: u32, correct: bool, total_points: i64 },
 
     /// Sent when a new round begins.
     ///
     /// The user is only sent information about how many choices there are.
+    /// The user has `time` seconds to answer, which includes any extra time
+    /// they were given.
     #[serde(rename_all = "camelCase")]
-    RoundBegin { choices: Vec<String> },
+    RoundBegin { choices: Vec<String>, time: u16 },
 
     /// Sent when the round ends.
     ///
-    /// The point gain field is a `number` if the player answered correctly,
-    /// otherwise it is `null`.
+    /// The point gain field is a `number` if the player's points changed
+    /// (negative if they lost points), otherwise it is `null`.
+    #[serde(rename_all = "camelCase")]
+    RoundEnd { point_gain: Option<i64> },
+
+    /// Sent after the round end message when the user is knocked out of an
+    /// elimination game.
+    ///
+    /// The user stays in the room as a spectator, and their answers are
+    /// ignored from now on.
+    Eliminated,
+
+    /// Sent when the user asks for the next question of a challenge.
+    ///
+    /// The user has `time` seconds to answer, starting from when this message
+    /// is sent.
+    ChallengeQuestion {
+        /// The 0-indexed position of the question.
+        index: usize,
+        /// The number of questions in the challenge.
+        count: usize,
+        question: String,
+        choices: Vec<String>,
+        time: u16,
+    },
+    /// Sent after the user answers a challenge question or runs out of time.
     #[serde(rename_all = "camelCase")]
-    RoundEnd { point_gain: Option<u32> },
+    AnswerResult { correct: bool, points: u32, total_points: u32 },
 
     /// Sent when the game is over.
     GameEnd,
 }
 
+/// The reasons a user can fail to join a room.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JoinFailCode {
+    /// There is no room with the given id.
+    RoomNotFound,
+    /// Someone in the room already has the same name (ignoring case).
+    DuplicateUser,
+    /// Someone in the room has a name that looks the same.
+    ConfusableUser,
+    /// The name is blank after removing whitespace and invisible characters.
+    UsernameEmpty,
+    /// The name is longer than the server allows.
+    UsernameTooLong,
+    /// The name was caught by the name filter.
+    NameNotAllowed,
+    /// The host didn't let the user in.
+    JoinDenied,
+    /// The room has a roster and the user isn't on it.
+    NotOnRoster,
+    /// The join code doesn't match the roster entry's.
+    WrongJoinCode,
+    /// The room has teams to pick from and the user didn't pick one of them.
+    InvalidTeam,
+    /// The challenge's deadline has passed.
+    ChallengeClosed,
+    /// The rejoin token wasn't given out in the room.
+    UnknownToken,
+}
+
+impl JoinFailCode {
+    /// A human-readable description of the failure.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            JoinFailCode::RoomNotFound => "Room does not exist",
+            JoinFailCode::DuplicateUser => "Duplicate user",
+            JoinFailCode::ConfusableUser => "Username looks too similar to another user",
+            JoinFailCode::UsernameEmpty => "Username is empty",
+            JoinFailCode::UsernameTooLong => "Username is too long",
+            JoinFailCode::NameNotAllowed => "Username is not allowed",
+            JoinFailCode::JoinDenied => "The host denied your request to join",
+            JoinFailCode::NotOnRoster => "You are not on this room's roster",
+            JoinFailCode::WrongJoinCode => "Wrong join code",
+            JoinFailCode::InvalidTeam => "Pick one of the room's teams",
+            JoinFailCode::ChallengeClosed => "The challenge is closed",
+            JoinFailCode::UnknownToken => "Unknown rejoin token",
+        }
+    }
+}
+
+impl UserEvent {
+    /// Creates a join failure event from its code.
+    pub fn join_failed(code: JoinFailCode) -> Self {
+        UserEvent::JoinFailed { code, reason: String::from(code.reason()) }
+    }
+}
+
+/// Why a user needs the host's approval to join.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JoinRequestReason {
+    /// The username was caught by the name filter.
+    NameFiltered,
+    /// The room has a waiting room.
+    WaitingRoom,
+}
+
+/// Options the host can set when creating a room.
+///
+/// Every field has a default, so only the options that differ need to be sent.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RoomSettings {
+    /// What to do when a username is caught by the name filter.
+    pub name_filter: FilterAction,
+    /// Ignore typed usernames and give everyone a random friendly name.
+    pub friendly_names: bool,
+    /// How many times each player may ask for a different friendly name.
+    pub name_rerolls: u8,
+    /// Put every new user in a waiting room until the host lets them in.
+    pub waiting_room: bool,
+    /// Only let these players in, each at most once.
+    pub roster: Option<Vec<RosterEntry>>,
+    /// Play in teams instead of individually.
+    pub teams: Option<TeamSettings>,
+    /// Knock out players who answer wrong or run out of time.
+    ///
+    /// The game ends early once there is only one player left, or none if
+    /// everyone left is knocked out in the same round. Players who join after
+    /// the game starts can only watch.
+    pub elimination: bool,
+    /// The points taken away for a wrong answer (before the question's
+    /// multiplier). Totals can go below zero.
+    pub negative_marking: u32,
+    /// Run the game without the host clicking through rounds.
+    ///
+    /// The host can only start the game early, and the room stays open if the
+    /// host leaves.
+    pub auto_advance: Option<AutoAdvance>,
+    /// Start the game at this time (in milliseconds since the Unix epoch).
+    ///
+    /// Players can join beforehand and are told how long is left. If there
+    /// aren't enough players by then, the host decides whether to start.
+    pub starts_at: Option<u64>,
+    /// How many players a scheduled game needs to start by itself (at least
+    /// one).
+    ///
+    /// Rooms that advance by themselves wait for the larger of this and
+    /// their own minimum.
+    pub min_players: usize,
+    /// Let players change their answer until the round ends.
+    ///
+    /// Only the final answer is scored, and rounds don't end early when
+    /// everyone has answered.
+    pub answer_changes: Option<AnswerChanges>,
+    /// Play the questions in a random order.
+    ///
+    /// Questions drawn from a pool are always in a random order.
+    pub shuffle_questions: bool,
+    /// Show each player the choices in their own random order.
+    pub shuffle_choices: bool,
+    /// The seed for shuffling and drawing questions, to play a game the same
+    /// way as before.
+    ///
+    /// A random seed is picked if there isn't one.
+    pub seed: Option<u64>,
+    /// Treat the questions as a pool, and only play some of them.
+    pub selection: Option<Selection>,
+    /// Treat the questions as a pool, and pick harder or easier questions
+    /// depending on how many players got the last one right.
+    ///
+    /// Can't be used with a selection.
+    pub adaptive: Option<Adaptive>,
+}
+
+/// Settings for rooms where players can change their answer.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AnswerChanges {
+    /// Which answer's time is used to rank correct answers by speed.
+    pub speed_from: AnswerTiming,
+}
+
+/// Which of a player's answers counts for speed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AnswerTiming {
+    /// When the player first answered.
+    First,
+    /// When the player last changed their answer.
+    #[default]
+    Last,
+}
+
+/// Settings for rooms that run by themselves.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AutoAdvance {
+    /// The number of seconds between the end of a round and the next one.
+    pub pause: u16,
+    /// Start the game once this many players are in the room, or at the
+    /// scheduled time if there is one.
+    pub min_players: usize,
+}
+
+impl Default for AutoAdvance {
+    fn default() -> Self {
+        Self { pause: 5, min_players: 1 }
+    }
+}
+
+/// Settings for team games.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamSettings {
+    /// The names of the teams.
+    pub names: Vec<String>,
+    /// How players end up on a team.
+    #[serde(default)]
+    pub assignment: TeamAssignment,
+    /// How the members' points are combined into the team's score.
+    #[serde(default)]
+    pub scoring: TeamScoring,
+}
+
+/// How players end up on a team.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TeamAssignment {
+    /// The host puts each player on a team.
+    Host,
+    /// Players pick a team when joining.
+    Pick,
+    /// The server puts players on the smallest team.
+    #[default]
+    Auto,
+}
+
+/// How the members' points are combined into the team's score.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TeamScoring {
+    /// Add up every member's points.
+    #[default]
+    Sum,
+    /// The average of the members' points.
+    Average,
+    /// The points of the best member.
+    Best,
+}
+
+/// A team's place in the rankings.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TeamStanding {
+    pub team: String,
+    pub score: i64,
+    pub members: Vec<String>,
+}
+
+/// A player who is allowed into a room with a roster.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RosterEntry {
+    /// The name the player is shown as, cleaned up like a typed name when
+    /// the room is created.
+    pub name: String,
+    /// A student id the player can join with instead of their name.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// A code the player has to give to join.
+    #[serde(default)]
+    pub code: Option<String>,
+    /// How much longer than usual the player gets to answer, eg. `1.5` for
+    /// 50% extra time.
+    #[serde(default)]
+    pub time_multiplier: Option<f64>,
+}
+
+/// What to do with a username caught by the name filter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FilterAction {
+    /// Don't let the user join.
+    #[default]
+    Reject,
+    /// Let the user join under a placeholder name.
+    Rename,
+    /// Ask the host whether to let the user join.
+    Approve,
+}
+
 /// A type alias representing a room's id.
 //
 // Type aliases are useful for reducing duplication and for improving clarity.
@@ -115,6 +614,45 @@ pub enum UserEvent {
 // Relevant: https://doc.rust-lang.org/reference/items/type-aliases.html
 pub type RoomId = u32;
 
+/// A type alias representing a challenge's id.
+pub type ChallengeId = u32;
+
+/// Everything recorded about a challenge, as returned by
+/// `GET /challenges/:id`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChallengeResults {
+    pub challenge_id: ChallengeId,
+    /// When the challenge closes, in milliseconds since the Unix epoch.
+    pub deadline: u64,
+    pub questions: Vec<Question>,
+    pub players: Vec<ChallengePlayerResult>,
+}
+
+/// A player's progress through a challenge.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChallengePlayerResult {
+    pub username: String,
+    pub total_points: u32,
+    /// Whether the player got through every question.
+    pub finished: bool,
+    /// The player's answers, in question order.
+    pub answers: Vec<ChallengeAnswer>,
+}
+
+/// A player's answer to a challenge question.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChallengeAnswer {
+    /// The choice picked, or `null` if the player ran out of time.
+    pub choice: Option<usize>,
+    pub correct: bool,
+    /// How long the player took to answer, in milliseconds.
+    pub time_taken: u64,
+    pub points: u32,
+}
+
 /// A structure containing all relevant information of a question.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Question {
@@ -125,6 +663,155 @@ pub struct Question {
     pub answer: usize,
     /// The maximum number of seconds for this question.
     pub time: u16,
+    /// The points for the fastest correct answer, before the multiplier.
+    #[serde(default = "default_points")]
+    pub points: u32,
+    /// Multiplies the points won (or lost to negative marking), eg. `2` for a
+    /// double points question or `0` for a question worth nothing.
+    #[serde(default = "default_multiplier")]
+    pub multiplier: u32,
+    /// Whether players bet some of their points before the question is shown.
+    ///
+    /// Players win their bet if they get it right and lose it otherwise,
+    /// instead of getting points for speed. Challenges ignore this.
+    #[serde(default)]
+    pub wager: bool,
+    /// An id for the question, to tell which questions were played.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Tags used to pick questions from a pool.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<Difficulty>,
+}
+
+/// How hard a question is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    /// Every difficulty, from easiest to hardest.
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    /// The next difficulty up, if there is one.
+    pub fn harder(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Medium,
+            _ => Difficulty::Hard,
+        }
+    }
+
+    /// The next difficulty down, if there is one.
+    pub fn easier(self) -> Self {
+        match self {
+            Difficulty::Hard => Difficulty::Medium,
+            _ => Difficulty::Easy,
+        }
+    }
+
+    /// How many steps apart two difficulties are.
+    pub fn distance(self, other: Self) -> usize {
+        (self as usize).abs_diff(other as usize)
+    }
+}
+
+/// Settings for games that get harder or easier depending on how the
+/// players do.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Adaptive {
+    /// How many questions to play.
+    pub count: usize,
+    /// The difficulty of the first question.
+    pub start: Difficulty,
+    /// Play a harder question next if at least this share of players got
+    /// the last one right.
+    pub harder_above: f64,
+    /// Play an easier question next if less than this share of players got
+    /// the last one right.
+    pub easier_below: f64,
+}
+
+impl Default for Adaptive {
+    fn default() -> Self {
+        Self { count: 10, start: Difficulty::Medium, harder_above: 0.75, easier_below: 0.4 }
+    }
+}
+
+/// Why a question was picked in an adaptive game.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestionPick {
+    pub difficulty: Difficulty,
+    /// The share of players who got the last question right.
+    pub correct_rate: Option<f64>,
+    /// A human-readable explanation.
+    pub reason: String,
+}
+
+/// Which questions to play from a pool.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Selection {
+    /// How many questions to play.
+    pub count: usize,
+    /// Only play questions with at least one of these tags, or any question
+    /// if there are none.
+    pub tags: Vec<String>,
+    /// How many of the questions have to be of each difficulty, eg.
+    /// `{ "hard": 3 }`. The rest are of the other difficulties.
+    pub difficulty: BTreeMap<Difficulty, usize>,
+}
+
+/// What a player bet in a wager round.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WagerOutcome {
+    pub username: String,
+    pub wager: u32,
+    pub correct: bool,
+}
+
+impl Question {
+    /// The points for the fastest correct answer.
+    pub fn max_points(&self) -> u32 {
+        self.points.saturating_mul(self.multiplier)
+    }
+}
+
+/// An empty question with the usual settings, to fill in the rest of.
+impl Default for Question {
+    fn default() -> Self {
+        Question {
+            question: String::new(),
+            choices: Vec::new(),
+            answer: 0,
+            time: DEFAULT_TIME,
+            points: default_points(),
+            multiplier: default_multiplier(),
+            wager: false,
+            id: None,
+            tags: Vec::new(),
+            difficulty: None,
+        }
+    }
+}
+
+/// The time to answer questions which don't say how long they take, such as
+/// imported ones.
+pub const DEFAULT_TIME: u16 = 20;
+
+fn default_points() -> u32 {
+    1000
+}
+
+fn default_multiplier() -> u32 {
+    1
 }
 
 // Trait implementation stuff. Doesn't matter too much.
@@ -0,0 +1,316 @@
+use super::api::{
+    Action, ChallengeAnswer, ChallengeId, ChallengePlayerResult, ChallengeResults, HostEvent, JoinFailCode, Question,
+    RoomSettings, UserEvent,
+};
+use super::state::SharedState;
+use super::{now_millis, username};
+
+use crate::ext::{NextActionExt, ToMessageExt};
+use crate::quiz::lint;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket};
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+
+use futures::SinkExt;
+
+use tokio::time::Instant;
+
+/// How long a challenge can stay open for.
+const MAX_OPEN_TIME: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// How long a challenge's results are kept after it closes.
+const RESULTS_KEPT_FOR: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// A self-paced quiz that players can take any time before the deadline.
+pub struct Challenge {
+    pub questions: Vec<Question>,
+    /// When the challenge closes, in milliseconds since the Unix epoch.
+    pub deadline: u64,
+    players: Mutex<HashMap<String, ChallengePlayer>>,
+}
+
+struct ChallengePlayer {
+    result: ChallengePlayerResult,
+    /// Whether the player is currently taking the challenge.
+    connected: bool,
+}
+
+/// Challenge results api router.
+pub fn router() -> Router {
+    Router::new()
+        // GET /:id
+        .route("/:id", get(challenge_results))
+}
+
+/// Works out the points for a correct answer.
+///
+/// There's nobody to race against, so instead of ranking players by who
+/// answered first, points go from the question's full points for an instant
+/// answer down to half for an answer at the last moment.
+fn speed_points(time_taken: Duration, time: u16, max_points: u32) -> u32 {
+    let fraction = time_taken.as_secs_f64() / f64::from(time.max(1));
+
+    (f64::from(max_points) * (1.0 - 0.5 * fraction.min(1.0))).round() as u32
+}
+
+impl Challenge {
+    pub fn new(questions: Vec<Question>, deadline: u64) -> Self {
+        Self { questions, deadline, players: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn is_open(&self) -> bool {
+        now_millis() < self.deadline
+    }
+
+    /// Whether the challenge closed long enough ago that its results can be
+    /// thrown away.
+    pub fn is_expired(&self) -> bool {
+        now_millis() >= self.deadline.saturating_add(RESULTS_KEPT_FOR.as_millis() as u64)
+    }
+
+    /// Adds a player, or picks up where they left off if they have taken the
+    /// challenge before.
+    ///
+    /// Returns the player's display name and the questions they've answered.
+    fn connect(&self, name: &str) -> Result<(String, usize), JoinFailCode> {
+        let name = username::normalize(name)?;
+        let mut players = self.players.lock().unwrap();
+
+        if let Some(player) = players.get_mut(&name) {
+            if player.connected || player.result.finished {
+                return Err(JoinFailCode::DuplicateUser);
+            }
+
+            player.connected = true;
+            return Ok((name, player.result.answers.len()));
+        }
+
+        let skeleton = username::skeleton(&name);
+        for taken in players.keys() {
+            username::check_clash(&name, &skeleton, taken, &username::skeleton(taken))?;
+        }
+
+        players.insert(
+            name.clone(),
+            ChallengePlayer {
+                result: ChallengePlayerResult {
+                    username: name.clone(),
+                    total_points: 0,
+                    finished: false,
+                    answers: Vec::new(),
+                },
+                connected: true,
+            },
+        );
+
+        Ok((name, 0))
+    }
+
+    /// Marks a player as no longer taking the challenge.
+    fn disconnect(&self, name: &str) {
+        if let Some(player) = self.players.lock().unwrap().get_mut(name) {
+            player.connected = false;
+        }
+    }
+
+    /// Records a player's answer.
+    ///
+    /// Returns the player's total points.
+    fn record(&self, name: &str, answer: ChallengeAnswer) -> u32 {
+        let mut players = self.players.lock().unwrap();
+        let player = match players.get_mut(name) {
+            Some(player) => player,
+            None => return 0,
+        };
+
+        player.result.total_points = player.result.total_points.saturating_add(answer.points);
+        player.result.answers.push(answer);
+        player.result.finished = player.result.answers.len() >= self.questions.len();
+
+        player.result.total_points
+    }
+
+    /// Gets everything recorded so far.
+    pub fn results(&self, challenge_id: ChallengeId) -> ChallengeResults {
+        let mut players: Vec<_> = self
+            .players
+            .lock()
+            .unwrap()
+            .values()
+            .map(|player| player.result.clone())
+            .collect();
+        players.sort_by(|a, b| a.username.cmp(&b.username));
+
+        ChallengeResults { challenge_id, deadline: self.deadline, questions: self.questions.clone(), players }
+    }
+}
+
+/// Handles challenge creation.
+///
+/// Unlike rooms, challenges run without a host, so the socket is closed once
+/// the challenge exists.
+pub async fn create_challenge(mut host: WebSocket, state: SharedState, questions: Vec<Question>, deadline: u64) {
+    // Challenges are held to the same rules as rooms
+    let problem = lint::validate(&questions, &RoomSettings::default()).into_iter().next();
+
+    let event = if let Some(problem) = problem {
+        let reason = problem.to_string();
+        tracing::error!("Couldn't create challenge: {reason}");
+        HostEvent::CreateFailed { reason }
+    } else if deadline <= now_millis() {
+        tracing::error!("Challenge deadline is in the past");
+        HostEvent::CreateFailed { reason: String::from("The deadline has already passed") }
+    } else if deadline > now_millis() + MAX_OPEN_TIME.as_millis() as u64 {
+        tracing::error!("Challenge deadline is too far away");
+        HostEvent::CreateFailed { reason: String::from("Challenges can be open for at most 30 days") }
+    } else {
+        let challenge_id = state.insert_challenge(Arc::new(Challenge::new(questions, deadline)));
+        tracing::debug!("Created challenge `{challenge_id}`");
+        HostEvent::ChallengeCreated { challenge_id }
+    };
+
+    let _ = host.send(event.to_message()).await;
+    let _ = host.close().await;
+}
+
+/// Handles a player taking a challenge.
+pub async fn join_challenge(mut socket: WebSocket, state: SharedState, challenge_id: ChallengeId, username: String) {
+    let challenge = match state.find_challenge(&challenge_id) {
+        Some(challenge) if challenge.is_open() => challenge,
+        found => {
+            let code = if found.is_some() { JoinFailCode::ChallengeClosed } else { JoinFailCode::RoomNotFound };
+            tracing::error!("Couldn't join challenge `{challenge_id}` ({code:?}), disconnecting...");
+            let _ = socket.send(UserEvent::join_failed(code).to_message()).await;
+            return;
+        }
+    };
+
+    let (username, answered) = match challenge.connect(&username) {
+        Ok(player) => player,
+        Err(code) => {
+            tracing::error!("User `{username}` couldn't join ({code:?}), disconnecting...");
+            let _ = socket.send(UserEvent::join_failed(code).to_message()).await;
+            return;
+        }
+    };
+
+    let _ = socket
+        .send(UserEvent::Joined { username: username.clone(), token: None }.to_message())
+        .await;
+
+    take_challenge(&mut socket, &challenge, &username, answered).await;
+
+    challenge.disconnect(&username);
+}
+
+/// Sends the questions to a player one at a time, starting from `start`.
+///
+/// Returns when the player is done or leaves.
+async fn take_challenge(socket: &mut WebSocket, challenge: &Challenge, username: &str, start: usize) {
+    let count = challenge.questions.len();
+
+    for (index, question) in challenge.questions.iter().enumerate().skip(start) {
+        // Wait until the player asks for the next question
+        loop {
+            match tokio::time::timeout(Duration::from_secs(25), socket.next_action()).await {
+                Ok(Some(Action::NextQuestion)) => break,
+                Ok(Some(_)) => (),
+                // Player left
+                Ok(None) => return,
+                // Ping the player to keep the socket alive
+                Err(_) => {
+                    tracing::debug!("Pinging player");
+                    let _ = socket.send(Message::Ping(vec![])).await;
+                }
+            }
+        }
+
+        // Questions can't be started after the deadline
+        if !challenge.is_open() {
+            tracing::debug!("Challenge closed, disconnecting `{username}`...");
+            let _ = socket
+                .send(UserEvent::join_failed(JoinFailCode::ChallengeClosed).to_message())
+                .await;
+            return;
+        }
+
+        let event = UserEvent::ChallengeQuestion {
+            index,
+            count,
+            question: question.question.clone(),
+            choices: question.choices.clone(),
+            time: question.time,
+        };
+        let _ = socket.send(event.to_message()).await;
+
+        // Wait for an answer or the player's timer to run out
+        let started = Instant::now();
+        let time_task = tokio::time::sleep(Duration::from_secs(question.time as u64));
+        tokio::pin!(time_task);
+
+        let (choice, left) = loop {
+            tokio::select! {
+                _ = (&mut time_task) => break (None, false),
+                act = socket.next_action() => match act {
+                    Some(Action::Answer { choice }) => break (Some(choice), false),
+                    Some(_) => (),
+                    // Leaving counts as running out of time, so players can't
+                    // reconnect to get more time
+                    None => break (None, true),
+                },
+            }
+        };
+
+        let time_taken = started.elapsed();
+        let correct = choice == Some(question.answer);
+        let points = if correct { speed_points(time_taken, question.time, question.max_points()) } else { 0 };
+
+        tracing::debug!("`{username}` answered {choice:?} for +{points}");
+
+        let total_points = challenge
+            .record(username, ChallengeAnswer { choice, correct, time_taken: time_taken.as_millis() as u64, points });
+
+        if left {
+            return;
+        }
+
+        let event = UserEvent::AnswerResult { correct, points, total_points };
+        let _ = socket.send(event.to_message()).await;
+    }
+
+    tracing::debug!("`{username}` finished the challenge");
+    let _ = socket.send(UserEvent::GameEnd.to_message()).await;
+    let _ = socket.close().await;
+}
+
+/// Returns a challenge's results as JSON.
+async fn challenge_results(
+    Path(challenge_id): Path<ChallengeId>,
+    Extension(state): Extension<SharedState>,
+) -> Result<Json<ChallengeResults>, StatusCode> {
+    let challenge = state.find_challenge(&challenge_id).ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(challenge.results(challenge_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_by_speed() {
+        assert_eq!(speed_points(Duration::ZERO, 10, 1000), 1000);
+        assert_eq!(speed_points(Duration::from_secs(5), 10, 1000), 750);
+        assert_eq!(speed_points(Duration::from_secs(10), 10, 1000), 500);
+        assert_eq!(speed_points(Duration::from_secs(11), 10, 1000), 500);
+        assert_eq!(speed_points(Duration::from_secs(5), 10, 2000), 1500);
+        assert_eq!(speed_points(Duration::ZERO, 10, 0), 0);
+    }
+}
@@ -0,0 +1,150 @@
+use std::path::Path;
+
+/// The word list used when `KAHOOT_NAME_FILTER` isn't set.
+pub const DEFAULT_WORD_LIST: &str = "name-filter.txt";
+
+/// Decides whether a username is inappropriate.
+///
+/// Any `Fn(&str) -> bool` closure can be used as a filter, which makes custom
+/// filters easy to plug in.
+pub trait NameFilter: Send + Sync {
+    /// Returns `true` if the name should be blocked.
+    fn is_blocked(&self, name: &str) -> bool;
+}
+
+impl<F> NameFilter for F
+where
+    F: Fn(&str) -> bool + Send + Sync,
+{
+    fn is_blocked(&self, name: &str) -> bool {
+        self(name)
+    }
+}
+
+/// A filter which blocks names with a word that starts with any word from a
+/// list.
+///
+/// Names are split into words at spaces, underscores and hyphens. Each word
+/// is compared after undoing common leetspeak substitutions and removing
+/// anything else that isn't a letter, so `xX_b@dw0rds` and `b.a.d.w.o.r.d`
+/// are both caught by `badword`. Blocked words hidden inside other words (eg.
+/// `ass` in `Cassidy`) aren't caught, so ordinary names get through.
+#[derive(Debug, Default)]
+pub struct WordList {
+    words: Vec<String>,
+}
+
+impl WordList {
+    /// Creates a filter from a list of words.
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let words = words
+            .into_iter()
+            .map(|word| unleet(word.as_ref()))
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        Self { words }
+    }
+
+    /// Loads a word list from a file.
+    ///
+    /// The file has one word per line. Blank lines and lines starting with `#`
+    /// are ignored.
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+
+        let words = text.lines().map(str::trim).filter(|line| !line.starts_with('#'));
+
+        Ok(Self::new(words))
+    }
+
+    /// Loads the word list at `KAHOOT_NAME_FILTER`, or `DEFAULT_WORD_LIST` if
+    /// it isn't set.
+    ///
+    /// If the file can't be read, an empty list is used so the server can
+    /// still run.
+    pub fn from_env() -> Self {
+        let path = std::env::var("KAHOOT_NAME_FILTER").unwrap_or_else(|_| DEFAULT_WORD_LIST.into());
+
+        match Self::load(&path) {
+            Ok(list) => {
+                tracing::debug!("Loaded {} filtered words from `{path}`", list.words.len());
+                list
+            }
+            Err(e) => {
+                tracing::warn!("Couldn't load name filter `{path}` ({e}), names won't be filtered");
+                Self::default()
+            }
+        }
+    }
+}
+
+impl NameFilter for WordList {
+    fn is_blocked(&self, name: &str) -> bool {
+        name.split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+            .map(unleet)
+            .any(|token| self.words.iter().any(|word| token.starts_with(word.as_str())))
+    }
+}
+
+/// Lowercases text, undoes leetspeak substitutions and removes everything that
+/// isn't a letter.
+fn unleet(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | '!' | '|' => 'i',
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '6' | '9' => 'g',
+            '7' | '+' => 't',
+            '8' => 'b',
+            c => c,
+        })
+        .filter(|c| c.is_alphabetic())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_list() {
+        let filter = WordList::new(["badword"]);
+
+        assert!(filter.is_blocked("badword"));
+        assert!(filter.is_blocked("xX_BadWord_Xx"));
+        assert!(filter.is_blocked("b@dw0rd"));
+        assert!(filter.is_blocked("b.a.d.w.o.r.d"));
+        assert!(filter.is_blocked("the badwords"));
+        assert!(!filter.is_blocked("bad"));
+        assert!(!filter.is_blocked("Alice"));
+    }
+
+    #[test]
+    fn ordinary_names() {
+        let filter = WordList::new(["shit", "crap", "ass"]);
+
+        assert!(!filter.is_blocked("Ash Itami"));
+        assert!(!filter.is_blocked("Kshitij"));
+        assert!(!filter.is_blocked("Scrappy"));
+        assert!(!filter.is_blocked("Cassidy"));
+        assert!(filter.is_blocked("Ash Sh1t"));
+        assert!(filter.is_blocked("xX_Cr@ppy"));
+    }
+
+    #[test]
+    fn closure_filter() {
+        let filter = |name: &str| name.starts_with("Bob");
+
+        assert!(filter.is_blocked("Bobby"));
+        assert!(!filter.is_blocked("Alice"));
+    }
+}
@@ -0,0 +1,824 @@
+use super::api::{Action, AnswerTiming, HostEvent, Question, QuestionPick, RoomId, UserEvent, WagerOutcome};
+use super::pool::{Picked, QuestionSource};
+use super::scoring::{self, RoundAnswers};
+use super::shuffle;
+use super::snapshot::{Game, GivenAnswer, Phase, Progress};
+use super::state::{self, GameEvent, PlayerAnswer, PlayerWager, Room, SharedState};
+
+use crate::ext::ToMessageExt;
+use crate::results::{self, GameRecorder, Outcome};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::ws::Message;
+
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// The number of seconds players have to bet in a wager round.
+const WAGER_TIME: u16 = 15;
+
+/// How long a room that advances by itself waits in the lobby with nobody in
+/// it, host included, before closing.
+pub const LOBBY_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// The game's ends of a room's channels.
+pub struct Channels {
+    pub actions: mpsc::Receiver<PlayerAnswer>,
+    pub wagers: mpsc::Receiver<PlayerWager>,
+    pub results: watch::Sender<GameEvent>,
+    pub player_events: mpsc::Receiver<state::PlayerEvent>,
+}
+
+/// The game's connection to the host.
+pub struct HostLink {
+    /// Messages for the host.
+    pub messages: mpsc::Sender<Message>,
+    /// The host's actions that the game deals with.
+    pub actions: mpsc::Receiver<Action>,
+    /// Rooms that advance by themselves don't listen to the host, so they keep
+    /// a sender around to never see the host leave.
+    pub keepalive: Option<mpsc::Sender<Action>>,
+}
+
+/// How a room restored after a server restart picks back up.
+pub struct Restored {
+    /// When the snapshot was taken, on the room's clock.
+    pub saved_at: u64,
+    /// Goes off once the host is back, for rooms that wait for them.
+    pub host_back: Option<oneshot::Receiver<()>>,
+}
+
+/// The host left (or nobody was around for too long), so the room closes.
+struct Closed;
+
+/// A round about to be played.
+struct Round {
+    /// The 0-indexed position of the round.
+    index: usize,
+    question: Question,
+    pick: Option<QuestionPick>,
+    /// Whether the round was going when the server stopped.
+    picked_up: bool,
+    /// How far into the round a restored game is.
+    elapsed: Duration,
+    /// What players bet, for wager rounds.
+    wagers: Option<HashMap<String, u32>>,
+    /// The answers given before the server stopped.
+    given: Vec<GivenAnswer>,
+}
+
+/// A running room, and the channels its game goes through.
+struct Runner {
+    state: SharedState,
+    room_id: RoomId,
+    room: Arc<Room>,
+    host_tx: mpsc::Sender<Message>,
+    host_rx: mpsc::Receiver<Action>,
+    action_rx: mpsc::Receiver<PlayerAnswer>,
+    wager_rx: mpsc::Receiver<PlayerWager>,
+    result_tx: watch::Sender<GameEvent>,
+    _keepalive: Option<mpsc::Sender<Action>>,
+}
+
+/// Runs a room's game, from wherever its progress says it got to.
+///
+/// New rooms start in the lobby, and `restored` is only given for rooms
+/// brought back from a snapshot.
+pub async fn run_room(
+    state: SharedState,
+    room_id: RoomId,
+    room: Arc<Room>,
+    channels: Channels,
+    source: QuestionSource,
+    host: HostLink,
+    restored: Option<Restored>,
+) {
+    let Channels { actions, wagers, results, player_events } = channels;
+    let HostLink { messages, actions: host_actions, keepalive } = host;
+
+    forward_player_events(&room, messages.clone(), player_events);
+    let heartbeat = heartbeat(messages.clone());
+
+    let mut runner = Runner {
+        state,
+        room_id,
+        room,
+        host_tx: messages,
+        host_rx: host_actions,
+        action_rx: actions,
+        wager_rx: wagers,
+        result_tx: results,
+        _keepalive: keepalive,
+    };
+
+    let Progress { phase, game } = runner.room.progress.lock().unwrap().clone();
+
+    if matches!(phase, Phase::Lobby) && runner.lobby().await.is_err() {
+        runner.state.remove_room(&runner.room_id).await;
+        return;
+    }
+
+    tracing::debug!("Starting game...");
+
+    // Everything carried from one round to the next, which restored games
+    // already have
+    let mut game = game.unwrap_or_else(|| runner.new_game());
+
+    match runner.play(&mut game, phase, source, restored).await {
+        Ok(played) => {
+            runner.finish(game, played).await;
+
+            // Stop sending heartbeat to host
+            heartbeat.abort();
+        }
+        Err(Closed) => {
+            let Runner { state, room, .. } = &runner;
+            results::save(state, game.recorder.finish(Outcome::Aborted, room, &game.totals)).await;
+        }
+    }
+
+    runner.state.remove_room(&runner.room_id).await;
+}
+
+/// Forwards players joining, leaving and the like to the host.
+fn forward_player_events(
+    room: &Room,
+    host_tx: mpsc::Sender<Message>,
+    mut player_event_rx: mpsc::Receiver<state::PlayerEvent>,
+) {
+    let roster = room.roster.clone();
+
+    tokio::spawn(async move {
+        // Who is in the room, to work out who's missing from the roster
+        let mut present = HashSet::new();
+
+        while let Some(event) = player_event_rx.recv().await {
+            let roster_changed = match &event {
+                state::PlayerEvent::Joined(username) => present.insert(username.clone()),
+                state::PlayerEvent::Left(username) => present.remove(username),
+                _ => false,
+            };
+
+            let event = match event {
+                state::PlayerEvent::Joined(username) => HostEvent::UserJoined { username },
+                state::PlayerEvent::JoinRequested(username, reason) => HostEvent::JoinRequested { username, reason },
+                state::PlayerEvent::Renamed { old, new } => HostEvent::UserRenamed { old_username: old, username: new },
+                state::PlayerEvent::TeamAssigned(username, team) => HostEvent::TeamAssigned { username, team },
+                state::PlayerEvent::Left(username) => HostEvent::UserLeft { username },
+            };
+
+            // Keep going if the host has left, as rooms that advance by
+            // themselves still need to track the roster
+            let _ = host_tx.send(event.to_message()).await;
+
+            // Let the host know who is still missing
+            if let (Some(roster), true) = (&roster, roster_changed) {
+                for name in &present {
+                    roster.mark_attended(name);
+                }
+
+                let missing = roster.missing(|name| present.contains(name));
+                let _ = host_tx.send(HostEvent::RosterUpdate { missing }.to_message()).await;
+            }
+        }
+    });
+}
+
+/// Pings the host every 25 seconds to keep the socket alive.
+fn heartbeat(host_tx: mpsc::Sender<Message>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while host_tx.send(Message::Ping(vec![])).await.is_ok() {
+            tracing::debug!("Pinging host");
+            tokio::time::sleep(Duration::from_secs(25)).await;
+        }
+    })
+}
+
+/// Whether a player was knocked out of an elimination game, and only watches.
+fn is_spectator(survivors: &Option<HashSet<String>>, name: &str) -> bool {
+    survivors.as_ref().is_some_and(|survivors| !survivors.contains(name))
+}
+
+impl Runner {
+    /// Waits in the lobby until the game starts.
+    async fn lobby(&mut self) -> Result<(), Closed> {
+        let room = &self.room;
+
+        // Scheduled games start by themselves if enough players have joined
+        let auto_min_players = room.settings.auto_advance.as_ref().map_or(1, |auto| auto.min_players);
+        let min_players = room.settings.min_players.max(auto_min_players).max(1);
+        let mut on_schedule = room.settings.starts_at.is_some();
+        let scheduled_start = tokio::time::sleep(Duration::from_millis(
+            room.settings
+                .starts_at
+                .map_or(0, |starts_at| starts_at.saturating_sub(room.now_millis())),
+        ));
+        tokio::pin!(scheduled_start);
+
+        // Rooms that advance by themselves check every so often if enough
+        // players have joined
+        let mut player_check = tokio::time::interval(Duration::from_millis(500));
+        let auto_advance = room.settings.auto_advance.is_some();
+
+        // They outlive their host, so they close once nobody has been around
+        // for a while instead
+        let mut idle_since = None;
+
+        // Wait until host begins room and there is at least one player in lobby
+        loop {
+            tokio::select! {
+                act = self.host_rx.recv() => match act {
+                    // If action is begin round and there is at least one player
+                    Some(Action::BeginRound) if room.users.player_count() > 0 => return Ok(()),
+                    // If received action but does not match above, ignore
+                    Some(_) => (),
+
+                    // If host dc's, close room
+                    None => {
+                        tracing::debug!("Closing room...");
+                        return Err(Closed);
+                    }
+                },
+
+                _ = &mut scheduled_start, if on_schedule => {
+                    on_schedule = false;
+
+                    let players = room.users.player_count();
+                    if players >= min_players {
+                        return Ok(());
+                    }
+
+                    tracing::debug!("Not enough players to start on time");
+                    let _ = self.host_tx.send(HostEvent::StartDelayed { players, min_players }.to_message()).await;
+                }
+
+                _ = player_check.tick(), if auto_advance && !on_schedule => {
+                    let players = room.users.player_count();
+                    if players >= min_players {
+                        return Ok(());
+                    }
+
+                    if players > 0 || !self.host_tx.is_closed() {
+                        idle_since = None;
+                    } else if idle_since.get_or_insert_with(Instant::now).elapsed() >= LOBBY_IDLE_TIMEOUT {
+                        tracing::debug!("Closing idle room...");
+                        return Err(Closed);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Starts a game with everyone in the lobby.
+    fn new_game(&self) -> Game {
+        let room = &self.room;
+
+        Game {
+            correct_rates: Vec::new(),
+            totals: HashMap::new(),
+            survivors: room.settings.elimination.then(|| room.users.names().into_iter().collect()),
+            starting_players: room.users.player_count(),
+            // Everything that happens is written down for the game's results
+            recorder: GameRecorder::new(self.room_id, room.quiz.clone(), room.seed, room.host_token.clone()),
+        }
+    }
+
+    /// Plays rounds until the questions run out, or elimination leaves a
+    /// single player.
+    ///
+    /// Returns the ids of the questions played.
+    async fn play(
+        &mut self,
+        game: &mut Game,
+        mut phase: Phase,
+        mut source: QuestionSource,
+        mut restored: Option<Restored>,
+    ) -> Result<Vec<String>, Closed> {
+        // The ids of the questions played so far
+        //
+        // Restored games pick the questions they already played again, so they
+        // go on to the same ones as before.
+        let mut played = Vec::new();
+        for round in 0..game.correct_rates.len() {
+            let correct_rate = round.checked_sub(1).and_then(|last| game.correct_rates[last]);
+            if let Some(Picked { id, .. }) = source.next(correct_rate) {
+                played.push(id);
+            }
+        }
+
+        // Rooms restored between rounds wait for the next one, like they did
+        // before
+        let mut between_rounds = matches!(phase, Phase::BetweenRounds);
+
+        loop {
+            if between_rounds {
+                self.between_rounds().await?;
+            }
+            between_rounds = true;
+
+            let correct_rate = game.correct_rates.last().copied().flatten();
+            let Some(Picked { question, id, pick }) = source.next(correct_rate) else {
+                return Ok(played);
+            };
+            let index = played.len();
+            played.push(id.clone());
+
+            // Games restored in the middle of a round pick it back up, once the
+            // host is back so nobody loses time waiting for them
+            let resuming = match std::mem::take(&mut phase) {
+                phase @ (Phase::Wagers { .. } | Phase::Round { .. }) => Some(phase),
+                _ => None,
+            };
+            let saved_at = match (&resuming, restored.take()) {
+                (Some(_), Some(Restored { saved_at, host_back })) => {
+                    if let Some(host_back) = host_back {
+                        tracing::debug!("Waiting for the host to come back...");
+                        host_back.await.map_err(|_| Closed)?;
+                    }
+
+                    saved_at
+                }
+                _ => 0,
+            };
+            if resuming.is_none() {
+                game.recorder.begin_round(id, question.clone());
+            }
+
+            let picked_up = matches!(resuming, Some(Phase::Round { .. }));
+            let mut round =
+                Round { index, question, pick, picked_up, elapsed: Duration::ZERO, wagers: None, given: Vec::new() };
+
+            // Players bet some of their points before seeing wager questions
+            match resuming {
+                Some(Phase::Round { started_at, wagers, answers }) => {
+                    round.elapsed = Duration::from_millis(saved_at.saturating_sub(started_at));
+                    round.wagers = wagers;
+                    round.given = answers;
+                }
+                Some(Phase::Wagers { ends_at, placed }) if round.question.wager => {
+                    let wager_time = Duration::from_millis(ends_at.saturating_sub(saved_at));
+                    round.wagers = Some(self.take_wagers(game, wager_time, placed).await?);
+                }
+                _ if round.question.wager => {
+                    let wager_time = Duration::from_secs(WAGER_TIME as u64);
+                    round.wagers = Some(self.take_wagers(game, wager_time, HashMap::new()).await?);
+                }
+                _ => (),
+            }
+
+            let round_answers = self.take_answers(game, &round).await?;
+            self.end_round(game, round, round_answers).await;
+
+            // Elimination games are over once there is at most one survivor
+            if let Some(survivors) = &game.survivors {
+                if survivors.len() <= 1 && game.starting_players > 1 {
+                    tracing::debug!("At most one player left, ending game early...");
+                    return Ok(played);
+                }
+            }
+        }
+    }
+
+    /// Waits until the next round should begin.
+    async fn between_rounds(&mut self) -> Result<(), Closed> {
+        if let Some(auto) = &self.room.settings.auto_advance {
+            // Rooms that advance by themselves just take a short break
+            let pause = tokio::time::sleep(Duration::from_secs(auto.pause as u64));
+            tokio::pin!(pause);
+
+            loop {
+                tokio::select! {
+                    _ = &mut pause => return Ok(()),
+                    // Starting early only counts in the lobby
+                    _ = self.host_rx.recv() => (),
+                }
+            }
+        }
+
+        // Wait until host begins next round
+        loop {
+            match self.host_rx.recv().await {
+                // If action is begin round, break loop
+                Some(Action::BeginRound) => return Ok(()),
+
+                // If host sends irrelevant message, ignore
+                Some(_) => (),
+
+                // If host dc's, close room
+                None => {
+                    tracing::debug!("Closing room...");
+                    return Err(Closed);
+                }
+            }
+        }
+    }
+
+    /// Takes bets for a wager round, on top of the ones already `placed`.
+    ///
+    /// Returns what every player bet.
+    async fn take_wagers(
+        &mut self,
+        game: &Game,
+        wager_time: Duration,
+        mut placed: HashMap<String, u32>,
+    ) -> Result<HashMap<String, u32>, Closed> {
+        let room = &self.room;
+        let spectating = |name: &str| is_spectator(&game.survivors, name);
+
+        let time = wager_time.as_secs_f64().ceil() as u16;
+        let ends_at = room.now_millis() + wager_time.as_millis() as u64;
+        room.save_progress(Phase::Wagers { ends_at, placed: placed.clone() }, game);
+
+        tracing::debug!("Taking wagers...");
+        let _ = self.host_tx.send(HostEvent::WagerBegin { time }.to_message()).await;
+        for username in room.users.names() {
+            if !spectating(&username) && !placed.contains_key(&username) {
+                let total_points = game.totals.get(&username).copied().unwrap_or(0);
+                let event = UserEvent::WagerBegin { total_points, time };
+                room.users.send_to(&username, event.to_message());
+            }
+        }
+
+        // Ignore wagers from earlier rounds
+        while self.wager_rx.try_recv().is_ok() {}
+
+        let wager_task = tokio::time::sleep(wager_time);
+        tokio::pin!(wager_task);
+        loop {
+            tokio::select! {
+                act = self.host_rx.recv() => match act {
+                    // The host can cut the betting short
+                    Some(Action::EndRound) => break,
+                    Some(_) => (),
+                    None => {
+                        tracing::debug!("Host disconnected...");
+                        return Err(Closed);
+                    }
+                },
+
+                _ = (&mut wager_task) => break,
+
+                Some(PlayerWager { username, amount }) = self.wager_rx.recv() => {
+                    if spectating(&username) || placed.contains_key(&username) {
+                        continue;
+                    }
+
+                    // Players can't bet more than they have
+                    let total = game.totals.get(&username).copied().unwrap_or(0);
+                    let amount = amount.min(u32::try_from(total.max(0)).unwrap_or(u32::MAX));
+                    tracing::debug!("`{username}` bet {amount}");
+                    placed.insert(username.clone(), amount);
+                    room.update_phase(|phase| {
+                        if let Phase::Wagers { placed, .. } = phase {
+                            placed.insert(username.clone(), amount);
+                        }
+                    });
+
+                    let _ = self.host_tx.send(HostEvent::WagerPlaced { username }.to_message()).await;
+
+                    if room.users.all_players(|name| placed.contains_key(name) || spectating(name)) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(placed)
+    }
+
+    /// Plays a round, taking answers until everyone is done or time is up.
+    async fn take_answers(&mut self, game: &mut Game, round: &Round) -> Result<RoundAnswers, Closed> {
+        let room = &self.room;
+        let question = &round.question;
+        let question_time = question.time as u64;
+        let choice_count = question.choices.len();
+
+        let mut answered = HashSet::new();
+        let mut round_answers = RoundAnswers::default();
+        let mut teams_answered = HashSet::new();
+
+        // Alert host that the round began
+        tracing::debug!("Alerting host that round began...");
+        if let Some(pick) = &round.pick {
+            tracing::debug!("Picked a {:?} question: {}", pick.difficulty, pick.reason);
+        }
+        let time_left = round
+            .picked_up
+            .then(|| (question_time as f64 - round.elapsed.as_secs_f64()).max(0.0).ceil() as u16);
+        let event = HostEvent::RoundBegin { question: question.clone(), pick: round.pick.clone(), time_left };
+        let _ = self.host_tx.send(event.to_message()).await;
+
+        // Alert players a round began
+        tracing::debug!("Alerting players that round began...");
+        let _ = self.result_tx.send(GameEvent::RoundBegin {
+            round: round.index,
+            choices: question.choices.clone(),
+            time: question.time,
+            elapsed: round.elapsed,
+        });
+
+        // Keep taking from stream until it is empty
+        while self.action_rx.try_recv().is_ok() {}
+
+        // Players with extra time get their own deadline
+        let multipliers = room.time_multipliers.lock().unwrap().clone();
+        let window_of = |name: &str| {
+            let window = Duration::from_secs(question_time);
+            multipliers.get(name).map_or(window, |multiplier| window.mul_f64(*multiplier))
+        };
+
+        // Every deadline in the round, with the last one first
+        let mut deadlines: Vec<Duration> = room.users.names().iter().map(|name| window_of(name)).collect();
+        deadlines.push(window_of(""));
+        deadlines.sort_by(|a, b| b.cmp(a));
+        deadlines.dedup();
+
+        // Whether a player can't answer anymore (spectators don't count)
+        let can_change = room.settings.answer_changes.is_some();
+        let round_start = Instant::now().checked_sub(round.elapsed).unwrap_or_else(Instant::now);
+        let done = |name: &str, answered: &HashSet<String>| {
+            is_spectator(&game.survivors, name)
+                || (answered.contains(name) && !can_change)
+                || round_start.elapsed() >= window_of(name)
+        };
+
+        // Count the answers given before a restart again
+        for GivenAnswer { username, choice, elapsed } in &round.given {
+            let elapsed = Duration::from_millis(*elapsed);
+            let normalized = multipliers
+                .get(username)
+                .map_or(elapsed, |multiplier| elapsed.div_f64(*multiplier));
+            round_answers.record(username, *choice, normalized);
+            game.recorder.answer(username, *choice, elapsed);
+            game.totals.entry(username.clone()).or_default();
+            answered.insert(username.clone());
+        }
+        if let Some(teams) = &room.teams {
+            teams_answered.extend(teams.answered_teams(&room.users.names(), &answered));
+        }
+
+        let started_at = room.now_millis().saturating_sub(round.elapsed.as_millis() as u64);
+        let phase = Phase::Round { started_at, wagers: round.wagers.clone(), answers: round.given.clone() };
+        room.save_progress(phase, game);
+
+        // Wait for round end event
+        let time_task = tokio::time::sleep_until(round_start + deadlines.pop().unwrap_or_default());
+        tokio::pin!(time_task);
+        loop {
+            // Pick whichever future resolves first
+            tokio::select! {
+                // Host force end
+                act = self.host_rx.recv() => {
+                    match act {
+                        // If the host sent an end round action, exit loop
+                        Some(Action::EndRound) => {
+                            tracing::debug!("Host forcefully ended round");
+                            break;
+                        }
+                        // Ignore all other actions
+                        Some(_) => (),
+
+                        // If the action is none, the socket must have dc'd
+                        None => {
+                            tracing::debug!("Host disconnected...");
+                            return Err(Closed);
+                        }
+                    }
+                }
+
+                // Timeout
+                _ = (&mut time_task) => {
+                    match deadlines.pop() {
+                        // Players with extra time might still be answering
+                        Some(deadline) if !room.users.all_players(|name| done(name, &answered)) => {
+                            time_task.as_mut().reset(round_start + deadline);
+                        }
+                        _ => {
+                            tracing::debug!("Question timeout");
+                            break;
+                        }
+                    }
+                }
+
+                // User answers
+                Some(PlayerAnswer { username, choice }) = self.action_rx.recv() => {
+                    if done(&username, &answered) {
+                        continue;
+                    }
+
+                    // Players answer with where the choice was on their screen
+                    let choice = if room.settings.shuffle_choices {
+                        let order = shuffle::choice_order(room.seed, round.index, &username, choice_count);
+                        order.get(choice).copied()
+                    } else {
+                        Some(choice).filter(|choice| *choice < choice_count)
+                    };
+
+                    // Choices that aren't there aren't answers
+                    let Some(choice) = choice else {
+                        tracing::debug!("`{username}` answered with a choice that isn't there");
+                        continue;
+                    };
+
+                    tracing::debug!("`{username}` answered {choice}");
+                    let elapsed = round_start.elapsed();
+
+                    // Speed is measured against the player's own time, so
+                    // extra time doesn't cost them points
+                    let normalized = multipliers.get(&username).map_or(elapsed, |multiplier| elapsed.div_f64(*multiplier));
+                    round_answers.record(&username, choice, normalized);
+                    game.recorder.answer(&username, choice, elapsed);
+                    room.update_phase(|phase| {
+                        if let Phase::Round { answers, .. } = phase {
+                            let elapsed = elapsed.as_millis() as u64;
+                            answers.push(GivenAnswer { username: username.clone(), choice, elapsed });
+                        }
+                    });
+
+                    // Changing an answer doesn't count as answering again
+                    if !answered.insert(username.clone()) {
+                        continue;
+                    }
+
+                    // Tell host user answered
+                    let _ = self.host_tx.send(HostEvent::UserAnswered {
+                            username: username.clone()
+                        }.to_message())
+                        .await;
+
+                    game.totals.entry(username.clone()).or_default();
+
+                    // Tell host about teams that just finished answering
+                    if let Some(teams) = &room.teams {
+                        for team in teams.answered_teams(&room.users.names(), &answered) {
+                            if teams_answered.insert(team.clone()) {
+                                tracing::debug!("Team `{team}` answered");
+                                let _ = self.host_tx.send(HostEvent::TeamAnswered { team }.to_message()).await;
+                            }
+                        }
+                    }
+
+                    // If everyone has answered, leave loop, unless they can
+                    // still change their minds
+                    if room.users.all_players(|name| done(name, &answered)) {
+                        break;
+                    }
+                }
+            };
+        }
+
+        Ok(round_answers)
+    }
+
+    /// Scores a round, and tells everyone how it went.
+    async fn end_round(&mut self, game: &mut Game, round: Round, round_answers: RoundAnswers) {
+        tracing::debug!("End of round...");
+
+        let room = &self.room;
+        let Round { question, wagers, .. } = round;
+        let mut point_gains = HashMap::new();
+
+        // Points lost for a wrong answer, if the room has negative marking
+        let penalty = i64::from(room.settings.negative_marking) * i64::from(question.multiplier);
+
+        // Only final answers are scored, ranked by how fast they came in
+        let timing = room
+            .settings
+            .answer_changes
+            .as_ref()
+            .map_or(AnswerTiming::First, |changes| changes.speed_from);
+        let correct = round_answers.correct(question.answer, timing);
+
+        for (username, points) in correct.iter().zip(scoring::ranked_points(question.max_points(), correct.len())) {
+            // Wager rounds pay out the bet instead of points for speed
+            let gain = match &wagers {
+                Some(wagers) => wagers.get(username).copied().unwrap_or(0),
+                None => points,
+            };
+
+            tracing::debug!("`{username}` +{gain}");
+            point_gains.insert(username.clone(), i64::from(gain));
+        }
+
+        // Wager rounds already cost the bet
+        if penalty > 0 && wagers.is_none() {
+            for username in round_answers.wrong(question.answer) {
+                tracing::debug!("`{username}` -{penalty}");
+                point_gains.insert(String::from(username), -penalty);
+            }
+        }
+
+        let ranked = correct.clone();
+        let correct: HashSet<String> = correct.into_iter().collect();
+
+        // How the class did, for picking the next question
+        let players = room
+            .users
+            .names()
+            .iter()
+            .filter(|name| !is_spectator(&game.survivors, name))
+            .count();
+        game.correct_rates
+            .push((players > 0).then(|| correct.len() as f64 / players as f64));
+
+        // Players who got a wager round wrong lose their bet
+        for (username, wager) in wagers.iter().flatten() {
+            if !correct.contains(username) && *wager > 0 {
+                point_gains.insert(username.clone(), -i64::from(*wager));
+            }
+        }
+
+        for (username, gain) in &point_gains {
+            let total = game.totals.entry(username.clone()).or_default();
+            *total = total.saturating_add(*gain);
+        }
+        game.recorder.end_round(&point_gains, &ranked);
+
+        // Let everyone who bet know how it went
+        let wagers = wagers.map(|wagers| {
+            let mut outcomes: Vec<_> = wagers
+                .into_iter()
+                .map(|(username, wager)| {
+                    let correct = correct.contains(&username);
+                    let total_points = game.totals.get(&username).copied().unwrap_or(0);
+
+                    let event = UserEvent::WagerResult { wager, correct, total_points };
+                    room.users.send_to(&username, event.to_message());
+
+                    WagerOutcome { username, wager, correct }
+                })
+                .collect();
+            outcomes.sort_by(|a, b| a.username.cmp(&b.username));
+
+            outcomes
+        });
+
+        // Knock out every survivor who didn't get the question right
+        let mut eliminated = HashSet::new();
+        if let Some(survivors) = &mut game.survivors {
+            eliminated = survivors.iter().filter(|name| !correct.contains(*name)).cloned().collect();
+            survivors.retain(|name| !eliminated.contains(name));
+
+            // In order, so replays send the same events
+            let mut in_order: Vec<_> = eliminated.iter().collect();
+            in_order.sort();
+            for username in in_order {
+                tracing::debug!("`{username}` was eliminated");
+                let event = HostEvent::UserEliminated { username: username.clone() };
+                let _ = self.host_tx.send(event.to_message()).await;
+            }
+        }
+
+        room.save_progress(Phase::BetweenRounds, game);
+
+        // Tell host that the round ended
+        tracing::debug!("Alerting host that round ended...");
+        let event = HostEvent::RoundEnd {
+            point_gains: point_gains.clone(),
+            team_standings: room.teams.as_ref().map(|teams| teams.standings(&game.totals)),
+            survivors: game.survivors.as_ref().map(HashSet::len),
+            wagers,
+        };
+        let _ = self.host_tx.send(event.to_message()).await;
+
+        // Alert players round ended
+        tracing::debug!("Alerting players that round ended...");
+        let _ = self
+            .result_tx
+            .send(GameEvent::RoundEnd { point_gains: Arc::new(point_gains), eliminated: Arc::new(eliminated) });
+    }
+
+    /// Saves the game's results, and sends them to the host.
+    async fn finish(&mut self, game: Game, played: Vec<String>) {
+        tracing::debug!("Game is over!");
+
+        let room = &self.room;
+        let pooled = room.settings.selection.is_some() || room.settings.adaptive.is_some();
+
+        // Send the final results to the host
+        let absent = room.roster.as_ref().map(|roster| roster.absentees()).unwrap_or_default();
+        let teams = room.teams.as_ref().map(|teams| teams.standings(&game.totals));
+        let survivors = game.survivors.map(|survivors| {
+            let mut survivors: Vec<_> = survivors.into_iter().collect();
+            survivors.sort();
+            survivors
+        });
+        let randomized = room.settings.shuffle_questions || room.settings.shuffle_choices || pooled;
+        let seed = randomized.then_some(room.seed);
+        let question_ids = pooled.then_some(played);
+        results::save(&self.state, game.recorder.finish(Outcome::Finished, room, &game.totals)).await;
+        let event = HostEvent::GameResults { totals: game.totals, absent, teams, survivors, seed, question_ids };
+        let _ = self.host_tx.send(event.to_message()).await;
+
+        // Alert host that the game ended
+        tracing::debug!("Alerting host that game has ended...");
+        let _ = self.host_tx.send(HostEvent::GameEnd.to_message()).await;
+
+        // Alert players game ended
+        let _ = self.result_tx.send(GameEvent::GameEnd);
+    }
+}
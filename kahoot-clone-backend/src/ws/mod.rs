@@ -13,38 +13,115 @@ pub mod api;
 /// Contains data for representing game states.
 pub mod state;
 
-use api::{Action, HostEvent, Question, RoomId, UserEvent};
+/// Contains the username policy (normalization and look-alike detection).
+pub mod username;
 
-use state::{GameEvent, PlayerAnswer, Room, SharedState, Users};
+/// Contains filters for inappropriate usernames.
+pub mod filter;
 
-use crate::ext::{ToMessageExt, NextActionExt};
+/// Contains the friendly name generator.
+pub mod names;
 
-use std::collections::{HashMap, HashSet};
+/// Contains rosters, the lists of players allowed into a room.
+pub mod roster;
+
+/// Contains team membership and scoring.
+pub mod teams;
+
+/// Contains self-paced challenges, which players take on their own time.
+pub mod challenge;
+
+/// Contains round scoring.
+pub mod scoring;
+
+/// Contains seeded shuffling of questions and choices.
+pub mod shuffle;
+
+/// Contains question pools and drawing questions from them.
+pub mod pool;
+
+/// Contains recordings of everything sent and received in a room.
+pub mod recording;
+
+/// Contains replaying recorded rooms.
+#[cfg(any(test, feature = "replay"))]
+pub mod replay;
+
+/// Contains snapshots of rooms, which bring them back after a server restart.
+pub mod snapshot;
+
+/// Contains running a room's game, one phase at a time.
+pub mod game;
+
+use api::{
+    Action, FilterAction, HostEvent, JoinFailCode, JoinRequestReason, Question, RoomId, RoomSettings, TeamAssignment,
+    UserEvent,
+};
+
+use roster::Roster;
+
+use pool::QuestionSource;
+
+use teams::Teams;
+
+use state::{GameEvent, Naming, PlayerAnswer, PlayerWager, Room, SharedState, Tokens, Users};
+
+use recording::{Created, RecordedSocket, Replaying};
+
+use snapshot::{Phase, Progress, Snapshot};
+
+use game::{run_room, Channels, HostLink, Restored};
+
+use crate::ext::{NextActionExt, ToMessageExt};
+use crate::quiz::lint;
+use crate::results::QuizRef;
+
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use axum::extract::ws::{WebSocket, Message};
+use axum::extract::ws::{Message, WebSocket};
 use axum::extract::WebSocketUpgrade;
 use axum::response::Response;
 use axum::routing::get;
 use axum::{Extension, Router};
 
-use tokio::sync::{mpsc, watch};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::time::Instant;
+
+use futures::{Sink, SinkExt, Stream, StreamExt};
 
-use futures::{SinkExt, StreamExt};
+/// How long a room restored after a server restart waits for its host.
+const RESUME_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Gets the current milliseconds since the Unix epoch.
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// A client's connection.
+///
+/// This is a websocket, except in replays.
+pub trait Socket:
+    Stream<Item = Result<Message, axum::Error>> + Sink<Message, Error = axum::Error> + Send + Unpin + 'static
+{
+}
 
-use self::state::State;
+impl<S> Socket for S where
+    S: Stream<Item = Result<Message, axum::Error>> + Sink<Message, Error = axum::Error> + Send + Unpin + 'static
+{
+}
 
 /// Websocket api router.
+///
+/// The shared state has to be included by whoever uses this router.
 pub fn router() -> Router {
-    let rooms = Mutex::new(HashMap::new());
-    let state = Arc::new(State { rooms });
-
     Router::new()
         // GET /
         .route("/", get(handle_ws_connection))
-        // Includes the shared state in routes
-        .layer(Extension(state))
 }
 
 /// Passes an upgraded websocket to `handle_socket`.
@@ -77,293 +154,637 @@ async fn handle_ws(mut socket: WebSocket, state: SharedState) {
     };
 
     match action {
-        Action::CreateRoom { questions } => create_room(socket, state, questions).await,
-        Action::JoinRoom { room_id, username } => join_room(socket, state, room_id, username).await,
+        Action::CreateRoom { questions, settings } => create_room(socket, state, questions, settings, None, None).await,
+        Action::CreateRoomFromQuiz { quiz_id, version, settings } => match state.quizzes.get(quiz_id, version) {
+            Ok(Some(quiz)) => {
+                let quiz_ref = QuizRef { id: quiz.id, version: quiz.version, title: quiz.title };
+                create_room(socket, state, quiz.questions, settings, Some(quiz_ref), None).await
+            }
+            Ok(None) => {
+                let reason = String::from("Quiz does not exist");
+                let _ = socket.send(HostEvent::CreateFailed { reason }.to_message()).await;
+            }
+            Err(e) => {
+                tracing::error!("Couldn't load quiz `{quiz_id}`: {e}");
+                let reason = String::from("Couldn't load the quiz");
+                let _ = socket.send(HostEvent::CreateFailed { reason }.to_message()).await;
+            }
+        },
+        Action::ResumeRoom { room_id, token } => resume_room(socket, state, room_id, token).await,
+        Action::JoinRoom { room_id, username, code, team } => {
+            join_room(socket, state, room_id, Joining::New { username, code, team }).await
+        }
+        Action::RejoinRoom { room_id, token } => join_room(socket, state, room_id, Joining::Returning { token }).await,
+        Action::CreateChallenge { questions, deadline } => {
+            challenge::create_challenge(socket, state, questions, deadline).await
+        }
+        Action::JoinChallenge { challenge_id, username } => {
+            challenge::join_challenge(socket, state, challenge_id, username).await
+        }
         action => tracing::error!("Invalid first action {action:?}"),
     };
 }
 
+/// Works out how a room's questions are picked. Pools only play some of their
+/// questions.
+fn question_source(questions: &[Question], settings: &RoomSettings, seed: u64) -> Result<QuestionSource, String> {
+    if let Some(adaptive) = &settings.adaptive {
+        Ok(QuestionSource::adaptive(questions.to_vec(), adaptive.clone(), seed))
+    } else if let Some(selection) = &settings.selection {
+        pool::draw(questions, selection, seed).map(|drawn| {
+            let drawn = drawn
+                .into_iter()
+                .map(|index| (pool::question_id(questions, index), questions[index].clone()))
+                .collect();
+
+            QuestionSource::fixed(drawn)
+        })
+    } else {
+        let mut questions = pool::with_ids(questions.to_vec());
+        if settings.shuffle_questions {
+            shuffle::shuffle_questions(&mut questions, seed);
+        }
+
+        Ok(QuestionSource::fixed(questions))
+    }
+}
+
 /// Handles room creation.
 ///
-/// The websocket will be treated as the "host" from now on.
-async fn create_room(mut host: WebSocket, state: SharedState, questions: Vec<Question>) {
+/// The websocket will be treated as the "host" from now on. `quiz` is the quiz
+/// from the quiz library being played, if there is one, and `replaying` is the
+/// recorded room being replayed, if there is one.
+async fn create_room(
+    mut host: impl Socket,
+    state: SharedState,
+    questions: Vec<Question>,
+    mut settings: RoomSettings,
+    quiz: Option<QuizRef>,
+    replaying: Option<Replaying>,
+) {
     tracing::debug!("Creating room...");
 
-    let (action_tx, mut action_rx) = mpsc::channel(20);
+    // Turn away quizzes that can't be played
+    if let Some(problem) = lint::validate(&questions, &settings).first() {
+        let reason = problem.to_string();
+        tracing::error!("Couldn't create room: {reason}");
+        let _ = host.send(HostEvent::CreateFailed { reason }.to_message()).await;
+        return;
+    }
+
+    // Roster names are shown and matched the way typed names would be
+    for entry in settings.roster.iter_mut().flatten() {
+        if let Ok(name) = username::normalize(&entry.name) {
+            entry.name = name;
+        }
+    }
+
+    let seed = settings.seed.unwrap_or_else(rand::random);
+    let room_id = replaying.as_ref().map_or_else(rand::random, |replaying| replaying.room_id);
+    let created = Instant::now();
+    let created_at = replaying.as_ref().map_or_else(now_millis, |replaying| replaying.created_at);
+    let token_seed = replaying.as_ref().map_or_else(rand::random, |replaying| replaying.token_seed);
+
+    // Written down before the questions are picked, so replays pick the same
+    // ones
+    let recorded = state.recordings.as_ref().map(|_| Created {
+        room_id,
+        created_at,
+        seed,
+        token_seed,
+        questions: questions.clone(),
+        settings: settings.clone(),
+        quiz: quiz.clone(),
+    });
+
+    let source = match question_source(&questions, &settings, seed) {
+        Ok(source) => source,
+        Err(reason) => {
+            tracing::error!("Couldn't pick questions: {reason}");
+            let _ = host.send(HostEvent::CreateFailed { reason }.to_message()).await;
+            return;
+        }
+    };
+
+    let (action_tx, action_rx) = mpsc::channel(20);
+    let (wager_tx, wager_rx) = mpsc::channel(20);
     let (result_tx, result_rx) = watch::channel(GameEvent::InLobby);
-    let (users, mut player_event_rx) = Users::new();
+    let (users, player_event_rx) = Users::new(seed);
+    let roster = settings.roster.clone().map(|entries| Arc::new(Roster::new(entries)));
+
+    // Roster entries can come with extra time
+    let time_multipliers = settings
+        .roster
+        .iter()
+        .flatten()
+        .filter_map(|entry| Some((entry.name.clone(), entry.time_multiplier?)))
+        .filter(|(_, multiplier)| multiplier.is_finite() && *multiplier > 0.0)
+        .collect();
+
+    let recording = state.recordings.as_ref().zip(recorded).and_then(|(recordings, recorded)| {
+        match recordings.start(recorded, created) {
+            Ok(recording) => Some(Arc::new(recording)),
+            Err(e) => {
+                tracing::error!("Couldn't record room `{room_id}`: {e}");
+                None
+            }
+        }
+    });
+    let mut host = RecordedSocket::new(host, recording.as_ref().map(|recording| (Arc::clone(recording), 0)));
+
+    let tokens = Tokens::new(token_seed);
+    let host_token = tokens.generate();
 
     // Create an empty room
     let room = Room {
+        roster,
+        teams: settings.teams.clone().map(Teams::new),
+        settings,
         users,
         result_stream: result_rx,
         action_stream: action_tx,
+        wager_stream: wager_tx,
+        time_multipliers: Mutex::new(time_multipliers),
+        seed,
+        created_at,
+        created,
+        recording,
+        questions,
+        quiz,
+        host_token,
+        tokens,
+        progress: Mutex::new(Progress::default()),
+        host_slot: Mutex::new(None),
+        closed: AtomicBool::new(false),
     };
 
     // Put the room into an `Arc`
     let room = Arc::new(room);
 
-    let room_id = state.insert_room(Arc::clone(&room));
+    state.insert_room(room_id, Arc::clone(&room));
 
     // Room creation event
     tracing::debug!("Sending room id: `{room_id}`");
     {
-        let event = HostEvent::RoomCreated { room_id };
+        let event = HostEvent::RoomCreated { room_id, token: room.host_token.clone() };
         let _ = host.send(event.to_message()).await;
     }
 
-    let (mut host_tx, mut host_rx) = host.split();
-
-    // Wrap the host transmitter with an `mpsc`
-    let host_tx = {
-        let (host_tx_mpsc, mut rx) = mpsc::channel::<Message>(30);
+    // Let the host know when a scheduled game starts
+    if let Some(starts_at) = room.settings.starts_at {
+        let event = HostEvent::Countdown { starts_in: starts_at.saturating_sub(room.now_millis()) };
+        let _ = host.send(event.to_message()).await;
+    }
 
-        tokio::spawn(async move {
-            while let Some(msg) = rx.recv().await {
-                // If socket is closed
-                if host_tx.send(msg).await.is_err() {
-                    return;
-                }
-            }
+    let (host_tx, host_messages) = mpsc::channel::<Message>(30);
+    let (host_actions, host_rx) = mpsc::channel::<Action>(20);
+    let keepalive = room.settings.auto_advance.as_ref().map(|_| host_actions.clone());
+    connect_host(host, Arc::clone(&room), host_messages, host_actions);
 
-            // Close connection
-            let _ = host_tx.close().await;
-        });
+    let channels =
+        Channels { actions: action_rx, wagers: wager_rx, results: result_tx, player_events: player_event_rx };
+    let host = HostLink { messages: host_tx, actions: host_rx, keepalive };
+    run_room(state, room_id, room, channels, source, host, None).await;
+}
 
-        host_tx_mpsc
+/// Brings back a room from a snapshot, for its host and players to rejoin.
+///
+/// The game waits for the host to come back with a resume room action before
+/// going on, and the room is closed if they don't within `RESUME_TIMEOUT`.
+/// Rooms that advance by themselves carry on without the host.
+pub fn restore_room(state: &SharedState, snapshot: Snapshot) {
+    let Snapshot {
+        room_id,
+        created_at,
+        saved_at,
+        seed,
+        questions,
+        settings,
+        quiz,
+        host_token,
+        tokens,
+        teams,
+        time_multipliers,
+        attended,
+        progress,
+    } = snapshot;
+
+    let source = match question_source(&questions, &settings, seed) {
+        Ok(source) => source,
+        Err(reason) => {
+            tracing::error!("Couldn't restore room `{room_id}`: {reason}");
+            return;
+        }
     };
 
-    // Forward player leave/join to host
-    {
-        let host_tx = host_tx.clone();
-        tokio::spawn(async move {
-            while let Some(event) = player_event_rx.recv().await {
-                let event = match event {
-                    state::PlayerEvent::Joined(username) => HostEvent::UserJoined { username },
-                    state::PlayerEvent::Left(username) => HostEvent::UserLeft { username },
-                };
-
-                // Stop if receiver is closed
-                if host_tx.send(event.to_message()).await.is_err() {
-                    break;
-                }
-            }
-        });
-    }
-
-    // Ping the host every 25 seconds to keep the socket alive
-    let heartbeat = {
-        let host_tx = host_tx.clone();
-        tokio::spawn(async move {
-            while host_tx.send(Message::Ping(vec![])).await.is_ok() {
-                tracing::debug!("Pinging host");
-                tokio::time::sleep(Duration::from_secs(25)).await;
-            }
-        })
+    // Players can't rename themselves once the game has started
+    let event = match progress.phase {
+        Phase::Lobby => GameEvent::InLobby,
+        _ => GameEvent::RoundEnd { point_gains: Arc::default(), eliminated: Arc::default() },
     };
 
-    // Wait until host begins room and there is at least one player in lobby
-    loop {
-        match host_rx.next_action().await {
-            // If action is begin round and there is at least one player
-            Some(Action::BeginRound) if room.users.player_count() > 0 => break,
-            // If received action but does not match above, ignore
-            Some(_) => (),
+    let (action_tx, action_rx) = mpsc::channel(20);
+    let (wager_tx, wager_rx) = mpsc::channel(20);
+    let (result_tx, result_rx) = watch::channel(event);
+    let (users, player_event_rx) = Users::new(seed);
 
-            // If host dc's, close room
-            None => {
-                tracing::debug!("Closing room...");
-                state.remove_room(&room_id).await;
-                return;
-            }
-        }
+    // Names stay with the players holding tokens for them
+    for name in tokens.values() {
+        users.reserve(name);
     }
 
-    tracing::debug!("Starting game...");
-
-    for question in questions.into_iter() {
-        let mut point_gains = HashMap::new();
-        let mut answered = HashSet::new();
-        let mut points = 1000;
-
-        // Save values
-        let question_time = question.time as u64;
-        let choices = question.choices.clone();
-        let answer = question.answer;
-
-        // Alert host that the round began
-        tracing::debug!("Alerting host that round began...");
-        let _ = host_tx.send(HostEvent::RoundBegin { question }.to_message()).await;
+    let roster = settings.roster.clone().map(|entries| Arc::new(Roster::new(entries)));
+    if let Some(roster) = &roster {
+        for name in &attended {
+            roster.mark_attended(name);
+        }
+    }
 
-        // Alert players a round began
-        tracing::debug!("Alerting players that round began...");
-        let _ = result_tx.send(GameEvent::RoundBegin { choices });
+    let room_teams = settings.teams.clone().map(Teams::new);
+    if let Some(room_teams) = &room_teams {
+        for (username, team) in &teams {
+            room_teams.assign(username, team);
+        }
+    }
 
-        // Keep taking from stream until it is empty
-        while let Ok(_) = action_rx.try_recv() { }
+    // The room's clock carries on from the time of day, like it did before
+    let since_created = Duration::from_millis(now_millis().saturating_sub(created_at));
+    let created = Instant::now().checked_sub(since_created).unwrap_or_else(Instant::now);
 
-        // Wait for round end event
-        let time_task = tokio::time::sleep(Duration::from_secs(question_time));
-        tokio::pin!(time_task);
-        loop {
-            // Pick whichever future resolves first
-            tokio::select! {
-                // Host force end
-                act = host_rx.next_action() => {
-                    match act {
-                        // If the host sent an end round action, exit loop
-                        Some(Action::EndRound) => {
-                            tracing::debug!("Host forcefully ended round");
-                            break;
-                        }
-                        // Ignore all other actions
-                        Some(_) => (),
+    let (socket_tx, socket_rx) = oneshot::channel();
+    let room = Arc::new(Room {
+        roster,
+        teams: room_teams,
+        settings,
+        users,
+        result_stream: result_rx,
+        action_stream: action_tx,
+        wager_stream: wager_tx,
+        time_multipliers: Mutex::new(time_multipliers),
+        seed,
+        created_at,
+        created,
+        recording: None,
+        questions,
+        quiz,
+        host_token,
+        tokens: Tokens::restore(tokens),
+        progress: Mutex::new(progress),
+        host_slot: Mutex::new(Some(socket_tx)),
+        closed: AtomicBool::new(false),
+    });
+
+    state.insert_room(room_id, Arc::clone(&room));
+
+    let (host_tx, mut host_messages) = mpsc::channel::<Message>(30);
+    let (host_actions, host_rx) = mpsc::channel::<Action>(20);
+    let keepalive = room.settings.auto_advance.as_ref().map(|_| host_actions.clone());
+    let (back_tx, host_back) = oneshot::channel();
+
+    // Hand the room to the host once they're back
+    {
+        let room = Arc::clone(&room);
+        tokio::spawn(async move {
+            let mut socket_rx = socket_rx;
+            let timeout = tokio::time::sleep(RESUME_TIMEOUT);
+            tokio::pin!(timeout);
 
-                        // If the action is none, the socket must have dc'd
-                        None => {
-                            tracing::debug!("Host disconnected...");
-                            state.remove_room(&room_id).await;
-                            return;
+            // Messages for the host are dropped until they're back, since
+            // they're brought up to date then
+            let socket = loop {
+                tokio::select! {
+                    socket = &mut socket_rx => break socket.ok(),
+                    _ = &mut timeout => break None,
+                    message = host_messages.recv() => {
+                        if message.is_none() {
+                            break None;
                         }
                     }
                 }
+            };
 
-                // Timeout
-                _ = (&mut time_task) => {
-                    tracing::debug!("Question timeout");
-                    break;
+            match socket {
+                Some(socket) => {
+                    tracing::debug!("Host is back in room `{room_id}`");
+                    connect_host(socket, Arc::clone(&room), host_messages, host_actions);
+                    let _ = back_tx.send(());
                 }
+                None => {
+                    tracing::debug!("Host didn't come back to room `{room_id}`");
+                    room.host_slot.lock().unwrap().take();
+                }
+            }
+        });
+    }
 
-                // User answers
-                Some(PlayerAnswer { username, choice }) = action_rx.recv() => {
-                    if answered.contains(&username) {
-                        continue;
-                    }
+    let channels =
+        Channels { actions: action_rx, wagers: wager_rx, results: result_tx, player_events: player_event_rx };
+    let host = HostLink { messages: host_tx, actions: host_rx, keepalive };
+    let restored = Restored { saved_at, host_back: room.settings.auto_advance.is_none().then_some(host_back) };
+    tokio::spawn(run_room(Arc::clone(state), room_id, room, channels, source, host, Some(restored)));
+}
 
-                    answered.insert(username.clone());
+/// Handles the host coming back to a room restored after a server restart.
+///
+/// The websocket will be treated as the room's "host" from now on.
+async fn resume_room(mut socket: impl Socket, state: SharedState, room_id: RoomId, token: String) {
+    let room = state.find_room(&room_id).filter(|room| room.host_token == token);
+    let slot = room.as_ref().and_then(|room| room.host_slot.lock().unwrap().take());
+
+    let (Some(room), Some(slot)) = (room, slot) else {
+        tracing::error!("Couldn't resume room `{room_id}`, disconnecting...");
+        let reason = String::from("There is no room to resume");
+        let _ = socket.send(HostEvent::ResumeFailed { reason }.to_message()).await;
+        return;
+    };
 
-                    // Tell host user answered
-                    let _ = host_tx.send(HostEvent::UserAnswered {
-                            username: username.clone()
-                        }.to_message())
-                        .await;
+    // Bring the host up to date
+    let mut players = room.users.names();
+    players.sort();
+    let game = room.progress.lock().unwrap().game.clone();
+    let (totals, rounds_played) = game.map_or_else(Default::default, |game| (game.totals, game.correct_rates.len()));
 
-                    tracing::debug!("`{username}` answered {choice}");
+    let event = HostEvent::RoomResumed { room_id, players, totals, rounds_played };
+    let _ = socket.send(event.to_message()).await;
 
-                    // If the choice is correct
-                    if choice == answer {
-                        // Update points log
-                        tracing::debug!("`{username}` +{points}");
-                        point_gains.insert(username, points);
+    let _ = slot.send(Box::new(socket));
+}
 
-                        // Decrease next point gain
-                        points = (points * 10 / 11).max(1);
-                    }
+/// Connects the host's socket to the game.
+///
+/// Everything sent on `messages` goes to the host. Room management actions are
+/// handled as soon as they arrive, and the rest are passed on to `actions`.
+fn connect_host(
+    host: impl Socket,
+    room: Arc<Room>,
+    mut messages: mpsc::Receiver<Message>,
+    actions: mpsc::Sender<Action>,
+) {
+    let (mut host_tx, mut host_rx) = host.split();
 
-                    // Has every player answered
-                    let all_answered = room
-                        .users
-                        .users
-                        .lock()
-                        .unwrap()
-                        .iter()
-                        .all(|name| answered.contains(name));
-
-                    // If everyone has answered, leave loop
-                    if all_answered {
-                        break;
-                    }
-                }
-            };
+    tokio::spawn(async move {
+        while let Some(msg) = messages.recv().await {
+            // If socket is closed
+            if host_tx.send(msg).await.is_err() {
+                return;
+            }
         }
-        drop(time_task);
-
-        tracing::debug!("End of round...");
 
-        // Tell host that the round ended
-        tracing::debug!("Alerting host that round ended...");
-        let _ = host_tx
-            .send(HostEvent::RoundEnd {
-                point_gains: point_gains.clone(),
-            }.to_message())
-            .await;
-
-        // Alert players round ended
-        tracing::debug!("Alerting players that round ended...");
-        let _ = result_tx.send(GameEvent::RoundEnd {
-            point_gains: Arc::new(point_gains),
-        });
+        // Close connection
+        let _ = host_tx.close().await;
+    });
 
-        // Wait until host begins next round
-        loop {
-            match host_rx.next_action().await {
-                // If action is begin round, break loop
-                Some(Action::BeginRound) => break,
-                
-                // If host sends irrelevant message, ignore
-                Some(_) => (),
+    tokio::spawn(async move {
+        while let Some(action) = host_rx.next_action().await {
+            match action {
+                Action::ApproveJoin { username } => {
+                    tracing::debug!("Host approved `{username}`");
+                    room.users.resolve_request(&username, true).await;
+                }
+                Action::DenyJoin { username } => {
+                    tracing::debug!("Host denied `{username}`");
+                    room.users.resolve_request(&username, false).await;
+                }
+                Action::ApproveAll => {
+                    let approved = room.users.approve_all().await;
+                    tracing::debug!("Host approved {approved} waiting users");
+                }
+                Action::AssignTeam { username, team } => {
+                    let assigned = room.users.is_present(&username)
+                        && room.teams.as_ref().is_some_and(|teams| teams.assign(&username, &team));
 
-                // If host dc's, close room
-                None => {
-                    tracing::debug!("Closing room...");
-                    state.remove_room(&room_id).await;
-                    return;
+                    if assigned {
+                        tracing::debug!("Host put `{username}` on team `{team}`");
+                        room.users.announce_team(&username, &team).await;
+                    }
+                }
+                Action::SetTimeMultiplier { username, multiplier } => {
+                    if room.set_time_multiplier(&username, multiplier) {
+                        tracing::debug!("Host gave `{username}` {multiplier}x time");
+                    }
+                }
+                // The host can only start rooms that advance by themselves
+                ref action if room.settings.auto_advance.is_some() && !matches!(action, Action::BeginRound) => (),
+                action => {
+                    // Stop if the game is over
+                    if actions.send(action).await.is_err() {
+                        return;
+                    }
                 }
             }
         }
-    }
-
-    tracing::debug!("Game is over!");
-
-    // Alert host that the game ended
-    tracing::debug!("Alerting host that game has ended...");
-    let _ = host_tx.send(HostEvent::GameEnd.to_message()).await;
-
-    // Stop sending heartbeat to host
-    heartbeat.abort();
+    });
+}
 
-    // Alert players game ended
-    let _ = result_tx.send(GameEvent::GameEnd);
+/// How a player joins a room.
+pub enum Joining {
+    /// As a new player, with the name they typed.
+    New { username: String, code: Option<String>, team: Option<String> },
+    /// As the player a rejoin token was given to.
+    Returning { token: String },
+}
 
-    state.remove_room(&room_id).await;
+impl Joining {
+    /// The action the player joined with.
+    fn action(&self, room_id: RoomId) -> Action {
+        match self {
+            Joining::New { username, code, team } => {
+                Action::JoinRoom { room_id, username: username.clone(), code: code.clone(), team: team.clone() }
+            }
+            Joining::Returning { token } => Action::RejoinRoom { room_id, token: token.clone() },
+        }
+    }
 }
 
 /// Handles room joining.
 ///
 /// The websocket will be treated as a "player" from now on.
-async fn join_room(mut socket: WebSocket, state: SharedState, room_id: RoomId, username: String) {
+async fn join_room(mut socket: impl Socket, state: SharedState, room_id: RoomId, joining: Joining) {
     tracing::debug!("Finding room `{room_id}`...");
     let room = if let Some(room) = state.find_room(&room_id) {
         room
     } else {
         tracing::error!("Couldn't find room `{room_id}`, disconnecting...");
-        let event = UserEvent::JoinFailed { reason: String::from("Room does not exist") };
+        let event = UserEvent::join_failed(JoinFailCode::RoomNotFound);
         let _ = socket.send(event.to_message()).await;
         return;
     };
 
     tracing::debug!("Joining room...");
 
+    // Write down everything the user does, starting with how they joined
+    let connection = room.recording.as_ref().map(|recording| {
+        let connection = recording.connect();
+        let action = joining.action(room_id);
+        recording.write(&recording::Entry::Received { at: recording.at(), connection, action });
+
+        (Arc::clone(recording), connection)
+    });
+    let socket = RecordedSocket::new(socket, connection);
+
     let (mut user_tx, mut user_rx) = socket.split();
+
+    // Wrap the user transmitter with an `mpsc` so the room can also send
+    // messages straight to the user
+    let user_tx = {
+        let (user_tx_mpsc, mut rx) = mpsc::channel::<Message>(30);
+
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                // If socket is closed
+                if user_tx.send(msg).await.is_err() {
+                    return;
+                }
+            }
+
+            // Close connection
+            let _ = user_tx.close().await;
+        });
+
+        user_tx_mpsc
+    };
+
+    // Players coming back are who their token says, and were let in before
+    let (username, code, team, token) = match joining {
+        Joining::New { username, code, team } => (username, code, team, None),
+        Joining::Returning { token } => match room.tokens.name_of(&token) {
+            Some(username) => (username, None, None, Some(token)),
+            None => {
+                tracing::error!("Rejoin token isn't from room `{room_id}`, disconnecting...");
+                let event = UserEvent::join_failed(JoinFailCode::UnknownToken);
+                let _ = user_tx.send(event.to_message()).await;
+                return;
+            }
+        },
+    };
+    let returning = token.is_some();
+
+    // Check the team the user picked
+    if let (Some(teams), false) = (&room.teams, returning) {
+        let picked = team.as_deref().is_some_and(|team| teams.exists(team));
+
+        if teams.settings().assignment == TeamAssignment::Pick && !picked {
+            tracing::error!("User `{username}` didn't pick a valid team, disconnecting...");
+            let event = UserEvent::join_failed(JoinFailCode::InvalidTeam);
+            let _ = user_tx.send(event.to_message()).await;
+            return;
+        }
+    }
+
+    // Check the name filter
+    let mut naming = Naming::Typed(username.clone());
+    let mut request = None;
+    if returning {
+        // Their name was already checked the first time they joined
+    } else if let Some(roster) = &room.roster {
+        // Rosters are set by the host, so there's nothing to filter
+        match roster.find(&username, code.as_deref()) {
+            Ok(entry) => naming = Naming::Typed(entry.name.clone()),
+            Err(code) => {
+                tracing::error!("User `{username}` couldn't join ({code:?}), disconnecting...");
+                let event = UserEvent::join_failed(code);
+                let _ = user_tx.send(event.to_message()).await;
+                return;
+            }
+        }
+    } else if room.settings.friendly_names {
+        // Typed names are ignored, so there's nothing to filter
+        naming = Naming::Friendly;
+    } else if state.name_filter.is_blocked(&username) {
+        match room.settings.name_filter {
+            FilterAction::Reject => {
+                tracing::error!("User `{username}` is not allowed, disconnecting...");
+                let event = UserEvent::join_failed(JoinFailCode::NameNotAllowed);
+                let _ = user_tx.send(event.to_message()).await;
+                return;
+            }
+            FilterAction::Rename => {
+                tracing::debug!("Renaming `{username}` to a placeholder");
+                naming = Naming::Placeholder;
+            }
+            FilterAction::Approve => request = Some(JoinRequestReason::NameFiltered),
+        }
+    }
+
+    // Everyone else still has to get through the waiting room
+    if room.settings.waiting_room && request.is_none() && !returning {
+        request = Some(JoinRequestReason::WaitingRoom);
+    }
+
     // Whenever the presence gets dropped (when the function returns),
     // a leave message is automatically sent to the host.
-    let _presence = if let Some(presence) = room.users.join_user(username.clone()).await {
-        presence
-    } else {
-        tracing::error!("User `{username}` already exists, disconnecting...");
-        let event = UserEvent::JoinFailed { reason: String::from("Duplicate user") };
-        let _ = user_tx.send(event.to_message()).await;
-        return;
+    let mut presence = match room.users.join_user(naming, request, user_tx.clone(), returning).await {
+        Ok(presence) => presence,
+        Err(code) => {
+            tracing::error!("User `{username}` couldn't join ({code:?}), disconnecting...");
+            let event = UserEvent::join_failed(code);
+            let _ = user_tx.send(event.to_message()).await;
+            return;
+        }
     };
 
-    // Emit joined event to user
-    let event = UserEvent::Joined;
+    // Use the cleaned up name from now on
+    let username = String::from(presence.name());
+
+    // Wait for the host to let the user in
+    if let Some(approval) = presence.approval() {
+        tracing::debug!("Waiting for host to approve `{username}`...");
+
+        let mut event_watch = room.result_stream.clone();
+        let approved = tokio::select! {
+            res = approval => res.unwrap_or(false),
+            // Ignore anything the user sends while waiting, but stop if they leave
+            _ = async { while user_rx.next_action().await.is_some() {} } => false,
+            // Stop if the host leaves
+            _ = async { while event_watch.changed().await.is_ok() {} } => false,
+        };
+
+        if !approved {
+            tracing::debug!("`{username}` wasn't let in, disconnecting...");
+            let event = UserEvent::join_failed(JoinFailCode::JoinDenied);
+            let _ = user_tx.send(event.to_message()).await;
+            return;
+        }
+    }
+
+    // Emit joined event to user, with the token to come back with, and keep
+    // their name for them from now on
+    let token = token.or_else(|| room.tokens.issue(&username));
+    room.users.reserve(&username);
+    let event = UserEvent::Joined { username: username.clone(), token };
     let _ = user_tx.send(event.to_message()).await;
 
+    // Let the user know how long they'll be waiting for a scheduled game
+    let in_lobby = matches!(*room.result_stream.borrow(), GameEvent::InLobby);
+    if let (Some(starts_at), true) = (room.settings.starts_at, in_lobby) {
+        let event = UserEvent::Countdown { starts_in: starts_at.saturating_sub(room.now_millis()) };
+        let _ = user_tx.send(event.to_message()).await;
+    }
+
+    // Put the user on a team
+    if let Some(teams) = &room.teams {
+        let team = match teams.settings().assignment {
+            // Users who come back with a token stay on their team
+            _ if returning => teams.team_of(&username),
+            TeamAssignment::Auto => teams.auto_assign(&username),
+            TeamAssignment::Pick => team.filter(|team| teams.assign(&username, team)),
+            // Users who come back stay on the team the host put them on
+            TeamAssignment::Host => teams.team_of(&username),
+        };
+
+        if let Some(team) = team {
+            room.users.announce_team(&username, &team).await;
+        }
+    }
+
+    // Keeps track of the user's name, in case they reroll it
+    let (name_tx, name_rx) = watch::channel(username);
+
     // Watch for game status updates
     let mut game_event_task = {
         let mut event_watch = room.result_stream.clone();
-        let username = username.clone();
+        let user_tx = user_tx.clone();
+        let room = Arc::clone(&room);
         tokio::spawn(async move {
             loop {
                 let heartbeat = tokio::time::sleep(Duration::from_secs(25));
@@ -372,10 +793,8 @@ async fn join_room(mut socket: WebSocket, state: SharedState, room_id: RoomId, u
                 tokio::select! {
                     // Game status changed
                     res = event_watch.changed() => {
-                        // Host dc'd
+                        // Host dc'd, close connection
                         if res.is_err() {
-                            // Close connection
-                            let _ = user_tx.close().await;
                             return;
                         }
 
@@ -386,19 +805,35 @@ async fn join_room(mut socket: WebSocket, state: SharedState, room_id: RoomId, u
                                 tracing::debug!("Game ended, closing user connection...");
                                 let event = UserEvent::GameEnd;
                                 let _ = user_tx.send(event.to_message()).await;
-                                
+
                                 // Close connection
-                                let _ = user_tx.close().await;
                                 return;
                             }
-                            GameEvent::RoundBegin { choices } => {
-                                let event = UserEvent::RoundBegin { choices };
+                            GameEvent::RoundBegin { round, mut choices, time, elapsed } => {
+                                let username = name_rx.borrow().clone();
+                                let multiplier = room.time_multiplier(&username);
+                                let time = (f64::from(time) * multiplier - elapsed.as_secs_f64()).max(0.0).ceil() as u16;
+
+                                // Every player gets their own order, so they can't
+                                // copy their neighbour's button
+                                if room.settings.shuffle_choices {
+                                    let order = shuffle::choice_order(room.seed, round, &username, choices.len());
+                                    choices = order.into_iter().map(|index| choices[index].clone()).collect();
+                                }
+
+                                let event = UserEvent::RoundBegin { choices, time };
                                 let _ = user_tx.send(event.to_message()).await;
                             }
-                            GameEvent::RoundEnd { point_gains } => {
+                            GameEvent::RoundEnd { point_gains, eliminated } => {
+                                let username = name_rx.borrow().clone();
+
                                 let point_gain = point_gains.get(&username).copied();
                                 let event = UserEvent::RoundEnd { point_gain };
                                 let _ = user_tx.send(event.to_message()).await;
+
+                                if eliminated.contains(&username) {
+                                    let _ = user_tx.send(UserEvent::Eliminated.to_message()).await;
+                                }
                             }
                             GameEvent::InLobby => (),
                         }
@@ -415,16 +850,46 @@ async fn join_room(mut socket: WebSocket, state: SharedState, room_id: RoomId, u
 
     // Feed user answers into action stream for the host to deal with
     let mut user_action_task = {
-        let action_stream = room.action_stream.clone();
+        let room = Arc::clone(&room);
+        let reply_tx = user_tx.clone();
+
         tokio::spawn(async move {
             while let Some(action) = user_rx.next_action().await {
-                if let Action::Answer { choice } = action {
-                    let _ = action_stream
-                        .send(PlayerAnswer {
-                            username: username.clone(),
-                            choice,
-                        })
-                        .await;
+                match action {
+                    Action::Answer { choice } => {
+                        let _ = room
+                            .action_stream
+                            .send(PlayerAnswer { username: String::from(presence.name()), choice })
+                            .await;
+                    }
+                    Action::PlaceWager { amount } => {
+                        let _ = room
+                            .wager_stream
+                            .send(PlayerWager { username: String::from(presence.name()), amount })
+                            .await;
+                    }
+                    Action::RerollName => {
+                        let in_lobby = matches!(*room.result_stream.borrow(), GameEvent::InLobby);
+
+                        // Names can only change before the game starts
+                        if !room.settings.friendly_names || !in_lobby {
+                            continue;
+                        }
+
+                        // Rerolls are counted per player, so rejoining doesn't
+                        // give any back
+                        let old = String::from(presence.name());
+                        let limit = room.settings.name_rerolls;
+                        let Some((username, rerolls_left)) = room.users.reroll_name(&mut presence, limit).await else {
+                            continue;
+                        };
+                        room.tokens.rename(&old, &username);
+                        let _ = name_tx.send(username.clone());
+
+                        let event = UserEvent::Renamed { username, rerolls_left };
+                        let _ = reply_tx.send(event.to_message()).await;
+                    }
+                    _ => (),
                 }
             }
         })
@@ -440,20 +905,41 @@ async fn join_room(mut socket: WebSocket, state: SharedState, room_id: RoomId, u
 /// Websocket api testing
 #[cfg(test)]
 mod tests {
-    use crate::ws::router;
-    use crate::ws::api::{Action, HostEvent, UserEvent, Question};
+    use crate::app_with_state;
+    use crate::quiz::import::Imported;
+    use crate::quiz::store::QuizStore;
+    use crate::quiz::{Quiz, QuizDraft, QuizSummary};
+    use crate::results::store::MemoryResults;
+    use crate::results::{GameRecord, GameSummary, Outcome};
+    use crate::ws::api::{
+        Action, Adaptive, AnswerChanges, AutoAdvance, ChallengeResults, Difficulty, FilterAction, HostEvent,
+        JoinFailCode, JoinRequestReason, Question, RoomSettings, RosterEntry, Selection, TeamAssignment, TeamScoring,
+        TeamSettings, UserEvent, WagerOutcome,
+    };
+    use crate::ws::challenge::Challenge;
+    use crate::ws::filter::WordList;
+    use crate::ws::game::LOBBY_IDLE_TIMEOUT;
+    use crate::ws::recording::{Entry, Recordings};
+    use crate::ws::snapshot::{self, Snapshots};
+    use crate::ws::state::{SharedState, State};
+    use crate::ws::{now_millis, replay, shuffle};
 
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use futures::{SinkExt, StreamExt};
+    use serde::Serialize;
     use std::collections::HashSet;
+    use std::path::Path;
     use std::sync::atomic::{AtomicU16, Ordering};
+    use std::sync::Arc;
     use std::{net::SocketAddr, time::Duration};
     use tokio::net::TcpStream;
-    use tokio_tungstenite::{connect_async, tungstenite::Message, WebSocketStream, MaybeTlsStream};
-    use futures::{StreamExt, SinkExt};
-    use serde::Serialize;
+    use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
 
     // `let_assert` is a useful testing macro asserting a specific enum variant
     // and destructuring the variant to get its inner value.
     use assert2::let_assert;
+    use tempfile::TempDir;
 
     use super::api::RoomId;
 
@@ -461,6 +947,10 @@ mod tests {
 
     struct TestServer {
         port: u16,
+        /// Where the server keeps its quizzes and recordings, removed when the
+        /// test ends.
+        dir: TempDir,
+        state: SharedState,
     }
 
     struct HostSocket(SocketStream);
@@ -470,11 +960,62 @@ mod tests {
 
     impl TestServer {
         async fn new() -> Self {
+            Self::with_name_filter(WordList::from_env()).await
+        }
+
+        /// Starts a server which filters out the word "badword".
+        async fn with_filter() -> Self {
+            Self::with_name_filter(WordList::new(["badword"])).await
+        }
+
+        /// Starts a server which records rooms into `recordings` in its
+        /// directory.
+        async fn recording() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let recordings = Recordings::new(dir.path().join("recordings"));
+            let state = State::new(
+                WordList::from_env(),
+                QuizStore::new(dir.path()),
+                MemoryResults::default(),
+                Some(recordings),
+                None,
+            );
+
+            Self::with_state(state, dir).await
+        }
+
+        /// Starts a server which keeps snapshots of its rooms in
+        /// `snapshots`, after restoring the rooms already there.
+        ///
+        /// Snapshots are only taken when tests take them.
+        async fn snapshotting(snapshots: &Path) -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let state = State::new(
+                WordList::from_env(),
+                QuizStore::new(dir.path()),
+                MemoryResults::default(),
+                None,
+                Some(Snapshots::new(snapshots)),
+            );
+            snapshot::restore_all(&state);
+
+            Self::with_state(state, dir).await
+        }
+
+        async fn with_name_filter(name_filter: WordList) -> Self {
+            let quiz_dir = tempfile::tempdir().unwrap();
+            let state = State::new(name_filter, QuizStore::new(quiz_dir.path()), MemoryResults::default(), None, None);
+
+            Self::with_state(state, quiz_dir).await
+        }
+
+        async fn with_state(state: SharedState, dir: TempDir) -> Self {
             let port = PORT.fetch_add(1, Ordering::Relaxed);
+            let router = app_with_state(Arc::clone(&state));
 
             tokio::spawn(async move {
                 axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], port)))
-                    .serve(router().into_make_service())
+                    .serve(router.into_make_service())
                     .await
                     .unwrap();
             });
@@ -483,22 +1024,24 @@ mod tests {
             // TODO: Make this wait for the server to open, not for a specific amount of time
             tokio::time::sleep(Duration::from_secs(1)).await;
 
-            Self { port }
+            Self { port, dir, state }
         }
 
         async fn connect(&self) -> SocketStream {
-            let (ws, _) = connect_async(format!("ws://127.0.0.1:{}", self.port))
-                .await
-                .unwrap();
+            let (ws, _) = connect_async(format!("ws://127.0.0.1:{}/ws", self.port)).await.unwrap();
 
             ws
         }
 
         async fn create_room(&self, questions: Vec<Question>) -> (HostSocket, RoomId) {
+            self.create_room_with(questions, RoomSettings::default()).await
+        }
+
+        async fn create_room_with(&self, questions: Vec<Question>, settings: RoomSettings) -> (HostSocket, RoomId) {
             let mut ws = self.connect().await;
 
             // Send create room action
-            ws.send(serial(&Action::CreateRoom { questions })).await.unwrap();
+            ws.send(serial(&Action::CreateRoom { questions, settings })).await.unwrap();
 
             // Response must be a text message with no errors
             let_assert!(Some(Ok(Message::Text(s))) = ws.next().await);
@@ -507,23 +1050,61 @@ mod tests {
             let event: HostEvent = serde_json::from_str(&s).unwrap();
 
             // Response must be a room created event
-            let_assert!(HostEvent::RoomCreated { room_id } = event);
+            let_assert!(HostEvent::RoomCreated { room_id, .. } = event);
 
             (HostSocket(ws), room_id)
         }
 
         async fn join_room(&self, room_id: RoomId, username: String) -> UserSocket {
+            self.join_room_with(room_id, username, None, None).await
+        }
+
+        async fn join_room_with(
+            &self,
+            room_id: RoomId,
+            username: String,
+            code: Option<String>,
+            team: Option<String>,
+        ) -> UserSocket {
             // Establish connection
             let mut ws = self.connect().await;
 
             // Send join room message
-            ws.send(serial(&Action::JoinRoom {
-                room_id,
-                username,
-            })).await.unwrap();
+            ws.send(serial(&Action::JoinRoom { room_id, username, code, team }))
+                .await
+                .unwrap();
 
             UserSocket(ws)
         }
+
+        /// Sends a `GET` request and returns the status code and body.
+        async fn get(&self, path: &str) -> (u16, String) {
+            self.request("GET", path, "").await
+        }
+
+        /// Sends a request with a JSON body and returns the status code and
+        /// body.
+        async fn request(&self, method: &str, path: &str, body: &str) -> (u16, String) {
+            let mut stream = TcpStream::connect(("127.0.0.1", self.port)).await.unwrap();
+
+            let request = format!(
+                "{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+                 Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len(),
+            );
+            stream.write_all(request.as_bytes()).await.unwrap();
+
+            // Bodies that aren't text, like spreadsheets, are mangled
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).await.unwrap();
+            let response = String::from_utf8_lossy(&response);
+
+            // eg. `HTTP/1.1 200 OK`
+            let status = response[9..12].parse().unwrap();
+            let (_, body) = response.split_once("\r\n\r\n").unwrap();
+
+            (status, String::from(body))
+        }
     }
 
     impl HostSocket {
@@ -586,6 +1167,7 @@ mod tests {
                     time: $time,
                     choices,
                     answer,
+                    ..Question::default()
                 }
             }
         };
@@ -621,7 +1203,7 @@ mod tests {
             host_ws.send(&Action::BeginRound).await;
 
             // Round begin event
-            let_assert!(HostEvent::RoundBegin { question } = host_ws.recv().await.unwrap());
+            let_assert!(HostEvent::RoundBegin { question, .. } = host_ws.recv().await.unwrap());
 
             // Check if the question is the same
             assert_eq!(question_clone, question);
@@ -633,7 +1215,7 @@ mod tests {
             assert_eq!("Johnny", &username);
 
             // Round end event
-            let_assert!(HostEvent::RoundEnd { point_gains } = host_ws.recv().await.unwrap());
+            let_assert!(HostEvent::RoundEnd { point_gains, .. } = host_ws.recv().await.unwrap());
 
             // Johnny gained 1000 points
             assert_eq!(point_gains.get("Johnny"), Some(&1000));
@@ -641,6 +1223,13 @@ mod tests {
             // Send begin round action
             host_ws.send(&Action::BeginRound).await;
 
+            // Game results event
+            let_assert!(HostEvent::GameResults { totals, absent, .. } = host_ws.recv().await.unwrap());
+
+            // Johnny has 1000 points in total and nobody is absent
+            assert_eq!(totals.get("Johnny"), Some(&1000));
+            assert!(absent.is_empty());
+
             // Game end event
             let_assert!(HostEvent::GameEnd = host_ws.recv().await.unwrap());
         });
@@ -649,10 +1238,11 @@ mod tests {
         let mut user_ws = server.join_room(room_id, String::from("Johnny")).await;
         let user_task = tokio::spawn(async move {
             // Joined event
-            assert_eq!(user_ws.recv().await.unwrap(), UserEvent::Joined);
+            let_assert!(UserEvent::Joined { username, .. } = user_ws.recv().await.unwrap());
+            assert_eq!(username, "Johnny");
 
             // Round begin event
-            let_assert!(UserEvent::RoundBegin { choices } = user_ws.recv().await.unwrap());
+            let_assert!(UserEvent::RoundBegin { choices, .. } = user_ws.recv().await.unwrap());
 
             // Has correct choice count
             assert_eq!(question.choices, choices);
@@ -678,14 +1268,14 @@ mod tests {
     async fn join_leave() {
         let server = TestServer::new().await;
 
-        let (mut host_ws, room_id) = server.create_room(vec![
-            question! {
+        let (mut host_ws, room_id) = server
+            .create_room(vec![question! {
                 "Fish?", time: 30 => [
                     true => "foo",
                     false => "bar",
                 ]
-            }
-        ]).await;
+            }])
+            .await;
 
         // Host tests
         let host_task = tokio::spawn(async move {
@@ -711,11 +1301,7 @@ mod tests {
                 }
             }
 
-            let names = HashSet::from([
-                String::from("Alice"),
-                String::from("Bob"),
-                String::from("Chris"),
-            ]);
+            let names = HashSet::from([String::from("Alice"), String::from("Bob"), String::from("Chris")]);
             assert_eq!(joined, names);
             assert_eq!(left, names);
         });
@@ -754,8 +1340,9 @@ mod tests {
         // Join non-existent room
         let mut user = server.join_room(0, String::from("Foo")).await;
 
-        let_assert!(UserEvent::JoinFailed { reason } = user.recv().await.unwrap());
+        let_assert!(UserEvent::JoinFailed { code, reason } = user.recv().await.unwrap());
 
+        assert_eq!(code, JoinFailCode::RoomNotFound);
         assert_eq!(reason, "Room does not exist");
     }
 
@@ -763,27 +1350,1515 @@ mod tests {
     async fn dupe_user() {
         // Start room
         let server = TestServer::new().await;
-        let (_host, room_id) = server.create_room(vec![
-            question! {
+        let (_host, room_id) = server
+            .create_room(vec![question! {
                 "Fish?", time: 30 => [
                     true => "foo",
                     false => "bar",
                 ]
-            }
-        ]).await;
+            }])
+            .await;
 
         // Join duplicate user
         let _user = server.join_room(room_id, String::from("Foo")).await;
         let mut user = server.join_room(room_id, String::from("Foo")).await;
 
-        let_assert!(UserEvent::JoinFailed { reason } = user.recv().await.unwrap());
+        let_assert!(UserEvent::JoinFailed { code, reason } = user.recv().await.unwrap());
 
+        assert_eq!(code, JoinFailCode::DuplicateUser);
         assert_eq!(reason, "Duplicate user");
     }
 
-    /// Convert a `Serialize`able into a JSON message.
-    fn serial(s: &impl Serialize) -> Message {
-        let json_string = serde_json::to_string(s).unwrap();
-        Message::text(json_string)
-    }
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn departed_name_is_kept() {
+        // Start room
+        let server = TestServer::new().await;
+        let (mut host, room_id) = server
+            .create_room(vec![question! {
+                "Fish?", time: 30 => [
+                    true => "foo",
+                    false => "bar",
+                ]
+            }])
+            .await;
+
+        // Foo joins and leaves
+        let mut foo = server.join_room(room_id, String::from("Foo")).await;
+        let_assert!(UserEvent::Joined { token: Some(token), .. } = foo.recv().await.unwrap());
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+        foo.leave().await;
+        let_assert!(HostEvent::UserLeft { .. } = host.recv().await.unwrap());
+
+        // Nobody else can take the name, or one like it
+        for name in ["Foo", "foo"] {
+            let mut impostor = server.join_room(room_id, String::from(name)).await;
+            let_assert!(UserEvent::JoinFailed { code, .. } = impostor.recv().await.unwrap());
+            assert_eq!(code, JoinFailCode::DuplicateUser);
+        }
+
+        // Foo can come back with their token, and keeps it
+        let mut foo = UserSocket(server.connect().await);
+        foo.send(&Action::RejoinRoom { room_id, token: token.clone() }).await;
+        let_assert!(UserEvent::Joined { username, token: rejoined } = foo.recv().await.unwrap());
+        assert_eq!(username, "Foo");
+        assert_eq!(rejoined, Some(token));
+    }
+
+    #[tokio::test]
+    async fn normalized_usernames() {
+        // Start room
+        let server = TestServer::new().await;
+        let (_host, room_id) = server
+            .create_room(vec![question! {
+                "Fish?", time: 30 => [
+                    true => "foo",
+                    false => "bar",
+                ]
+            }])
+            .await;
+
+        // Name gets cleaned up
+        let mut alice = server.join_room(room_id, String::from("  Alice\u{200B} ")).await;
+        let_assert!(UserEvent::Joined { username, .. } = alice.recv().await.unwrap());
+        assert_eq!(username, "Alice");
+
+        // Different case is still a duplicate
+        let mut user = server.join_room(room_id, String::from("alice")).await;
+        let_assert!(UserEvent::JoinFailed { code, .. } = user.recv().await.unwrap());
+        assert_eq!(code, JoinFailCode::DuplicateUser);
+
+        // Cyrillic look-alike
+        let mut user = server.join_room(room_id, String::from("\u{0410}lice")).await;
+        let_assert!(UserEvent::JoinFailed { code, .. } = user.recv().await.unwrap());
+        assert_eq!(code, JoinFailCode::ConfusableUser);
+
+        // Blank name
+        let mut user = server.join_room(room_id, String::from("\u{200B}")).await;
+        let_assert!(UserEvent::JoinFailed { code, .. } = user.recv().await.unwrap());
+        assert_eq!(code, JoinFailCode::UsernameEmpty);
+    }
+
+    /// Creates a room on a filtered server with the given filter action.
+    async fn filtered_room(action: FilterAction) -> (TestServer, HostSocket, RoomId) {
+        let server = TestServer::with_filter().await;
+        let settings = RoomSettings { name_filter: action, ..RoomSettings::default() };
+
+        let (host, room_id) = server
+            .create_room_with(
+                vec![question! {
+                    "Fish?", time: 30 => [
+                        true => "foo",
+                        false => "bar",
+                    ]
+                }],
+                settings,
+            )
+            .await;
+
+        (server, host, room_id)
+    }
+
+    #[tokio::test]
+    async fn filter_reject() {
+        let (server, _host, room_id) = filtered_room(FilterAction::Reject).await;
+
+        let mut user = server.join_room(room_id, String::from("B@dW0rd")).await;
+        let_assert!(UserEvent::JoinFailed { code, .. } = user.recv().await.unwrap());
+        assert_eq!(code, JoinFailCode::NameNotAllowed);
+
+        // Names that only have the word inside another word are fine
+        for name in ["Sinbadwordsmith", "B.A. Dwordle"] {
+            let mut user = server.join_room(room_id, String::from(name)).await;
+            let_assert!(UserEvent::Joined { username, .. } = user.recv().await.unwrap());
+            assert_eq!(username, name);
+        }
+    }
+
+    #[tokio::test]
+    async fn filter_rename() {
+        let (server, mut host, room_id) = filtered_room(FilterAction::Rename).await;
+
+        let mut user = server.join_room(room_id, String::from("badword")).await;
+        let_assert!(UserEvent::Joined { username, .. } = user.recv().await.unwrap());
+        assert_eq!(username, "Player 1");
+
+        let_assert!(HostEvent::UserJoined { username } = host.recv().await.unwrap());
+        assert_eq!(username, "Player 1");
+
+        // Ordinary names keep their name
+        let mut user = server.join_room(room_id, String::from("Sinbadwordsmith")).await;
+        let_assert!(UserEvent::Joined { username, .. } = user.recv().await.unwrap());
+        assert_eq!(username, "Sinbadwordsmith");
+    }
+
+    #[tokio::test]
+    async fn filter_approve() {
+        let (server, mut host, room_id) = filtered_room(FilterAction::Approve).await;
+
+        // Host lets the first user in
+        let mut user = server.join_room(room_id, String::from("badword")).await;
+        let_assert!(HostEvent::JoinRequested { username, reason } = host.recv().await.unwrap());
+        assert_eq!(username, "badword");
+        assert_eq!(reason, JoinRequestReason::NameFiltered);
+
+        host.send(&Action::ApproveJoin { username }).await;
+        let_assert!(UserEvent::Joined { username, .. } = user.recv().await.unwrap());
+        assert_eq!(username, "badword");
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+
+        // Host turns the second user away
+        let mut user = server.join_room(room_id, String::from("badwords")).await;
+        let_assert!(HostEvent::JoinRequested { username, .. } = host.recv().await.unwrap());
+
+        host.send(&Action::DenyJoin { username }).await;
+        let_assert!(UserEvent::JoinFailed { code, .. } = user.recv().await.unwrap());
+        assert_eq!(code, JoinFailCode::JoinDenied);
+    }
+
+    #[tokio::test]
+    async fn friendly_names() {
+        let server = TestServer::new().await;
+        let settings = RoomSettings { friendly_names: true, name_rerolls: 1, ..RoomSettings::default() };
+        let (mut host, room_id) = server
+            .create_room_with(
+                vec![question! {
+                    "Fish?", time: 30 => [
+                        true => "foo",
+                        false => "bar",
+                    ]
+                }],
+                settings,
+            )
+            .await;
+
+        // Typed name is ignored
+        let mut user = server.join_room(room_id, String::from("Johnny")).await;
+        let_assert!(UserEvent::Joined { username, token: Some(token) } = user.recv().await.unwrap());
+        assert_ne!(username, "Johnny");
+
+        let_assert!(HostEvent::UserJoined { username: joined } = host.recv().await.unwrap());
+        assert_eq!(joined, username);
+
+        // Reroll the name
+        user.send(&Action::RerollName).await;
+        let_assert!(UserEvent::Renamed { username: renamed, rerolls_left: 0 } = user.recv().await.unwrap());
+        assert_ne!(renamed, username);
+
+        let_assert!(HostEvent::UserRenamed { old_username, username: new_username } = host.recv().await.unwrap());
+        assert_eq!(old_username, username);
+        assert_eq!(new_username, renamed);
+
+        // Coming back doesn't give the reroll back
+        user.leave().await;
+        let_assert!(HostEvent::UserLeft { .. } = host.recv().await.unwrap());
+
+        let mut user = UserSocket(server.connect().await);
+        user.send(&Action::RejoinRoom { room_id, token }).await;
+        let_assert!(UserEvent::Joined { username, .. } = user.recv().await.unwrap());
+        assert_eq!(username, renamed);
+
+        user.send(&Action::RerollName).await;
+        assert!(tokio::time::timeout(Duration::from_millis(200), user.recv()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn friendly_names_at_once() {
+        let server = TestServer::new().await;
+        let settings = RoomSettings { friendly_names: true, ..RoomSettings::default() };
+        let (_host, room_id) = server
+            .create_room_with(
+                vec![question! {
+                    "Fish?", time: 30 => [
+                        true => "foo",
+                        false => "bar",
+                    ]
+                }],
+                settings,
+            )
+            .await;
+
+        // Everyone joining at once still gets in, with a name of their own
+        let joins = (0..20).map(|_| async {
+            let mut user = server.join_room(room_id, String::from("Johnny")).await;
+            let_assert!(UserEvent::Joined { username, .. } = user.recv().await.unwrap());
+            (user, username)
+        });
+        let joined = futures::future::join_all(joins).await;
+
+        let names: HashSet<_> = joined.iter().map(|(_, username)| username.clone()).collect();
+        assert_eq!(names.len(), joined.len());
+    }
+
+    #[tokio::test]
+    async fn waiting_room() {
+        let server = TestServer::new().await;
+        let settings = RoomSettings { waiting_room: true, ..RoomSettings::default() };
+        let (mut host, room_id) = server
+            .create_room_with(
+                vec![question! {
+                    "Fish?", time: 30 => [
+                        true => "foo",
+                        false => "bar",
+                    ]
+                }],
+                settings,
+            )
+            .await;
+
+        let mut alice = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(HostEvent::JoinRequested { username, reason } = host.recv().await.unwrap());
+        assert_eq!(username, "Alice");
+        assert_eq!(reason, JoinRequestReason::WaitingRoom);
+
+        let mut bob = server.join_room(room_id, String::from("Bob")).await;
+        let_assert!(HostEvent::JoinRequested { username, .. } = host.recv().await.unwrap());
+        assert_eq!(username, "Bob");
+
+        // Let everyone in at once
+        host.send(&Action::ApproveAll).await;
+        let_assert!(UserEvent::Joined { username, .. } = alice.recv().await.unwrap());
+        assert_eq!(username, "Alice");
+        let_assert!(UserEvent::Joined { username, .. } = bob.recv().await.unwrap());
+        assert_eq!(username, "Bob");
+
+        let mut joined = HashSet::new();
+        for _ in 0..2 {
+            let_assert!(HostEvent::UserJoined { username } = host.recv().await.unwrap());
+            joined.insert(username);
+        }
+        assert_eq!(joined, HashSet::from([String::from("Alice"), String::from("Bob")]));
+
+        // Start the game
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+        let_assert!(UserEvent::RoundBegin { .. } = alice.recv().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn roster() {
+        let server = TestServer::new().await;
+        let settings = RoomSettings {
+            roster: Some(vec![
+                // Roster names are cleaned up like typed ones
+                RosterEntry {
+                    name: String::from(" Alice\u{200B}"),
+                    id: None,
+                    code: Some(String::from("1234")),
+                    time_multiplier: None,
+                },
+                RosterEntry {
+                    name: String::from("Bob"),
+                    id: Some(String::from("S002")),
+                    code: None,
+                    time_multiplier: None,
+                },
+            ]),
+            ..RoomSettings::default()
+        };
+        let (mut host, room_id) = server
+            .create_room_with(
+                vec![question! {
+                    "Fish?", time: 30 => [
+                        false => "foo",
+                        true => "bar",
+                    ]
+                }],
+                settings,
+            )
+            .await;
+
+        // Not on the roster
+        let mut user = server.join_room(room_id, String::from("Carol")).await;
+        let_assert!(UserEvent::JoinFailed { code, .. } = user.recv().await.unwrap());
+        assert_eq!(code, JoinFailCode::NotOnRoster);
+
+        // Missing join code
+        let mut user = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::JoinFailed { code, .. } = user.recv().await.unwrap());
+        assert_eq!(code, JoinFailCode::WrongJoinCode);
+
+        // Join with a student id
+        let mut bob = server.join_room(room_id, String::from("s002")).await;
+        let_assert!(UserEvent::Joined { username, .. } = bob.recv().await.unwrap());
+        assert_eq!(username, "Bob");
+
+        let_assert!(HostEvent::UserJoined { username } = host.recv().await.unwrap());
+        assert_eq!(username, "Bob");
+        let_assert!(HostEvent::RosterUpdate { missing } = host.recv().await.unwrap());
+        assert_eq!(missing, ["Alice"]);
+
+        // Each entry can only join once
+        let mut user = server.join_room(room_id, String::from("Bob")).await;
+        let_assert!(UserEvent::JoinFailed { code, .. } = user.recv().await.unwrap());
+        assert_eq!(code, JoinFailCode::DuplicateUser);
+
+        // Play through the game without Alice
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+        let_assert!(UserEvent::RoundBegin { .. } = bob.recv().await.unwrap());
+        bob.send(&Action::Answer { choice: 1 }).await;
+        let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+        let_assert!(HostEvent::RoundEnd { .. } = host.recv().await.unwrap());
+        host.send(&Action::BeginRound).await;
+
+        let_assert!(HostEvent::GameResults { totals, absent, .. } = host.recv().await.unwrap());
+        assert_eq!(totals.get("Bob"), Some(&1000));
+        assert_eq!(absent, ["Alice"]);
+    }
+
+    #[tokio::test]
+    async fn teams() {
+        let server = TestServer::new().await;
+        let settings = RoomSettings {
+            teams: Some(TeamSettings {
+                names: vec![String::from("Red"), String::from("Blue")],
+                assignment: TeamAssignment::Auto,
+                scoring: TeamScoring::Sum,
+            }),
+            ..RoomSettings::default()
+        };
+        let (mut host, room_id) = server
+            .create_room_with(
+                vec![question! {
+                    "Fish?", time: 30 => [
+                        false => "foo",
+                        true => "bar",
+                    ]
+                }],
+                settings,
+            )
+            .await;
+
+        // Players are spread across the teams
+        let mut alice = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::Joined { .. } = alice.recv().await.unwrap());
+        assert_eq!(alice.recv().await.unwrap(), UserEvent::TeamAssigned { team: String::from("Red") });
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+        let_assert!(HostEvent::TeamAssigned { username, team } = host.recv().await.unwrap());
+        assert_eq!((username.as_str(), team.as_str()), ("Alice", "Red"));
+
+        let mut bob = server.join_room(room_id, String::from("Bob")).await;
+        let_assert!(UserEvent::Joined { .. } = bob.recv().await.unwrap());
+        assert_eq!(bob.recv().await.unwrap(), UserEvent::TeamAssigned { team: String::from("Blue") });
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+        let_assert!(HostEvent::TeamAssigned { .. } = host.recv().await.unwrap());
+
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+        let_assert!(UserEvent::RoundBegin { .. } = alice.recv().await.unwrap());
+        let_assert!(UserEvent::RoundBegin { .. } = bob.recv().await.unwrap());
+
+        // Blue is done once Bob answers
+        bob.send(&Action::Answer { choice: 1 }).await;
+        let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+        let_assert!(HostEvent::TeamAnswered { team } = host.recv().await.unwrap());
+        assert_eq!(team, "Blue");
+
+        alice.send(&Action::Answer { choice: 0 }).await;
+        let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+        let_assert!(HostEvent::TeamAnswered { team } = host.recv().await.unwrap());
+        assert_eq!(team, "Red");
+
+        // Blue is ahead
+        let_assert!(HostEvent::RoundEnd { team_standings: Some(standings), .. } = host.recv().await.unwrap());
+        assert_eq!(standings[0].team, "Blue");
+        assert_eq!(standings[0].score, 1000);
+        assert_eq!(standings[0].members, ["Bob"]);
+        assert_eq!(standings[1].team, "Red");
+        assert_eq!(standings[1].score, 0);
+    }
+
+    #[tokio::test]
+    async fn pick_team() {
+        let server = TestServer::new().await;
+        let settings = RoomSettings {
+            teams: Some(TeamSettings {
+                names: vec![String::from("Red"), String::from("Blue")],
+                assignment: TeamAssignment::Pick,
+                scoring: TeamScoring::Sum,
+            }),
+            ..RoomSettings::default()
+        };
+        let (_host, room_id) = server
+            .create_room_with(
+                vec![question! {
+                    "Fish?", time: 30 => [
+                        true => "foo",
+                        false => "bar",
+                    ]
+                }],
+                settings,
+            )
+            .await;
+
+        let mut user = server
+            .join_room_with(room_id, String::from("Alice"), None, Some(String::from("Green")))
+            .await;
+        let_assert!(UserEvent::JoinFailed { code, .. } = user.recv().await.unwrap());
+        assert_eq!(code, JoinFailCode::InvalidTeam);
+
+        let mut user = server
+            .join_room_with(room_id, String::from("Alice"), None, Some(String::from("Blue")))
+            .await;
+        let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+        assert_eq!(user.recv().await.unwrap(), UserEvent::TeamAssigned { team: String::from("Blue") });
+    }
+
+    #[tokio::test]
+    async fn elimination() {
+        let server = TestServer::new().await;
+        let settings = RoomSettings { elimination: true, ..RoomSettings::default() };
+        let question = question! {
+            "Fish?", time: 30 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+        let (mut host, room_id) = server.create_room_with(vec![question; 3], settings).await;
+
+        let mut players = Vec::new();
+        for name in ["Alice", "Bob", "Carol"] {
+            let mut user = server.join_room(room_id, String::from(name)).await;
+            let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+            let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+            players.push(user);
+        }
+
+        // Plays a round where each player picks the given choice
+        async fn play_round(host: &mut HostSocket, players: &mut [UserSocket], choices: [usize; 3]) {
+            host.send(&Action::BeginRound).await;
+            let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+
+            for (player, choice) in players.iter_mut().zip(choices) {
+                // Skip anything left over from the last round
+                while !matches!(player.recv().await.unwrap(), UserEvent::RoundBegin { .. }) {}
+                player.send(&Action::Answer { choice }).await;
+            }
+        }
+
+        // Bob gets it wrong
+        play_round(&mut host, &mut players, [1, 0, 1]).await;
+        for _ in 0..3 {
+            let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+        }
+        let_assert!(HostEvent::UserEliminated { username } = host.recv().await.unwrap());
+        assert_eq!(username, "Bob");
+        let_assert!(HostEvent::RoundEnd { survivors: Some(2), .. } = host.recv().await.unwrap());
+
+        let_assert!(UserEvent::RoundEnd { point_gain: None } = players[1].recv().await.unwrap());
+        assert_eq!(players[1].recv().await.unwrap(), UserEvent::Eliminated);
+
+        // Bob's answer doesn't count anymore, and Carol gets it wrong
+        play_round(&mut host, &mut players, [1, 1, 0]).await;
+        for _ in 0..2 {
+            let_assert!(HostEvent::UserAnswered { username } = host.recv().await.unwrap());
+            assert_ne!(username, "Bob");
+        }
+        let_assert!(HostEvent::UserEliminated { username } = host.recv().await.unwrap());
+        assert_eq!(username, "Carol");
+        let_assert!(HostEvent::RoundEnd { survivors: Some(1), .. } = host.recv().await.unwrap());
+
+        // Alice wins without playing the last question
+        let_assert!(HostEvent::GameResults { survivors: Some(survivors), .. } = host.recv().await.unwrap());
+        assert_eq!(survivors, ["Alice"]);
+        let_assert!(HostEvent::GameEnd = host.recv().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn elimination_knocks_out_everyone() {
+        let server = TestServer::new().await;
+        let settings = RoomSettings { elimination: true, ..RoomSettings::default() };
+        let question = question! {
+            "Fish?", time: 1 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+        let (mut host, room_id) = server.create_room_with(vec![question; 2], settings).await;
+
+        let mut alice = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::Joined { .. } = alice.recv().await.unwrap());
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+        let mut bob = server.join_room(room_id, String::from("Bob")).await;
+        let_assert!(UserEvent::Joined { .. } = bob.recv().await.unwrap());
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+
+        // Alice gets it wrong and Bob runs out of time
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+        let_assert!(UserEvent::RoundBegin { .. } = alice.recv().await.unwrap());
+        alice.send(&Action::Answer { choice: 0 }).await;
+        let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+
+        // Nobody got it right, so nobody is left
+        for name in ["Alice", "Bob"] {
+            let_assert!(HostEvent::UserEliminated { username } = host.recv().await.unwrap());
+            assert_eq!(username, name);
+        }
+        let_assert!(HostEvent::RoundEnd { survivors: Some(0), .. } = host.recv().await.unwrap());
+
+        let_assert!(HostEvent::GameResults { survivors: Some(survivors), .. } = host.recv().await.unwrap());
+        assert!(survivors.is_empty());
+        let_assert!(HostEvent::GameEnd = host.recv().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn auto_advance() {
+        let server = TestServer::new().await;
+        let settings =
+            RoomSettings { auto_advance: Some(AutoAdvance { pause: 1, min_players: 2 }), ..RoomSettings::default() };
+        let question = question! {
+            "Fish?", time: 1 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+        let (host, room_id) = server.create_room_with(vec![question; 2], settings).await;
+
+        // The game keeps going without the host
+        drop(host);
+
+        let mut alice = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::Joined { .. } = alice.recv().await.unwrap());
+
+        // Starts once Bob joins
+        let mut bob = server.join_room(room_id, String::from("Bob")).await;
+        let_assert!(UserEvent::Joined { .. } = bob.recv().await.unwrap());
+
+        for _ in 0..2 {
+            let_assert!(UserEvent::RoundBegin { .. } = alice.recv().await.unwrap());
+            alice.send(&Action::Answer { choice: 1 }).await;
+            let_assert!(UserEvent::RoundEnd { point_gain: Some(1000) } = alice.recv().await.unwrap());
+        }
+        assert_eq!(alice.recv().await.unwrap(), UserEvent::GameEnd);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_auto_advance() {
+        let server = TestServer::new().await;
+        let settings =
+            RoomSettings { auto_advance: Some(AutoAdvance { pause: 1, min_players: 2 }), ..RoomSettings::default() };
+        let question = question! {
+            "Fish?", time: 1 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+        let (host, room_id) = server.create_room_with(vec![question], settings).await;
+
+        // Someone in the lobby keeps the room open without the host
+        drop(host);
+        let mut alice = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::Joined { .. } = alice.recv().await.unwrap());
+        tokio::time::sleep(LOBBY_IDLE_TIMEOUT * 2).await;
+        assert!(server.state.find_room(&room_id).is_some());
+
+        // Nobody has come back for a while, so it closes
+        alice.leave().await;
+        tokio::time::sleep(LOBBY_IDLE_TIMEOUT - Duration::from_secs(60)).await;
+        assert!(server.state.find_room(&room_id).is_some());
+        tokio::time::sleep(Duration::from_secs(120)).await;
+        assert!(server.state.find_room(&room_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn scheduled_start() {
+        let server = TestServer::new().await;
+        let question = question! {
+            "Fish?", time: 30 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+
+        // Starts by itself once the time comes
+        let settings = RoomSettings { starts_at: Some(now_millis() + 1000), ..RoomSettings::default() };
+        let (mut host, room_id) = server.create_room_with(vec![question.clone()], settings).await;
+        let_assert!(HostEvent::Countdown { starts_in } = host.recv().await.unwrap());
+        assert!(starts_in > 0 && starts_in <= 1000);
+
+        let mut user = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+        let_assert!(UserEvent::Countdown { starts_in } = user.recv().await.unwrap());
+        assert!(starts_in <= 1000);
+
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+        let_assert!(UserEvent::RoundBegin { .. } = user.recv().await.unwrap());
+
+        // Not enough players, so the host has to start it
+        let settings = RoomSettings {
+            starts_at: Some(now_millis() + 1000),
+            auto_advance: Some(AutoAdvance { pause: 1, min_players: 2 }),
+            ..RoomSettings::default()
+        };
+        let (mut host, room_id) = server.create_room_with(vec![question.clone()], settings).await;
+        let_assert!(HostEvent::Countdown { .. } = host.recv().await.unwrap());
+
+        let mut user = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+
+        let_assert!(HostEvent::StartDelayed { players: 1, min_players: 2 } = host.recv().await.unwrap());
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+
+        // Rooms the host clicks through can ask for more players, too
+        let settings = RoomSettings { starts_at: Some(now_millis() + 1000), min_players: 2, ..RoomSettings::default() };
+        let (mut host, room_id) = server.create_room_with(vec![question], settings).await;
+        let_assert!(HostEvent::Countdown { .. } = host.recv().await.unwrap());
+
+        let mut user = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+
+        let_assert!(HostEvent::StartDelayed { players: 1, min_players: 2 } = host.recv().await.unwrap());
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn wagers() {
+        let server = TestServer::new().await;
+        let question = question! {
+            "Fish?", time: 30 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+        let wager_question = Question { wager: true, ..question.clone() };
+        let (mut host, room_id) = server.create_room(vec![question, wager_question]).await;
+
+        let mut alice = server.join_room(room_id, String::from("Alice")).await;
+        let mut bob = server.join_room(room_id, String::from("Bob")).await;
+        for user in [&mut alice, &mut bob] {
+            let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+            let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+        }
+
+        // Only Alice gets the first question right
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+        for (user, choice) in [(&mut alice, 1), (&mut bob, 0)] {
+            let_assert!(UserEvent::RoundBegin { .. } = user.recv().await.unwrap());
+            user.send(&Action::Answer { choice }).await;
+        }
+        for _ in 0..2 {
+            let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+        }
+        let_assert!(HostEvent::RoundEnd { wagers: None, .. } = host.recv().await.unwrap());
+
+        // Alice bets most of her points, and Bob has nothing to bet
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::WagerBegin { .. } = host.recv().await.unwrap());
+        for (user, amount, total) in [(&mut alice, 600, 1000), (&mut bob, 50, 0)] {
+            let_assert!(UserEvent::RoundEnd { .. } = user.recv().await.unwrap());
+            let_assert!(UserEvent::WagerBegin { total_points, .. } = user.recv().await.unwrap());
+            assert_eq!(total_points, total);
+            user.send(&Action::PlaceWager { amount }).await;
+        }
+        for _ in 0..2 {
+            let_assert!(HostEvent::WagerPlaced { .. } = host.recv().await.unwrap());
+        }
+
+        // Alice gets it wrong this time
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+        for (user, choice) in [(&mut alice, 0), (&mut bob, 1)] {
+            let_assert!(UserEvent::RoundBegin { .. } = user.recv().await.unwrap());
+            user.send(&Action::Answer { choice }).await;
+        }
+        for _ in 0..2 {
+            let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+        }
+        let_assert!(HostEvent::RoundEnd { wagers: Some(wagers), .. } = host.recv().await.unwrap());
+        assert_eq!(
+            wagers,
+            [
+                WagerOutcome { username: String::from("Alice"), wager: 600, correct: false },
+                WagerOutcome { username: String::from("Bob"), wager: 0, correct: true },
+            ]
+        );
+
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::GameResults { totals, .. } = host.recv().await.unwrap());
+        assert_eq!(totals["Alice"], 400);
+        assert_eq!(totals["Bob"], 0);
+    }
+
+    #[tokio::test]
+    async fn point_multipliers() {
+        let server = TestServer::new().await;
+        let settings = RoomSettings { negative_marking: 100, ..RoomSettings::default() };
+        let question = question! {
+            "Fish?", time: 30 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+        let questions = vec![Question { multiplier: 2, ..question.clone() }, Question { multiplier: 0, ..question }];
+        let (mut host, room_id) = server.create_room_with(questions, settings).await;
+
+        let mut alice = server.join_room(room_id, String::from("Alice")).await;
+        let mut bob = server.join_room(room_id, String::from("Bob")).await;
+        for user in [&mut alice, &mut bob] {
+            let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+            let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+        }
+
+        // Double points, and Bob loses double points
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+        for (user, choice) in [(&mut alice, 1), (&mut bob, 0)] {
+            let_assert!(UserEvent::RoundBegin { .. } = user.recv().await.unwrap());
+            user.send(&Action::Answer { choice }).await;
+        }
+        for _ in 0..2 {
+            let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+        }
+        let_assert!(HostEvent::RoundEnd { point_gains, .. } = host.recv().await.unwrap());
+        assert_eq!(point_gains.get("Alice"), Some(&2000));
+        assert_eq!(point_gains.get("Bob"), Some(&-200));
+        let_assert!(UserEvent::RoundEnd { point_gain: Some(-200) } = bob.recv().await.unwrap());
+
+        // Nothing to win or lose
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+        for (user, choice) in [(&mut alice, 0), (&mut bob, 1)] {
+            while !matches!(user.recv().await.unwrap(), UserEvent::RoundBegin { .. }) {}
+            user.send(&Action::Answer { choice }).await;
+        }
+        for _ in 0..2 {
+            let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+        }
+        let_assert!(HostEvent::RoundEnd { point_gains, .. } = host.recv().await.unwrap());
+        assert_eq!(point_gains.get("Alice"), None);
+        assert_eq!(point_gains.get("Bob"), Some(&0));
+
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::GameResults { totals, .. } = host.recv().await.unwrap());
+        assert_eq!(totals["Alice"], 2000);
+        assert_eq!(totals["Bob"], -200);
+    }
+
+    #[tokio::test]
+    async fn answer_changes() {
+        let server = TestServer::new().await;
+        let settings = RoomSettings { answer_changes: Some(AnswerChanges::default()), ..RoomSettings::default() };
+        let question = question! {
+            "Fish?", time: 2 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+        let (mut host, room_id) = server.create_room_with(vec![question], settings).await;
+
+        let mut alice = server.join_room(room_id, String::from("Alice")).await;
+        let mut bob = server.join_room(room_id, String::from("Bob")).await;
+        for user in [&mut alice, &mut bob] {
+            let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+            let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+        }
+
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+        for (user, choice) in [(&mut alice, 0), (&mut bob, 1)] {
+            let_assert!(UserEvent::RoundBegin { .. } = user.recv().await.unwrap());
+            user.send(&Action::Answer { choice }).await;
+            let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+        }
+
+        // Alice changes her mind after Bob answered, so she's slower
+        alice.send(&Action::Answer { choice: 1 }).await;
+
+        // The round keeps going until the time runs out
+        let_assert!(HostEvent::RoundEnd { point_gains, .. } = host.recv().await.unwrap());
+        assert_eq!(point_gains.get("Bob"), Some(&1000));
+        assert_eq!(point_gains.get("Alice"), Some(&909));
+    }
+
+    #[tokio::test]
+    async fn extra_time() {
+        let server = TestServer::new().await;
+        let question = question! {
+            "Fish?", time: 1 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+        let (mut host, room_id) = server.create_room(vec![question]).await;
+
+        let mut alice = server.join_room(room_id, String::from("Alice")).await;
+        let mut bob = server.join_room(room_id, String::from("Bob")).await;
+        for user in [&mut alice, &mut bob] {
+            let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+            let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+        }
+
+        // Alice gets double time
+        host.send(&Action::SetTimeMultiplier { username: String::from("Alice"), multiplier: 2.0 })
+            .await;
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+        let_assert!(UserEvent::RoundBegin { time: 2, .. } = alice.recv().await.unwrap());
+        let_assert!(UserEvent::RoundBegin { time: 1, .. } = bob.recv().await.unwrap());
+
+        // Alice can still answer after Bob's time is up, and the round ends
+        // as soon as she does
+        tokio::time::sleep(Duration::from_millis(1300)).await;
+        alice.send(&Action::Answer { choice: 1 }).await;
+        let_assert!(HostEvent::UserAnswered { username } = host.recv().await.unwrap());
+        assert_eq!(username, "Alice");
+        let_assert!(HostEvent::RoundEnd { point_gains, .. } = host.recv().await.unwrap());
+        assert_eq!(point_gains.get("Alice"), Some(&1000));
+    }
+
+    #[tokio::test]
+    async fn shuffled_choices() {
+        let server = TestServer::new().await;
+        let settings =
+            RoomSettings { shuffle_questions: true, shuffle_choices: true, seed: Some(42), ..RoomSettings::default() };
+        let question = question! {
+            "Fish?", time: 30 => [
+                false => "foo",
+                false => "bar",
+                false => "baz",
+                true => "qux",
+            ]
+        };
+        let (mut host, room_id) = server.create_room_with(vec![question], settings).await;
+
+        let mut user = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+
+        // Alice picks the right answer wherever it ended up
+        let_assert!(UserEvent::RoundBegin { choices, .. } = user.recv().await.unwrap());
+        let expected: Vec<_> = shuffle::choice_order(42, 0, "Alice", 4)
+            .into_iter()
+            .map(|index| ["foo", "bar", "baz", "qux"][index])
+            .collect();
+        assert_eq!(choices, expected);
+
+        // A button that isn't there doesn't count as answering
+        user.send(&Action::Answer { choice: 4 }).await;
+
+        let choice = choices.iter().position(|choice| choice == "qux").unwrap();
+        user.send(&Action::Answer { choice }).await;
+
+        let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+        let_assert!(HostEvent::RoundEnd { point_gains, .. } = host.recv().await.unwrap());
+        assert_eq!(point_gains.get("Alice"), Some(&1000));
+
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::GameResults { seed: Some(42), .. } = host.recv().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn question_pool() {
+        let server = TestServer::new().await;
+        let question = question! {
+            "Fish?", time: 30 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+        let pool: Vec<_> = ["q1", "q2", "q3"]
+            .into_iter()
+            .map(|id| Question {
+                id: Some(String::from(id)),
+                tags: vec![String::from(if id == "q2" { "fish" } else { "birds" })],
+                ..question.clone()
+            })
+            .collect();
+
+        // Not enough fish questions
+        let selection = Selection { count: 2, tags: vec![String::from("fish")], ..Selection::default() };
+        let mut host = HostSocket(server.connect().await);
+        host.send(&Action::CreateRoom {
+            questions: pool.clone(),
+            settings: RoomSettings { selection: Some(selection), ..RoomSettings::default() },
+        })
+        .await;
+        let_assert!(HostEvent::CreateFailed { .. } = host.recv().await.unwrap());
+
+        let selection = Selection { count: 1, tags: vec![String::from("fish")], ..Selection::default() };
+        let settings = RoomSettings { selection: Some(selection), seed: Some(7), ..RoomSettings::default() };
+        let (mut host, room_id) = server.create_room_with(pool, settings).await;
+
+        let mut user = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { question, .. } = host.recv().await.unwrap());
+        assert_eq!(question.id.as_deref(), Some("q2"));
+        host.send(&Action::EndRound).await;
+        let_assert!(HostEvent::RoundEnd { .. } = host.recv().await.unwrap());
+
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::GameResults { seed: Some(7), question_ids: Some(ids), .. } = host.recv().await.unwrap());
+        assert_eq!(ids, ["q2"]);
+    }
+
+    #[tokio::test]
+    async fn adaptive() {
+        let server = TestServer::new().await;
+        let question = question! {
+            "Fish?", time: 30 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+        let pool: Vec<_> = Difficulty::ALL
+            .into_iter()
+            .map(|difficulty| Question { difficulty: Some(difficulty), ..question.clone() })
+            .collect();
+        let settings =
+            RoomSettings { adaptive: Some(Adaptive { count: 2, ..Adaptive::default() }), ..RoomSettings::default() };
+        let (mut host, room_id) = server.create_room_with(pool, settings).await;
+
+        let mut user = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { pick: Some(pick), .. } = host.recv().await.unwrap());
+        assert_eq!(pick.difficulty, Difficulty::Medium);
+        let_assert!(UserEvent::RoundBegin { .. } = user.recv().await.unwrap());
+        user.send(&Action::Answer { choice: 1 }).await;
+        let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+        let_assert!(HostEvent::RoundEnd { .. } = host.recv().await.unwrap());
+
+        // Everyone got it right, so it gets harder
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { pick: Some(pick), .. } = host.recv().await.unwrap());
+        assert_eq!(pick.difficulty, Difficulty::Hard);
+        assert_eq!(pick.correct_rate, Some(1.0));
+        host.send(&Action::EndRound).await;
+        let_assert!(HostEvent::RoundEnd { .. } = host.recv().await.unwrap());
+
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::GameResults { question_ids: Some(ids), .. } = host.recv().await.unwrap());
+        assert_eq!(ids, ["1", "2"]);
+    }
+
+    #[tokio::test]
+    async fn invalid_quiz() {
+        let server = TestServer::new().await;
+        let question = question! {
+            "Fish?", time: 30 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+
+        let mut host = HostSocket(server.connect().await);
+        host.send(&Action::CreateRoom {
+            questions: vec![Question { answer: 2, ..question.clone() }],
+            settings: RoomSettings::default(),
+        })
+        .await;
+        let_assert!(HostEvent::CreateFailed { reason } = host.recv().await.unwrap());
+        assert_eq!(reason, "Question 1: The answer isn't one of the choices");
+
+        // Points too big to add up
+        let mut host = HostSocket(server.connect().await);
+        host.send(&Action::CreateRoom {
+            questions: vec![Question { points: u32::MAX, ..question }],
+            settings: RoomSettings::default(),
+        })
+        .await;
+        let_assert!(HostEvent::CreateFailed { reason } = host.recv().await.unwrap());
+        assert_eq!(reason, "Question 1: Questions can be worth at most 10000 points");
+    }
+
+    #[tokio::test]
+    async fn quiz_library() {
+        let server = TestServer::new().await;
+        let draft = QuizDraft {
+            title: String::from("Fish"),
+            questions: vec![question! {
+                "Fish?", time: 30 => [
+                    false => "foo",
+                    true => "bar",
+                ]
+            }],
+        };
+
+        // Quizzes that can't be played are turned away
+        let empty = QuizDraft { questions: Vec::new(), ..draft.clone() };
+        let (status, _) = server
+            .request("POST", "/quizzes", &serde_json::to_string(&empty).unwrap())
+            .await;
+        assert_eq!(status, 400);
+
+        let (status, body) = server
+            .request("POST", "/quizzes", &serde_json::to_string(&draft).unwrap())
+            .await;
+        assert_eq!(status, 201);
+        let quiz: Quiz = serde_json::from_str(&body).unwrap();
+        assert_eq!(quiz.version, 1);
+
+        let renamed = QuizDraft { title: String::from("More fish"), ..draft.clone() };
+        let path = format!("/quizzes/{}", quiz.id);
+        let (status, body) = server.request("PUT", &path, &serde_json::to_string(&renamed).unwrap()).await;
+        assert_eq!(status, 200);
+        assert_eq!(serde_json::from_str::<Quiz>(&body).unwrap().version, 2);
+
+        let (_, body) = server.get("/quizzes").await;
+        let list: Vec<QuizSummary> = serde_json::from_str(&body).unwrap();
+        assert_eq!(list, [QuizSummary { title: String::from("More fish"), version: 2, ..list[0].clone() }]);
+
+        // Older versions are kept
+        let (_, body) = server.get(&format!("{path}/versions/1")).await;
+        assert_eq!(serde_json::from_str::<Quiz>(&body).unwrap(), quiz);
+
+        let mut host = HostSocket(server.connect().await);
+        host.send(&Action::CreateRoomFromQuiz {
+            quiz_id: quiz.id,
+            version: Some(1),
+            settings: RoomSettings::default(),
+        })
+        .await;
+        let_assert!(HostEvent::RoomCreated { room_id, .. } = host.recv().await.unwrap());
+
+        let mut user = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { question, .. } = host.recv().await.unwrap());
+        assert_eq!(question, draft.questions[0]);
+
+        let (status, _) = server.request("DELETE", &path, "").await;
+        assert_eq!(status, 204);
+        let (status, _) = server.get(&path).await;
+        assert_eq!(status, 404);
+
+        let mut host = HostSocket(server.connect().await);
+        host.send(&Action::CreateRoomFromQuiz { quiz_id: quiz.id, version: None, settings: RoomSettings::default() })
+            .await;
+        let_assert!(HostEvent::CreateFailed { .. } = host.recv().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn import_quiz() {
+        let server = TestServer::new().await;
+        let source = "What colour is a goldfish?\nA. Blue\nB. Orange\nANSWER: B\n\nWhich fish?\nA. Trout\nANSWER: A\n";
+
+        let (status, body) = server.request("POST", "/quizzes/import/aiken", source).await;
+        assert_eq!(status, 200);
+
+        let imported: Imported = serde_json::from_str(&body).unwrap();
+        assert_eq!(imported.questions.len(), 1);
+        assert_eq!(imported.questions[0].answer, 1);
+        assert_eq!(imported.warnings[0].item, Some(2));
+
+        let (status, _) = server.request("POST", "/quizzes/import/qti", "<html></html>").await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn export_quiz() {
+        let server = TestServer::new().await;
+        let questions = vec![question! {
+            "Fish?", time: 30 => [
+                false => "foo",
+                true => "bar",
+            ]
+        }];
+
+        let draft = QuizDraft { title: String::from("Fish"), questions: questions.clone() };
+        let (_, body) = server
+            .request("POST", "/quizzes", &serde_json::to_string(&draft).unwrap())
+            .await;
+        let quiz: Quiz = serde_json::from_str(&body).unwrap();
+
+        let (status, body) = server.get(&format!("/quizzes/{}/export/aiken?version=1", quiz.id)).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, "Fish?\nA. foo\nB. bar\nANSWER: B\n\n");
+
+        // `createRoom` messages can be exported too
+        let create_room = Action::CreateRoom { questions, settings: RoomSettings::default() };
+        let (status, body) = server
+            .request("POST", "/quizzes/export/gift", &serde_json::to_string(&create_room).unwrap())
+            .await;
+        assert_eq!(status, 200);
+        assert!(body.contains("Fish? {\n    ~foo\n    =bar\n}"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn game_history() {
+        let server = TestServer::new().await;
+        let question = question! {
+            "Fish?", time: 30 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+
+        async fn play_round(host: &mut HostSocket, user: &mut UserSocket, choice: usize) {
+            host.send(&Action::BeginRound).await;
+            let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+            let_assert!(UserEvent::RoundBegin { .. } = user.recv().await.unwrap());
+            user.send(&Action::Answer { choice }).await;
+            let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+            let_assert!(HostEvent::RoundEnd { .. } = host.recv().await.unwrap());
+            let_assert!(UserEvent::RoundEnd { .. } = user.recv().await.unwrap());
+        }
+
+        let (mut host, room_id) = server.create_room(vec![question.clone(), question.clone()]).await;
+        let token = server.state.find_room(&room_id).unwrap().host_token.clone();
+        let mut alice = server.join_room(room_id, String::from("Alice")).await;
+        let_assert!(UserEvent::Joined { .. } = alice.recv().await.unwrap());
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+
+        play_round(&mut host, &mut alice, 1).await;
+        play_round(&mut host, &mut alice, 0).await;
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::GameResults { .. } = host.recv().await.unwrap());
+
+        let (status, body) = server.get("/games").await;
+        assert_eq!(status, 200);
+        let games: Vec<GameSummary> = serde_json::from_str(&body).unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!((games[0].outcome, games[0].players, games[0].rounds), (Outcome::Finished, 1, 2));
+
+        // Only the host can see a game
+        let (status, _) = server.get(&format!("/games/{}", games[0].id)).await;
+        assert_eq!(status, 403);
+        let (status, _) = server.get(&format!("/games/{}?token=guess", games[0].id)).await;
+        assert_eq!(status, 403);
+
+        let (status, body) = server.get(&format!("/games/{}?token={token}", games[0].id)).await;
+        assert_eq!(status, 200);
+        let game: GameRecord = serde_json::from_str(&body).unwrap();
+        assert_eq!(game.room_id, room_id);
+        assert!(game.host_token.is_empty(), "The token isn't sent back");
+        assert_eq!(game.players[0].username, "Alice");
+        assert_eq!(game.players[0].total_points, 1000);
+        assert_eq!(game.rounds[0].answers[0].choice, 1);
+        assert_eq!(game.rounds[0].point_gains["Alice"], 1000);
+        assert_eq!(game.rounds[0].correct, ["Alice"]);
+        assert_eq!(game.rounds[1].answers[0].choice, 0);
+        assert!(game.rounds[1].correct.is_empty());
+
+        // Games the host leaves are kept too
+        let (mut host, room_id) = server.create_room(vec![question.clone(), question]).await;
+        let mut bob = server.join_room(room_id, String::from("Bob")).await;
+        let_assert!(UserEvent::Joined { .. } = bob.recv().await.unwrap());
+        let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+
+        play_round(&mut host, &mut bob, 1).await;
+        host.0.close(None).await.unwrap();
+
+        let mut games = Vec::new();
+        for _ in 0..50 {
+            let (_, body) = server.get("/games").await;
+            games = serde_json::from_str::<Vec<GameSummary>>(&body).unwrap();
+            if games.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(games.len(), 2);
+        assert_eq!((games[0].outcome, games[0].rounds), (Outcome::Aborted, 1));
+
+        let (status, _) = server.get(&format!("/games/0?token={token}")).await;
+        assert_eq!(status, 404);
+
+        // Finished games can be exported for gradebooks
+        let (status, _) = server.get(&format!("/games/{}/export/csv", games[1].id)).await;
+        assert_eq!(status, 403);
+        let (status, body) = server
+            .get(&format!("/games/{}/export/csv?order=points&anonymize=true&token={token}", games[1].id))
+            .await;
+        assert_eq!(status, 200);
+        let lines: Vec<_> = body.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("Q2 choice,Q2 correct,Q2 time,Q2 points"), "{body}");
+        assert!(lines[1].starts_with("Player 1,,,1000,1,bar,TRUE,"), "{body}");
+
+        let (status, body) = server.get(&format!("/games/{}/export/xlsx?token={token}", games[1].id)).await;
+        assert_eq!(status, 200);
+        assert!(body.starts_with("PK"), "Workbooks are zip files");
+    }
+
+    #[tokio::test]
+    async fn record_and_replay() {
+        let server = TestServer::recording().await;
+        let settings = RoomSettings { shuffle_choices: true, friendly_names: true, ..RoomSettings::default() };
+        let question = question! {
+            "Fish?", time: 30 => [
+                false => "foo",
+                true => "bar",
+                false => "baz",
+            ]
+        };
+        let (mut host, room_id) = server.create_room_with(vec![question.clone(), question], settings).await;
+
+        let mut users = Vec::new();
+        for _ in 0..2 {
+            let mut user = server.join_room(room_id, String::from("Fish")).await;
+            let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+            let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+            users.push(user);
+        }
+
+        // Everyone answers the first question, and the host cuts the second
+        // short after one answer
+        for answering in [2, 1] {
+            host.send(&Action::BeginRound).await;
+            let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+
+            for (choice, user) in users.iter_mut().enumerate() {
+                let_assert!(UserEvent::RoundBegin { .. } = user.recv().await.unwrap());
+                if choice < answering {
+                    user.send(&Action::Answer { choice }).await;
+                    let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+                }
+            }
+            if answering < users.len() {
+                host.send(&Action::EndRound).await;
+            }
+
+            let_assert!(HostEvent::RoundEnd { .. } = host.recv().await.unwrap());
+            for user in &mut users {
+                let_assert!(UserEvent::RoundEnd { .. } = user.recv().await.unwrap());
+            }
+        }
+
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::GameResults { .. } = host.recv().await.unwrap());
+        let_assert!(HostEvent::GameEnd = host.recv().await.unwrap());
+        for user in &mut users {
+            let_assert!(UserEvent::GameEnd = user.recv().await.unwrap());
+        }
+
+        // Give the recording a moment to be written
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let dir = server.dir.path().join("recordings");
+        let file = std::fs::read_dir(dir).unwrap().next().unwrap().unwrap().path();
+        let entries = replay::read(&file).unwrap();
+
+        // Replays start their own runtime, which can't be done from this one
+        let replayed = |entries| {
+            std::thread::spawn(|| replay::replay(entries, WordList::from_env()))
+                .join()
+                .unwrap()
+        };
+
+        let report = replayed(entries).unwrap();
+        assert!(report.messages > 20);
+        assert_eq!(report.mismatches, []);
+
+        // Without an answer, the game goes differently
+        let mut entries = replay::read(&file).unwrap();
+        let answer = entries
+            .iter()
+            .position(|entry| matches!(entry, Entry::Received { action: Action::Answer { .. }, .. }))
+            .unwrap();
+        entries.remove(answer);
+
+        let report = replayed(entries).unwrap();
+        assert!(!report.mismatches.is_empty());
+    }
+
+    #[tokio::test]
+    async fn challenge() {
+        let server = TestServer::new().await;
+        let question = question! {
+            "Fish?", time: 1 => [
+                false => "foo",
+                true => "bar",
+            ]
+        };
+
+        // Create a challenge that's open for a minute
+        let mut host = HostSocket(server.connect().await);
+        host.send(&Action::CreateChallenge {
+            questions: vec![question.clone(), question.clone()],
+            deadline: now_millis() + 60_000,
+        })
+        .await;
+        let_assert!(HostEvent::ChallengeCreated { challenge_id } = host.recv().await.unwrap());
+
+        // Take the challenge
+        let mut user = UserSocket(server.connect().await);
+        user.send(&Action::JoinChallenge { challenge_id, username: String::from("Alice") })
+            .await;
+        let_assert!(UserEvent::Joined { .. } = user.recv().await.unwrap());
+
+        // Answer the first question right
+        user.send(&Action::NextQuestion).await;
+        let_assert!(
+            UserEvent::ChallengeQuestion { index: 0, count: 2, question: text, .. } = user.recv().await.unwrap()
+        );
+        assert_eq!(text, question.question);
+        user.send(&Action::Answer { choice: 1 }).await;
+        let_assert!(UserEvent::AnswerResult { correct: true, points, total_points } = user.recv().await.unwrap());
+        assert!(points > 500);
+        assert_eq!(points, total_points);
+
+        // Run out of time on the second question
+        user.send(&Action::NextQuestion).await;
+        let_assert!(UserEvent::ChallengeQuestion { index: 1, .. } = user.recv().await.unwrap());
+        let_assert!(UserEvent::AnswerResult { correct: false, points: 0, .. } = user.recv().await.unwrap());
+        let_assert!(UserEvent::GameEnd = user.recv().await.unwrap());
+
+        // Results are kept on the server
+        let (status, body) = server.get(&format!("/challenges/{challenge_id}")).await;
+        assert_eq!(status, 200);
+
+        let results: ChallengeResults = serde_json::from_str(&body).unwrap();
+        assert_eq!(results.players.len(), 1);
+
+        let alice = &results.players[0];
+        assert!(alice.finished);
+        assert_eq!(alice.answers[0].choice, Some(1));
+        assert_eq!(alice.answers[1].choice, None);
+
+        // Unknown challenge
+        let (status, _) = server.get("/challenges/0").await;
+        assert_eq!(status, 404);
+
+        // Questions are checked like a room's
+        let mut host = HostSocket(server.connect().await);
+        host.send(&Action::CreateChallenge {
+            questions: vec![Question { answer: 2, ..question.clone() }],
+            deadline: now_millis() + 60_000,
+        })
+        .await;
+        let_assert!(HostEvent::CreateFailed { reason } = host.recv().await.unwrap());
+        assert_eq!(reason, "Question 1: The answer isn't one of the choices");
+
+        // Results from long ago are thrown away once the next challenge
+        // comes in
+        let old = server
+            .state
+            .insert_challenge(Arc::new(Challenge::new(vec![question.clone()], 1)));
+        let (status, _) = server.get(&format!("/challenges/{old}")).await;
+        assert_eq!(status, 404);
+
+        server
+            .state
+            .insert_challenge(Arc::new(Challenge::new(vec![question], now_millis() + 60_000)));
+        let challenges = server.state.challenges.lock().unwrap();
+        assert!(!challenges.contains_key(&old));
+        assert!(challenges.contains_key(&challenge_id));
+    }
+
+    #[tokio::test]
+    async fn snapshot_and_restore() {
+        let snapshots = tempfile::tempdir().unwrap();
+        let server = TestServer::snapshotting(snapshots.path()).await;
+        let questions = vec![
+            question! {
+                "Fish?", time: 30 => [
+                    false => "foo",
+                    true => "bar",
+                ]
+            },
+            question! {
+                "Cats?", time: 30 => [
+                    false => "foo",
+                    true => "bar",
+                ]
+            },
+        ];
+
+        let mut host = HostSocket(server.connect().await);
+        host.send(&Action::CreateRoom { questions, settings: RoomSettings::default() })
+            .await;
+        let_assert!(HostEvent::RoomCreated { room_id, token: host_token } = host.recv().await.unwrap());
+
+        // Both players are given tokens to rejoin with
+        let mut tokens = Vec::new();
+        for name in ["Alice", "Bob"] {
+            let mut user = server.join_room(room_id, String::from(name)).await;
+            let_assert!(UserEvent::Joined { token: Some(token), .. } = user.recv().await.unwrap());
+            let_assert!(HostEvent::UserJoined { .. } = host.recv().await.unwrap());
+            tokens.push((user, token));
+        }
+        let ((mut alice, alice_token), (_bob, bob_token)) = (tokens.remove(0), tokens.remove(0));
+
+        // Alice answers the first question before the server stops
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { .. } = host.recv().await.unwrap());
+        let_assert!(UserEvent::RoundBegin { .. } = alice.recv().await.unwrap());
+        alice.send(&Action::Answer { choice: 1 }).await;
+        let_assert!(HostEvent::UserAnswered { .. } = host.recv().await.unwrap());
+        snapshot::save_all(&server.state).await;
+
+        // A new server picks the room back up
+        let server = TestServer::snapshotting(snapshots.path()).await;
+
+        // Unknown tokens are turned away
+        let mut stranger = UserSocket(server.connect().await);
+        stranger
+            .send(&Action::RejoinRoom { room_id, token: String::from("nope") })
+            .await;
+        let_assert!(UserEvent::JoinFailed { code: JoinFailCode::UnknownToken, .. } = stranger.recv().await.unwrap());
+
+        let mut impostor = HostSocket(server.connect().await);
+        impostor.send(&Action::ResumeRoom { room_id, token: alice_token.clone() }).await;
+        let_assert!(HostEvent::ResumeFailed { .. } = impostor.recv().await.unwrap());
+
+        // Players come back as themselves
+        let mut alice = UserSocket(server.connect().await);
+        alice.send(&Action::RejoinRoom { room_id, token: alice_token.clone() }).await;
+        let_assert!(UserEvent::Joined { username, token } = alice.recv().await.unwrap());
+        assert_eq!(username, "Alice");
+        assert_eq!(token, Some(alice_token));
+
+        let mut bob = UserSocket(server.connect().await);
+        bob.send(&Action::RejoinRoom { room_id, token: bob_token }).await;
+        let_assert!(UserEvent::Joined { username, .. } = bob.recv().await.unwrap());
+        assert_eq!(username, "Bob");
+
+        // The host takes the room back, and the round carries on
+        let mut host = HostSocket(server.connect().await);
+        host.send(&Action::ResumeRoom { room_id, token: host_token }).await;
+        let_assert!(HostEvent::RoomResumed { players, rounds_played: 0, .. } = host.recv().await.unwrap());
+        assert_eq!(players, ["Alice", "Bob"]);
+        let_assert!(HostEvent::RoundBegin { time_left: Some(time_left), .. } = host.recv().await.unwrap());
+        assert!(time_left <= 30);
+
+        let_assert!(UserEvent::RoundBegin { time, .. } = bob.recv().await.unwrap());
+        assert!(time <= 30);
+        bob.send(&Action::Answer { choice: 1 }).await;
+
+        // Alice's answer from before the restart still counts, and it was
+        // faster than Bob's
+        let_assert!(HostEvent::UserAnswered { username } = host.recv().await.unwrap());
+        assert_eq!(username, "Bob");
+        let_assert!(HostEvent::RoundEnd { point_gains, .. } = host.recv().await.unwrap());
+        assert!(point_gains["Alice"] > point_gains["Bob"]);
+        assert!(point_gains["Bob"] > 0);
+
+        // The rest of the game goes on as usual
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::RoundBegin { question, time_left: None, .. } = host.recv().await.unwrap());
+        assert_eq!(question.question, "Cats?");
+        host.send(&Action::EndRound).await;
+        let_assert!(HostEvent::RoundEnd { .. } = host.recv().await.unwrap());
+        host.send(&Action::BeginRound).await;
+        let_assert!(HostEvent::GameResults { totals, .. } = host.recv().await.unwrap());
+        assert_eq!(totals.len(), 2);
+        let_assert!(HostEvent::GameEnd = host.recv().await.unwrap());
+
+        // Closed rooms aren't restored again
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(Snapshots::new(snapshots.path()).load().unwrap().is_empty());
+    }
+
+    /// Convert a `Serialize`able into a JSON message.
+    fn serial(s: &impl Serialize) -> Message {
+        let json_string = serde_json::to_string(s).unwrap();
+        Message::text(json_string)
+    }
+}
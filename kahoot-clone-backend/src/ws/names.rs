@@ -0,0 +1,107 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// The first half of a friendly name.
+const ADJECTIVES: &[&str] = &[
+    "Brave", "Bouncy", "Bright", "Bubbly", "Calm", "Cheerful", "Clever", "Cosy", "Curious", "Daring", "Dizzy", "Eager",
+    "Fancy", "Fluffy", "Friendly", "Fuzzy", "Gentle", "Giggly", "Glowing", "Happy", "Helpful", "Jolly", "Kind",
+    "Lively", "Lucky", "Merry", "Mighty", "Nimble", "Peppy", "Polite", "Proud", "Quick", "Quiet", "Shiny", "Silly",
+    "Sleepy", "Snappy", "Sparkly", "Speedy", "Sunny", "Swift", "Tiny", "Wise", "Witty", "Zippy",
+];
+
+/// The second half of a friendly name.
+const ANIMALS: &[&str] = &[
+    "Alpaca",
+    "Badger",
+    "Beaver",
+    "Bunny",
+    "Chinchilla",
+    "Cheetah",
+    "Chipmunk",
+    "Dolphin",
+    "Duckling",
+    "Eagle",
+    "Ferret",
+    "Flamingo",
+    "Fox",
+    "Gecko",
+    "Giraffe",
+    "Goose",
+    "Hedgehog",
+    "Hippo",
+    "Jellyfish",
+    "Kangaroo",
+    "Kitten",
+    "Koala",
+    "Lemur",
+    "Llama",
+    "Lobster",
+    "Meerkat",
+    "Moose",
+    "Narwhal",
+    "Octopus",
+    "Otter",
+    "Owl",
+    "Panda",
+    "Parrot",
+    "Penguin",
+    "Puffin",
+    "Puppy",
+    "Quokka",
+    "Raccoon",
+    "Seal",
+    "Sloth",
+    "Squirrel",
+    "Tiger",
+    "Toucan",
+    "Turtle",
+    "Walrus",
+    "Wombat",
+    "Zebra",
+];
+
+/// How many random names to try before adding a number to the end.
+const ATTEMPTS: usize = 20;
+
+/// Picks a random friendly name (eg. `Sleepy Otter`) which isn't taken.
+pub fn friendly_name(rng: &mut impl Rng, mut is_taken: impl FnMut(&str) -> bool) -> String {
+    let random_name = |rng: &mut _| {
+        let adjective = ADJECTIVES.choose(rng).unwrap();
+        let animal = ANIMALS.choose(rng).unwrap();
+        format!("{adjective} {animal}")
+    };
+
+    for _ in 0..ATTEMPTS {
+        let name = random_name(rng);
+        if !is_taken(&name) {
+            return name;
+        }
+    }
+
+    // The room is pretty full, so fall back to numbered names
+    loop {
+        let name = format!("{} {}", random_name(rng), rng.gen_range(2..100));
+        if !is_taken(&name) {
+            return name;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn avoids_taken_names() {
+        let mut rng = rand::thread_rng();
+        let name = friendly_name(&mut rng, |_| false);
+        let (adjective, animal) = name.split_once(' ').unwrap();
+
+        assert!(ADJECTIVES.contains(&adjective));
+        assert!(ANIMALS.contains(&animal));
+
+        // Only numbered names are free
+        let name = friendly_name(&mut rng, |name| !name.ends_with(char::is_numeric));
+        assert!(name.ends_with(char::is_numeric));
+    }
+}
@@ -0,0 +1,262 @@
+use super::api::{Adaptive, Difficulty, Question, QuestionPick, Selection};
+
+use std::collections::BTreeMap;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+/// A question picked to be played next.
+pub struct Picked {
+    pub question: Question,
+    /// The id the question is known by in game results.
+    pub id: String,
+    /// Why the question was picked, for adaptive games.
+    pub pick: Option<QuestionPick>,
+}
+
+/// Picks the questions of a game one at a time.
+pub enum QuestionSource {
+    /// Plays a list of questions in order.
+    Fixed(std::vec::IntoIter<(String, Question)>),
+    /// Picks harder or easier questions depending on how the last one went.
+    Adaptive(Box<AdaptivePicker>),
+}
+
+impl QuestionSource {
+    /// Plays the questions in order, with the ids they are known by.
+    pub fn fixed(questions: Vec<(String, Question)>) -> Self {
+        Self::Fixed(questions.into_iter())
+    }
+
+    /// Picks questions from a pool by difficulty.
+    pub fn adaptive(pool: Vec<Question>, settings: Adaptive, seed: u64) -> Self {
+        Self::Adaptive(Box::new(AdaptivePicker::new(pool, settings, seed)))
+    }
+
+    /// Picks the next question, given the share of players who got the last
+    /// one right.
+    ///
+    /// Returns `None` once the game is over.
+    pub fn next(&mut self, correct_rate: Option<f64>) -> Option<Picked> {
+        match self {
+            Self::Fixed(questions) => questions.next().map(|(id, question)| Picked { question, id, pick: None }),
+            Self::Adaptive(picker) => picker.next(correct_rate),
+        }
+    }
+}
+
+/// Picks questions from a pool by difficulty.
+///
+/// Questions without a difficulty count as medium.
+pub struct AdaptivePicker {
+    pool: Vec<Question>,
+    settings: Adaptive,
+    /// The questions that haven't been played yet, by difficulty.
+    remaining: BTreeMap<Difficulty, Vec<usize>>,
+    /// The difficulty of the last question.
+    current: Option<Difficulty>,
+    rng: StdRng,
+}
+
+impl AdaptivePicker {
+    pub fn new(pool: Vec<Question>, settings: Adaptive, seed: u64) -> Self {
+        let mut remaining: BTreeMap<Difficulty, Vec<usize>> = BTreeMap::new();
+        for (index, question) in pool.iter().enumerate() {
+            let difficulty = question.difficulty.unwrap_or(Difficulty::Medium);
+            remaining.entry(difficulty).or_default().push(index);
+        }
+
+        Self { pool, settings, remaining, current: None, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    fn next(&mut self, correct_rate: Option<f64>) -> Option<Picked> {
+        let played = self.pool.len() - self.remaining.values().map(Vec::len).sum::<usize>();
+        if played >= self.settings.count {
+            return None;
+        }
+
+        let (target, mut reason) = match (self.current, correct_rate) {
+            (Some(current), Some(rate)) => {
+                let percent = (rate * 100.0).round();
+
+                if rate >= self.settings.harder_above {
+                    (current.harder(), format!("{percent}% got the last question right, so it gets harder"))
+                } else if rate < self.settings.easier_below {
+                    (current.easier(), format!("{percent}% got the last question right, so it gets easier"))
+                } else {
+                    (current, format!("{percent}% got the last question right, so it stays the same"))
+                }
+            }
+            (Some(current), None) => (current, String::from("Nobody played the last question, so it stays the same")),
+            (None, _) => (self.settings.start, String::from("The game starts here")),
+        };
+
+        // Fall back to the closest difficulty with questions left, preferring
+        // easier ones
+        let difficulty = Difficulty::ALL
+            .into_iter()
+            .filter(|difficulty| self.remaining.get(difficulty).is_some_and(|left| !left.is_empty()))
+            .min_by_key(|difficulty| (difficulty.distance(target), *difficulty))?;
+
+        if difficulty != target {
+            reason.push_str(&format!(", but there are no {target:?} questions left"));
+        }
+
+        let left = self.remaining.get_mut(&difficulty)?;
+        let index = left.swap_remove(self.rng.gen_range(0..left.len()));
+        self.current = Some(difficulty);
+
+        Some(Picked {
+            question: self.pool[index].clone(),
+            id: question_id(&self.pool, index),
+            pick: Some(QuestionPick { difficulty, correct_rate, reason }),
+        })
+    }
+}
+
+/// Draws questions from a pool, following the selection.
+///
+/// The draw only depends on the seed, so the same seed gives the same
+/// questions in the same order. Returns the indexes of the drawn questions in
+/// the pool, or why there aren't enough questions.
+pub fn draw(pool: &[Question], selection: &Selection, seed: u64) -> Result<Vec<usize>, String> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let candidates: Vec<usize> = (0..pool.len())
+        .filter(|&index| selection.tags.is_empty() || pool[index].tags.iter().any(|tag| selection.tags.contains(tag)))
+        .collect();
+
+    let mut drawn = Vec::new();
+
+    for (&difficulty, &wanted) in &selection.difficulty {
+        let matching: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|&index| pool[index].difficulty == Some(difficulty))
+            .collect();
+
+        if matching.len() < wanted {
+            return Err(format!("Only {} {difficulty:?} questions match, but {wanted} are needed", matching.len()));
+        }
+
+        drawn.extend(matching.choose_multiple(&mut rng, wanted));
+    }
+
+    if drawn.len() > selection.count {
+        return Err(format!("The difficulties add up to more than {} questions", selection.count));
+    }
+
+    // The rest can be anything, as long as it doesn't count towards a
+    // difficulty
+    let rest: Vec<usize> = candidates
+        .into_iter()
+        .filter(|&index| {
+            pool[index]
+                .difficulty
+                .is_none_or(|difficulty| !selection.difficulty.contains_key(&difficulty))
+        })
+        .collect();
+
+    let wanted = selection.count - drawn.len();
+    if rest.len() < wanted {
+        return Err(format!("Only {} questions match, but {} are needed", rest.len() + drawn.len(), selection.count));
+    }
+
+    drawn.extend(rest.choose_multiple(&mut rng, wanted));
+    drawn.shuffle(&mut rng);
+
+    Ok(drawn)
+}
+
+/// Pairs every question with the id it is known by in game results.
+pub fn with_ids(questions: Vec<Question>) -> Vec<(String, Question)> {
+    let ids: Vec<_> = (0..questions.len()).map(|index| question_id(&questions, index)).collect();
+
+    ids.into_iter().zip(questions).collect()
+}
+
+/// The id a question is known by in game results.
+///
+/// Questions without an id are known by their position in the pool.
+pub fn question_id(pool: &[Question], index: usize) -> String {
+    pool[index].id.clone().unwrap_or_else(|| index.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::api::Difficulty;
+
+    use std::collections::BTreeMap;
+
+    fn question(tag: &str, difficulty: Option<Difficulty>) -> Question {
+        Question {
+            question: String::from("?"),
+            choices: vec![String::from("a"), String::from("b")],
+            time: 10,
+            tags: vec![String::from(tag)],
+            difficulty,
+            ..Question::default()
+        }
+    }
+
+    fn pool() -> Vec<Question> {
+        vec![
+            question("algebra", Some(Difficulty::Hard)),
+            question("algebra", Some(Difficulty::Easy)),
+            question("geometry", Some(Difficulty::Hard)),
+            question("geometry", None),
+            question("history", Some(Difficulty::Hard)),
+            question("algebra", None),
+        ]
+    }
+
+    #[test]
+    fn follows_selection() {
+        let pool = pool();
+        let selection = Selection {
+            count: 4,
+            tags: vec![String::from("algebra"), String::from("geometry")],
+            difficulty: BTreeMap::from([(Difficulty::Hard, 2)]),
+        };
+
+        let drawn = draw(&pool, &selection, 7).unwrap();
+        assert_eq!(drawn, draw(&pool, &selection, 7).unwrap());
+
+        // Both hard questions with the right tags, and two of the others
+        assert_eq!(drawn.len(), 4);
+        assert!(drawn.contains(&0) && drawn.contains(&2));
+        assert!(!drawn.contains(&4));
+    }
+
+    #[test]
+    fn adapts_to_correct_rate() {
+        let settings = Adaptive { count: 4, ..Adaptive::default() };
+        let mut source = QuestionSource::adaptive(pool(), settings, 7);
+
+        let difficulty = |source: &mut QuestionSource, rate| source.next(rate).unwrap().pick.unwrap().difficulty;
+
+        // Starts at medium (questions without a difficulty)
+        assert_eq!(difficulty(&mut source, None), Difficulty::Medium);
+        assert_eq!(difficulty(&mut source, Some(1.0)), Difficulty::Hard);
+        assert_eq!(difficulty(&mut source, Some(0.0)), Difficulty::Medium);
+        // There are no medium questions left
+        assert_eq!(difficulty(&mut source, Some(0.5)), Difficulty::Easy);
+        assert!(source.next(Some(0.5)).is_none());
+    }
+
+    #[test]
+    fn not_enough_questions() {
+        let pool = pool();
+        let selection = Selection {
+            count: 2,
+            tags: vec![String::from("algebra")],
+            difficulty: BTreeMap::from([(Difficulty::Hard, 2)]),
+        };
+        assert!(draw(&pool, &selection, 7).is_err());
+
+        let selection = Selection { count: 10, tags: Vec::new(), difficulty: BTreeMap::new() };
+        assert!(draw(&pool, &selection, 7).is_err());
+    }
+}
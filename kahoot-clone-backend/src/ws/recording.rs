@@ -0,0 +1,266 @@
+use super::api::{Action, Question, RoomId, RoomSettings};
+
+use crate::results::QuizRef;
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::extract::ws::Message;
+
+use futures::{Sink, Stream};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// The directory used when `KAHOOT_RECORDINGS_DIR` isn't set.
+pub const DEFAULT_RECORDINGS_DIR: &str = "recordings";
+
+/// Something that happened in a recorded room.
+///
+/// Recordings are files with one JSON entry per line, starting with
+/// `roomCreated`. Times are in milliseconds since the room was created.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Entry {
+    RoomCreated(Created),
+    /// An action a client sent.
+    ///
+    /// The host is connection 0. The first action on a player's connection is
+    /// the `joinRoom` or `rejoinRoom` they came in with.
+    Received {
+        at: u64,
+        connection: usize,
+        action: Action,
+    },
+    /// A message sent to a client.
+    Sent {
+        at: u64,
+        connection: usize,
+        message: Value,
+    },
+    /// A client's connection closed.
+    Closed {
+        at: u64,
+        connection: usize,
+    },
+}
+
+/// How a recorded room was set up.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Created {
+    pub room_id: RoomId,
+    /// In milliseconds since the Unix epoch.
+    pub created_at: u64,
+    pub seed: u64,
+    /// Where the room's rejoin tokens come from.
+    pub token_seed: u64,
+    /// The questions the host sent, or those of the quiz the room was created
+    /// from, before any were picked or shuffled.
+    pub questions: Vec<Question>,
+    pub settings: RoomSettings,
+    /// Which quiz the questions came from, if they came from the quiz library.
+    pub quiz: Option<QuizRef>,
+}
+
+/// What a replayed room has to reuse from its recording.
+pub struct Replaying {
+    pub room_id: RoomId,
+    /// In milliseconds since the Unix epoch.
+    pub created_at: u64,
+    pub token_seed: u64,
+}
+
+/// Keeps every recording as a file in a directory, named after when the room
+/// was created and its id.
+pub struct Recordings {
+    dir: PathBuf,
+}
+
+impl Recordings {
+    /// Records rooms into the given directory.
+    ///
+    /// The directory is created when the first room is recorded.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Uses the directory at `KAHOOT_RECORDINGS_DIR`, or
+    /// `DEFAULT_RECORDINGS_DIR` if it isn't set.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("KAHOOT_RECORDINGS_DIR").unwrap_or_else(|_| DEFAULT_RECORDINGS_DIR.into()))
+    }
+
+    /// Starts recording a room, which was created at `started` on the clock
+    /// timers use.
+    pub fn start(&self, created: Created, started: Instant) -> io::Result<Recording> {
+        fs::create_dir_all(&self.dir)?;
+
+        let path = self.dir.join(format!("{}-{}.jsonl", created.created_at, created.room_id));
+        let file = File::create(path)?;
+        let (lines, lines_rx) = mpsc::unbounded_channel();
+        tokio::spawn(write_lines(file, lines_rx));
+
+        let recording = Recording {
+            started,
+            lines,
+            // The host is connection 0
+            connections: AtomicUsize::new(1),
+        };
+        recording.write(&Entry::RoomCreated(created));
+
+        Ok(recording)
+    }
+}
+
+/// Writes lines to a recording's file until the recording is dropped.
+///
+/// Lines are written in batches on a blocking thread, and the file is flushed
+/// whenever there's nothing left to write, so rooms never wait on the disk.
+async fn write_lines(file: File, mut lines: mpsc::UnboundedReceiver<String>) {
+    let mut file = BufWriter::new(file);
+
+    while let Some(line) = lines.recv().await {
+        let mut batch = vec![line];
+        while let Ok(line) = lines.try_recv() {
+            batch.push(line);
+        }
+
+        let written = tokio::task::spawn_blocking(move || {
+            let written = batch
+                .iter()
+                .try_for_each(|line| writeln!(file, "{line}"))
+                .and_then(|_| file.flush());
+            (file, written)
+        })
+        .await;
+
+        match written {
+            Ok((written_to, result)) => {
+                file = written_to;
+                if let Err(e) = result {
+                    tracing::error!("Couldn't write to a recording: {e}");
+                }
+            }
+            // The file went with the thread that panicked
+            Err(e) => {
+                tracing::error!("Couldn't write to a recording: {e}");
+                return;
+            }
+        }
+    }
+}
+
+/// Where everything that happens in a room is written down.
+pub struct Recording {
+    started: Instant,
+    /// Sends lines to the task writing the file.
+    lines: mpsc::UnboundedSender<String>,
+    /// The next player's connection number.
+    connections: AtomicUsize,
+}
+
+impl Recording {
+    /// Gives a new connection its number.
+    pub fn connect(&self) -> usize {
+        self.connections.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Milliseconds since the room was created.
+    pub fn at(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    /// Adds an entry, logging instead of failing if it can't.
+    ///
+    /// The entry is written to the file in the background.
+    pub fn write(&self, entry: &Entry) {
+        let written = serde_json::to_string(entry)
+            .map_err(|e| e.to_string())
+            .and_then(|json| self.lines.send(json).map_err(|e| e.to_string()));
+
+        if let Err(e) = written {
+            tracing::error!("Couldn't write to a recording: {e}");
+        }
+    }
+}
+
+/// A socket which writes down the actions and events going through it, if its
+/// room is recorded.
+pub struct RecordedSocket<S> {
+    inner: S,
+    recording: Option<(Arc<Recording>, usize)>,
+}
+
+impl<S> RecordedSocket<S> {
+    /// Wraps a socket, which is recorded as `connection` if there's a
+    /// recording.
+    pub fn new(inner: S, recording: Option<(Arc<Recording>, usize)>) -> Self {
+        Self { inner, recording }
+    }
+}
+
+impl<S> Stream for RecordedSocket<S>
+where
+    S: Stream<Item = Result<Message, axum::Error>> + Unpin,
+{
+    type Item = Result<Message, axum::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let polled = Pin::new(&mut self.inner).poll_next(cx);
+
+        if let (Poll::Ready(message), Some((recording, connection))) = (&polled, &self.recording) {
+            let (at, connection) = (recording.at(), *connection);
+
+            match message {
+                // Anything that isn't an action is ignored anyway
+                Some(Ok(Message::Text(text))) => {
+                    if let Ok(action) = serde_json::from_str(text) {
+                        recording.write(&Entry::Received { at, connection, action });
+                    }
+                }
+                Some(Ok(_)) => (),
+                Some(Err(_)) | None => recording.write(&Entry::Closed { at, connection }),
+            }
+        }
+
+        polled
+    }
+}
+
+impl<S> Sink<Message> for RecordedSocket<S>
+where
+    S: Sink<Message, Error = axum::Error> + Unpin,
+{
+    type Error = axum::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, message: Message) -> Result<(), Self::Error> {
+        if let (Message::Text(text), Some((recording, connection))) = (&message, &self.recording) {
+            if let Ok(message) = serde_json::from_str(text) {
+                recording.write(&Entry::Sent { at: recording.at(), connection: *connection, message });
+            }
+        }
+
+        Pin::new(&mut self.inner).start_send(message)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
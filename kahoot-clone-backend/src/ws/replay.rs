@@ -0,0 +1,303 @@
+use super::api::Action;
+use super::filter::NameFilter;
+use super::recording::{Created, Entry, Replaying};
+use super::state::State;
+use super::{create_room, join_room, Joining};
+
+use crate::quiz::store::QuizStore;
+use crate::results::store::MemoryResults;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use axum::extract::ws::Message;
+
+use futures::{Sink, Stream};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+
+/// How long a replay keeps going after the last recorded entry, to catch
+/// anything sent that wasn't recorded, and how long past its time an action
+/// waits for what its client saw first.
+const GRACE: Duration = Duration::from_secs(1);
+
+/// How a replay went.
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    /// The number of messages sent in the recording.
+    pub messages: usize,
+    /// The first difference on every connection where the replay sent
+    /// something else.
+    pub mismatches: Vec<Mismatch>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Mismatch {
+    pub connection: usize,
+    /// The 0-indexed position of the message among those sent on the
+    /// connection.
+    pub index: usize,
+    /// What was sent when the room was recorded, if anything.
+    pub recorded: Option<Value>,
+    /// What was sent in the replay, if anything.
+    pub replayed: Option<Value>,
+}
+
+/// Reads a recording back.
+pub fn read(path: &Path) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            entries.push(serde_json::from_str(&line)?);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Feeds a recording back through the game, and checks that it sends the same
+/// messages as when it was recorded.
+///
+/// Actions are sent at the same times as they were recorded, on a paused
+/// clock, so timers go off the same way without having to wait for them. Each
+/// action also waits until its client has been sent as many messages as it had
+/// when it was recorded, since many can happen in the same millisecond.
+/// Messages are compared connection by connection, since the order messages to
+/// different clients were sent in depends on how tasks were scheduled. For the
+/// same reason, messages recorded in the same millisecond on a connection can
+/// come in any order.
+///
+/// Usernames are checked with `name_filter`, which should be the filter the
+/// room was recorded with. The quiz library isn't used, since the room plays
+/// the questions written down when it was created, even if it was created from
+/// a quiz that has changed or been deleted since.
+pub fn replay(entries: Vec<Entry>, name_filter: impl NameFilter + 'static) -> Result<Report, String> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .start_paused(true)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    runtime.block_on(run(entries, name_filter))
+}
+
+async fn run(entries: Vec<Entry>, name_filter: impl NameFilter + 'static) -> Result<Report, String> {
+    let mut entries = entries.into_iter();
+    let Some(Entry::RoomCreated(created)) = entries.next() else {
+        return Err(String::from("The recording doesn't start with a room being created"));
+    };
+
+    let state = State::new(name_filter, QuizStore::empty(), MemoryResults::default(), None, None);
+    let started = Instant::now();
+    let replayed = Arc::new(Sent::default());
+    let mut recorded: HashMap<usize, Vec<(u64, Value)>> = HashMap::new();
+    let mut last = 0;
+
+    // The host's socket, which creates the room
+    let Created { room_id, created_at, seed, token_seed, questions, mut settings, quiz } = created;
+    settings.seed = Some(seed);
+    let (host, host_actions) = ChannelSocket::connect(0, &replayed);
+    let replaying = Replaying { room_id, created_at, token_seed };
+    tokio::spawn(create_room(host, Arc::clone(&state), questions, settings, quiz, Some(replaying)));
+
+    // Senders for what every client sends
+    let mut connections = HashMap::from([(0, host_actions)]);
+
+    for entry in entries {
+        match entry {
+            Entry::RoomCreated(_) => return Err(String::from("The recording has more than one room")),
+            Entry::Received { at, connection, action } => {
+                let at_time = started + Duration::from_millis(at);
+                tokio::time::sleep_until(at_time).await;
+                last = last.max(at);
+
+                let seen = recorded.get(&connection).map_or(0, Vec::len);
+                let _ = tokio::time::timeout_at(at_time + GRACE, replayed.wait_for(connection, seen)).await;
+
+                if let Some(actions) = connections.get(&connection) {
+                    let _ = actions.send(Message::Text(serde_json::to_string(&action).map_err(|e| e.to_string())?));
+                    continue;
+                }
+
+                let (room_id, joining) = match action {
+                    Action::JoinRoom { room_id, username, code, team } => {
+                        (room_id, Joining::New { username, code, team })
+                    }
+                    Action::RejoinRoom { room_id, token } => (room_id, Joining::Returning { token }),
+                    _ => return Err(format!("Connection {connection} doesn't start by joining the room")),
+                };
+
+                let (socket, actions) = ChannelSocket::connect(connection, &replayed);
+                tokio::spawn(join_room(socket, Arc::clone(&state), room_id, joining));
+                connections.insert(connection, actions);
+            }
+            Entry::Sent { at, connection, message } => {
+                last = last.max(at);
+                recorded.entry(connection).or_default().push((at, message));
+            }
+            Entry::Closed { at, connection } => {
+                tokio::time::sleep_until(started + Duration::from_millis(at)).await;
+                last = last.max(at);
+
+                connections.remove(&connection);
+            }
+        }
+    }
+
+    tokio::time::sleep_until(started + Duration::from_millis(last) + GRACE).await;
+    let replayed = std::mem::take(&mut *replayed.messages.lock().unwrap());
+
+    Ok(compare(recorded, replayed))
+}
+
+/// Finds the first difference on every connection.
+fn compare(recorded: HashMap<usize, Vec<(u64, Value)>>, mut replayed: HashMap<usize, Vec<Value>>) -> Report {
+    let mut report = Report { messages: recorded.values().map(Vec::len).sum(), ..Report::default() };
+
+    // Connections in order, including ones that only sent something in the
+    // replay
+    let mut connections: BTreeMap<usize, Vec<(u64, Value)>> =
+        replayed.keys().map(|connection| (*connection, Vec::new())).collect();
+    connections.extend(recorded);
+
+    for (connection, recorded) in connections {
+        let replayed = replayed.remove(&connection).unwrap_or_default();
+
+        // Compare the messages from each millisecond, and then anything extra
+        // the replay sent
+        let mut start = 0;
+        let mut runs: Vec<Vec<&Value>> = recorded
+            .chunk_by(|a, b| a.0 == b.0)
+            .map(|run| run.iter().map(|(_, message)| message).collect())
+            .collect();
+        runs.push(Vec::new());
+
+        for (index, run) in runs.iter().enumerate() {
+            let last = index == runs.len() - 1;
+            let end = if last { replayed.len() } else { (start + run.len()).min(replayed.len()) };
+            let replayed: Vec<&Value> = replayed.get(start..end).unwrap_or_default().iter().collect();
+
+            let (recorded, replayed) = (in_order(run), in_order(&replayed));
+            let length = recorded.len().max(replayed.len());
+            if let Some(offset) = (0..length).find(|offset| recorded.get(*offset) != replayed.get(*offset)) {
+                report.mismatches.push(Mismatch {
+                    connection,
+                    index: start + offset,
+                    recorded: recorded.get(offset).map(|message| (*message).clone()),
+                    replayed: replayed.get(offset).map(|message| (*message).clone()),
+                });
+                break;
+            }
+
+            start += run.len();
+        }
+    }
+
+    report
+}
+
+/// Sorts messages sent in the same millisecond, so they can be compared.
+fn in_order<'a>(messages: &[&'a Value]) -> Vec<&'a Value> {
+    let mut messages = messages.to_vec();
+    messages.sort_by_cached_key(|message| message.to_string());
+
+    messages
+}
+
+/// The messages sent to every connection in a replay.
+#[derive(Default)]
+struct Sent {
+    messages: Mutex<HashMap<usize, Vec<Value>>>,
+    /// Goes up with every message.
+    count: watch::Sender<usize>,
+}
+
+impl Sent {
+    fn push(&self, connection: usize, message: Value) {
+        self.messages.lock().unwrap().entry(connection).or_default().push(message);
+        self.count.send_modify(|count| *count += 1);
+    }
+
+    /// Waits until a connection has been sent `count` messages.
+    async fn wait_for(&self, connection: usize, count: usize) {
+        let sent = |connection| self.messages.lock().unwrap().get(&connection).map_or(0, Vec::len);
+
+        let _ = self.count.subscribe().wait_for(|_| sent(connection) >= count).await;
+    }
+}
+
+/// A socket that stands in for a client in a replay.
+struct ChannelSocket {
+    actions: mpsc::UnboundedReceiver<Message>,
+    messages: Option<mpsc::UnboundedSender<Message>>,
+}
+
+impl ChannelSocket {
+    /// Creates a socket whose messages are kept in `sent`.
+    ///
+    /// Returns the socket and a sender for what the client sends. The socket
+    /// closes when the sender is dropped.
+    fn connect(connection: usize, sent: &Arc<Sent>) -> (Self, mpsc::UnboundedSender<Message>) {
+        let (actions_tx, actions) = mpsc::unbounded_channel();
+        let (messages, mut messages_rx) = mpsc::unbounded_channel();
+
+        let sent = Arc::clone(sent);
+        tokio::spawn(async move {
+            while let Some(message) = messages_rx.recv().await {
+                // Pings aren't recorded
+                if let Message::Text(text) = message {
+                    if let Ok(message) = serde_json::from_str(&text) {
+                        sent.push(connection, message);
+                    }
+                }
+            }
+        });
+
+        (Self { actions, messages: Some(messages) }, actions_tx)
+    }
+}
+
+impl Stream for ChannelSocket {
+    type Item = Result<Message, axum::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.actions.poll_recv(cx).map(|message| message.map(Ok))
+    }
+}
+
+impl Sink<Message> for ChannelSocket {
+    type Error = axum::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, message: Message) -> Result<(), Self::Error> {
+        self.messages
+            .as_ref()
+            .and_then(|messages| messages.send(message).ok())
+            .ok_or_else(|| axum::Error::new("The socket is closed"))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.messages = None;
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,109 @@
+use super::api::{JoinFailCode, RosterEntry};
+use super::username;
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// The list of players allowed into a room.
+pub struct Roster {
+    entries: Vec<RosterEntry>,
+    /// Names of the entries that have been in the room at some point.
+    attended: Mutex<HashSet<String>>,
+}
+
+impl Roster {
+    pub fn new(entries: Vec<RosterEntry>) -> Self {
+        Self { entries, attended: Mutex::new(HashSet::new()) }
+    }
+
+    /// Finds the entry a user is joining as.
+    ///
+    /// Users can type either the entry's name (ignoring case) or its student id.
+    /// If the entry has a join code, the user has to give the same code.
+    pub fn find(&self, typed: &str, code: Option<&str>) -> Result<&RosterEntry, JoinFailCode> {
+        let typed = username::normalize(typed)?.to_lowercase();
+
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| {
+                entry.name.to_lowercase() == typed
+                    || entry.id.as_deref().map(str::to_lowercase).as_deref() == Some(typed.as_str())
+            })
+            .ok_or(JoinFailCode::NotOnRoster)?;
+
+        match &entry.code {
+            Some(expected) if Some(expected.as_str()) != code.map(str::trim) => Err(JoinFailCode::WrongJoinCode),
+            _ => Ok(entry),
+        }
+    }
+
+    /// Every entry, in roster order.
+    pub fn entries(&self) -> &[RosterEntry] {
+        &self.entries
+    }
+
+    /// Remembers that an entry has been in the room.
+    pub fn mark_attended(&self, name: &str) {
+        self.attended.lock().unwrap().insert(String::from(name));
+    }
+
+    /// Names of the entries that have been in the room at some point.
+    pub fn attended(&self) -> Vec<String> {
+        let mut attended: Vec<_> = self.attended.lock().unwrap().iter().cloned().collect();
+        attended.sort();
+
+        attended
+    }
+
+    /// Names of the entries that aren't in the room right now.
+    pub fn missing(&self, mut is_present: impl FnMut(&str) -> bool) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| !is_present(&entry.name))
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    /// Names of the entries that never joined the room.
+    pub fn absentees(&self) -> Vec<String> {
+        let attended = self.attended.lock().unwrap();
+
+        self.missing(|name| attended.contains(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, id: Option<&str>, code: Option<&str>) -> RosterEntry {
+        RosterEntry {
+            name: String::from(name),
+            id: id.map(String::from),
+            code: code.map(String::from),
+            time_multiplier: None,
+        }
+    }
+
+    #[test]
+    fn find_entries() {
+        let roster = Roster::new(vec![entry("Alice", None, Some("1234")), entry("Bob", Some("S002"), None)]);
+
+        assert_eq!(roster.find(" alice ", Some("1234")).unwrap().name, "Alice");
+        assert_eq!(roster.find("s002", None).unwrap().name, "Bob");
+        assert_eq!(roster.find("Alice", None), Err(JoinFailCode::WrongJoinCode));
+        assert_eq!(roster.find("Alice", Some("4321")), Err(JoinFailCode::WrongJoinCode));
+        assert_eq!(roster.find("Carol", None), Err(JoinFailCode::NotOnRoster));
+    }
+
+    #[test]
+    fn absentees() {
+        let roster = Roster::new(vec![entry("Alice", None, None), entry("Bob", None, None)]);
+
+        assert_eq!(roster.missing(|name| name == "Bob"), ["Alice"]);
+
+        roster.mark_attended("Bob");
+        assert_eq!(roster.absentees(), ["Alice"]);
+    }
+}
@@ -0,0 +1,106 @@
+use super::api::AnswerTiming;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A player's answer to a round, and when they gave it.
+struct RoundAnswer {
+    choice: usize,
+    /// When the player first answered, since the round began.
+    first: Duration,
+    /// When the player last changed their answer, since the round began.
+    last: Duration,
+}
+
+/// Collects the answers given during a round, so they can be scored once the
+/// round is over.
+#[derive(Default)]
+pub struct RoundAnswers {
+    answers: HashMap<String, RoundAnswer>,
+}
+
+impl RoundAnswers {
+    /// Records a player's answer, replacing any answer they gave before.
+    pub fn record(&mut self, username: &str, choice: usize, elapsed: Duration) {
+        self.answers
+            .entry(String::from(username))
+            .and_modify(|answer| {
+                answer.choice = choice;
+                answer.last = elapsed;
+            })
+            .or_insert(RoundAnswer { choice, first: elapsed, last: elapsed });
+    }
+
+    /// The players whose final answer is correct, fastest first.
+    pub fn correct(&self, answer: usize, timing: AnswerTiming) -> Vec<String> {
+        let mut correct: Vec<_> = self
+            .answers
+            .iter()
+            .filter(|(_, given)| given.choice == answer)
+            .map(|(name, given)| {
+                let time = match timing {
+                    AnswerTiming::First => given.first,
+                    AnswerTiming::Last => given.last,
+                };
+
+                (time, name)
+            })
+            .collect();
+        correct.sort();
+
+        correct.into_iter().map(|(_, name)| name.clone()).collect()
+    }
+
+    /// The players whose final answer is wrong.
+    pub fn wrong(&self, answer: usize) -> impl Iterator<Item = &str> {
+        self.answers
+            .iter()
+            .filter(move |(_, given)| given.choice != answer)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// Works out the points for each correct answer, fastest first.
+///
+/// The fastest answer gets `max_points`, and every answer after that gets a bit
+/// less than the one before it.
+pub fn ranked_points(max_points: u32, count: usize) -> Vec<u32> {
+    let mut points = max_points;
+
+    (0..count)
+        .map(|_| {
+            let gain = points;
+            // Questions worth nothing stay that way, and the decay is worked
+            // out in `u64` so it can't overflow
+            let decayed = u64::from(points) * 10 / 11;
+            points = (decayed as u32).max(points.min(1));
+            gain
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn final_answers_count() {
+        let mut answers = RoundAnswers::default();
+        answers.record("Alice", 0, Duration::from_secs(1));
+        answers.record("Bob", 1, Duration::from_secs(2));
+        answers.record("Alice", 1, Duration::from_secs(3));
+        answers.record("Carol", 0, Duration::from_secs(4));
+
+        assert_eq!(answers.correct(1, AnswerTiming::First), ["Alice", "Bob"]);
+        assert_eq!(answers.correct(1, AnswerTiming::Last), ["Bob", "Alice"]);
+        assert_eq!(answers.wrong(1).collect::<Vec<_>>(), ["Carol"]);
+    }
+
+    #[test]
+    fn points_by_rank() {
+        assert_eq!(ranked_points(1000, 3), [1000, 909, 826]);
+        assert_eq!(ranked_points(1, 2), [1, 1]);
+        assert_eq!(ranked_points(0, 2), [0, 0]);
+        assert_eq!(ranked_points(u32::MAX, 2), [u32::MAX, 3904515722]);
+    }
+}
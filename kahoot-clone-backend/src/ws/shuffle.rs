@@ -0,0 +1,62 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+/// Puts the questions in a random order that only depends on the seed.
+pub fn shuffle_questions<T>(questions: &mut [T], seed: u64) {
+    questions.shuffle(&mut StdRng::seed_from_u64(seed));
+}
+
+/// The order a player sees a question's choices in.
+///
+/// `order[shown]` is the index of the choice shown at position `shown`. The
+/// order only depends on the seed, the round and the player's name, so it is
+/// the same every time it's worked out.
+pub fn choice_order(seed: u64, round: usize, username: &str, count: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..count).collect();
+    order.shuffle(&mut StdRng::seed_from_u64(mix(seed, round, username)));
+
+    order
+}
+
+/// Combines everything a player's choice order depends on into one seed,
+/// using FNV-1a so it doesn't change between builds.
+fn mix(seed: u64, round: usize, username: &str) -> u64 {
+    let bytes = seed
+        .to_le_bytes()
+        .into_iter()
+        .chain((round as u64).to_le_bytes())
+        .chain(username.bytes());
+
+    bytes.fold(0xcbf29ce484222325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_order() {
+        let mut a: Vec<_> = (0..10).collect();
+        let mut b = a.clone();
+        shuffle_questions(&mut a, 42);
+        shuffle_questions(&mut b, 42);
+        assert_eq!(a, b);
+
+        assert_eq!(choice_order(42, 0, "Alice", 4), choice_order(42, 0, "Alice", 4));
+    }
+
+    #[test]
+    fn orders_are_per_player() {
+        let mut order = choice_order(42, 0, "Alice", 4);
+        order.sort();
+        assert_eq!(order, [0, 1, 2, 3]);
+
+        // Some of these are bound to differ
+        let orders: Vec<_> = ["Alice", "Bob", "Carol", "Dave", "Erin"]
+            .into_iter()
+            .map(|name| choice_order(42, 0, name, 4))
+            .collect();
+        assert!(orders.iter().any(|order| *order != orders[0]));
+    }
+}
@@ -0,0 +1,284 @@
+use super::api::{Question, RoomId, RoomSettings};
+use super::state::{Room, SharedState};
+
+use crate::results::{GameRecorder, QuizRef};
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// The directory used when `KAHOOT_SNAPSHOTS_DIR` isn't set.
+pub const DEFAULT_SNAPSHOTS_DIR: &str = "snapshots";
+
+/// How often every room is written to disk.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Everything needed to pick a room back up after a server restart.
+///
+/// Nobody is connected to a restored room, so players and the host come back
+/// with the tokens they were given.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub room_id: RoomId,
+    /// In milliseconds since the Unix epoch.
+    pub created_at: u64,
+    /// When the snapshot was taken, in milliseconds since the Unix epoch.
+    pub saved_at: u64,
+    pub seed: u64,
+    /// The questions the host sent, before any were picked or shuffled.
+    pub questions: Vec<Question>,
+    pub settings: RoomSettings,
+    pub quiz: Option<QuizRef>,
+    pub host_token: String,
+    /// Maps rejoin tokens to the player they were given to.
+    pub tokens: HashMap<String, String>,
+    /// Maps players to their team, for team games.
+    pub teams: HashMap<String, String>,
+    pub time_multipliers: HashMap<String, f64>,
+    /// The roster names that have been in the room.
+    pub attended: Vec<String>,
+    pub progress: Progress,
+}
+
+/// How far a room's game has got.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub phase: Phase,
+    /// Everything carried from one round to the next, once the game has
+    /// started.
+    pub game: Option<Game>,
+}
+
+/// What a room is doing.
+///
+/// Times are in milliseconds since the Unix epoch, on the room's clock.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Phase {
+    /// Waiting for the game to start.
+    #[default]
+    Lobby,
+    /// Players are betting before a wager round.
+    #[serde(rename_all = "camelCase")]
+    Wagers { ends_at: u64, placed: HashMap<String, u32> },
+    /// Players are answering a question.
+    #[serde(rename_all = "camelCase")]
+    Round {
+        started_at: u64,
+        /// The bets placed, for wager rounds.
+        wagers: Option<HashMap<String, u32>>,
+        /// Every answer given so far, in order.
+        answers: Vec<GivenAnswer>,
+    },
+    /// Waiting for the next round to begin.
+    BetweenRounds,
+}
+
+/// An answer given during a round.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GivenAnswer {
+    pub username: String,
+    /// The index of the choice in the question, not on the player's screen.
+    pub choice: usize,
+    /// Milliseconds since the round began.
+    pub elapsed: u64,
+}
+
+/// The state of a game as it goes from one round to the next.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Game {
+    /// The share of players who got each finished round right, in order.
+    ///
+    /// Adaptive games pick their questions from these, so they pick the same
+    /// ones again when restored.
+    pub correct_rates: Vec<Option<f64>>,
+    /// The total points of every player who answered at least once.
+    pub totals: HashMap<String, i64>,
+    /// The players who haven't been knocked out, for elimination games.
+    pub survivors: Option<HashSet<String>>,
+    pub starting_players: usize,
+    /// Everything written down for the game's results, as of the start of
+    /// the phase.
+    pub recorder: GameRecorder,
+}
+
+impl Snapshot {
+    /// Takes a snapshot of a room as it is now.
+    pub fn of(room_id: RoomId, room: &Room) -> Self {
+        Self {
+            room_id,
+            created_at: room.created_at,
+            saved_at: room.now_millis(),
+            seed: room.seed,
+            questions: room.questions.clone(),
+            settings: room.settings.clone(),
+            quiz: room.quiz.clone(),
+            host_token: room.host_token.clone(),
+            tokens: room.tokens.names(),
+            teams: room.teams.as_ref().map(|teams| teams.members()).unwrap_or_default(),
+            time_multipliers: room.time_multipliers.lock().unwrap().clone(),
+            attended: room.roster.as_ref().map(|roster| roster.attended()).unwrap_or_default(),
+            progress: room.progress.lock().unwrap().clone(),
+        }
+    }
+}
+
+/// Keeps a snapshot of every room as a file in a directory, named after the
+/// room's id.
+pub struct Snapshots {
+    dir: PathBuf,
+    /// Held while a file is written or removed, so a room's snapshot can't be
+    /// written after it's removed.
+    files: Mutex<()>,
+}
+
+impl Snapshots {
+    /// Keeps snapshots in the given directory.
+    ///
+    /// The directory is created when the first snapshot is taken.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), files: Mutex::new(()) }
+    }
+
+    /// Uses the directory at `KAHOOT_SNAPSHOTS_DIR`, or
+    /// `DEFAULT_SNAPSHOTS_DIR` if it isn't set.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("KAHOOT_SNAPSHOTS_DIR").unwrap_or_else(|_| DEFAULT_SNAPSHOTS_DIR.into()))
+    }
+
+    fn path(&self, room_id: RoomId) -> PathBuf {
+        self.dir.join(format!("{room_id}.json"))
+    }
+
+    /// Writes a room's snapshot, replacing the one before, unless the room
+    /// has closed.
+    pub fn save(&self, room_id: RoomId, room: &Room) -> io::Result<()> {
+        let _files = self.files.lock().unwrap();
+        if room.closed.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let snapshot = Snapshot::of(room_id, room);
+        fs::create_dir_all(&self.dir)?;
+
+        // Write to a temporary file first so a crash can't leave half a room
+        // behind
+        let path = self.path(room_id);
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_vec(&snapshot)?)?;
+        fs::rename(temporary, path)
+    }
+
+    /// Forgets a room, once it's closed.
+    pub fn remove(&self, room_id: RoomId) -> io::Result<()> {
+        let _files = self.files.lock().unwrap();
+        match fs::remove_file(self.path(room_id)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads every snapshot.
+    ///
+    /// Files that can't be read are logged and skipped, so one bad room
+    /// can't keep the others from coming back.
+    pub fn load(&self) -> io::Result<Vec<Snapshot>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            // Nothing has been saved yet
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+
+            let read = fs::read(&path).and_then(|bytes| Ok(serde_json::from_slice(&bytes)?));
+            match read {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(e) => tracing::error!("Couldn't read the snapshot at {}: {e}", path.display()),
+            }
+        }
+
+        Ok(snapshots)
+    }
+}
+
+/// Brings back every room from the last time the server ran.
+pub fn restore_all(state: &SharedState) {
+    let Some(snapshots) = &state.snapshots else {
+        return;
+    };
+
+    match snapshots.load() {
+        Ok(snapshots) => {
+            for snapshot in snapshots {
+                tracing::debug!("Restoring room `{}`", snapshot.room_id);
+                super::restore_room(state, snapshot);
+            }
+        }
+        Err(e) => tracing::error!("Couldn't read the room snapshots: {e}"),
+    }
+}
+
+/// Takes a snapshot of every room now.
+///
+/// Files are written on a blocking thread, without the rooms locked, so a slow
+/// disk doesn't hold up anyone joining or creating a room.
+pub async fn save_all(state: &SharedState) {
+    if state.snapshots.is_none() {
+        return;
+    }
+
+    let rooms: Vec<_> = state
+        .rooms
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(room_id, room)| (*room_id, Arc::clone(room)))
+        .collect();
+
+    let state = Arc::clone(state);
+    let saved = tokio::task::spawn_blocking(move || {
+        let Some(snapshots) = &state.snapshots else {
+            return;
+        };
+
+        for (room_id, room) in rooms {
+            if let Err(e) = snapshots.save(room_id, &room) {
+                tracing::error!("Couldn't save a snapshot of room `{room_id}`: {e}");
+            }
+        }
+    });
+    if let Err(e) = saved.await {
+        tracing::error!("Couldn't save the room snapshots: {e}");
+    }
+}
+
+/// Takes a snapshot of every room every few seconds, for as long as the
+/// server runs.
+pub async fn keep_snapshots(state: SharedState) {
+    if state.snapshots.is_none() {
+        return;
+    }
+
+    let mut interval = tokio::time::interval(SNAPSHOT_INTERVAL);
+    loop {
+        interval.tick().await;
+        save_all(&state).await;
+    }
+}
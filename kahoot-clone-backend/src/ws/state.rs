@@ -1,9 +1,30 @@
-use super::api::RoomId;
+use super::api::{ChallengeId, JoinFailCode, JoinRequestReason, Question, RoomId, RoomSettings, UserEvent};
+use super::challenge::Challenge;
+use super::filter::NameFilter;
+use super::recording::{Recording, Recordings};
+use super::roster::Roster;
+use super::snapshot::{Game, Phase, Progress, Snapshots};
+use super::teams::Teams;
+use super::{names, username, Socket};
+
+use crate::ext::ToMessageExt;
+use crate::quiz::store::QuizStore;
+use crate::results::store::ResultStore;
+use crate::results::QuizRef;
 
 use std::collections::{HashMap, HashSet};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::ws::Message;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
-use tokio::sync::{mpsc, watch, oneshot};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::time::Instant;
 
 // `Arc` is an "atomic reference counter" which allows multiple ownership
 // of values across threads.
@@ -18,141 +39,648 @@ pub struct State {
     //
     // Relevant: https://doc.rust-lang.org/book/ch16-03-shared-state.html
     pub rooms: Mutex<HashMap<RoomId, Arc<Room>>>,
+    pub challenges: Mutex<HashMap<ChallengeId, Arc<Challenge>>>,
+    /// Checks usernames for inappropriate words.
+    pub name_filter: Box<dyn NameFilter>,
+    /// The quiz library.
+    pub quizzes: QuizStore,
+    /// Where the records of past games are kept.
+    pub results: Box<dyn ResultStore>,
+    /// Where rooms are recorded, if they are.
+    pub recordings: Option<Recordings>,
+    /// Where rooms are kept across server restarts, if they are.
+    pub snapshots: Option<Snapshots>,
 }
 
 pub struct Room {
+    pub settings: RoomSettings,
+    pub roster: Option<Arc<Roster>>,
+    pub teams: Option<Teams>,
     pub users: Users,
     pub action_stream: mpsc::Sender<PlayerAnswer>,
+    pub wager_stream: mpsc::Sender<PlayerWager>,
     pub result_stream: watch::Receiver<GameEvent>,
+    /// How much longer than usual players get to answer.
+    ///
+    /// Players who aren't in here get the usual time.
+    pub time_multipliers: Mutex<HashMap<String, f64>>,
+    /// The seed everything random in the game comes from.
+    pub seed: u64,
+    /// When the room was created, in milliseconds since the Unix epoch.
+    pub created_at: u64,
+    /// When the room was created, on the clock timers use.
+    pub created: Instant,
+    /// Where everything sent and received in the room is written down, if
+    /// the server records rooms.
+    pub recording: Option<Arc<Recording>>,
+    /// The questions the host sent, before any were picked or shuffled.
+    pub questions: Vec<Question>,
+    /// The quiz from the quiz library being played, if there is one.
+    pub quiz: Option<QuizRef>,
+    /// Lets the host take the room back after a server restart.
+    pub host_token: String,
+    pub tokens: Tokens,
+    /// How far the game has got, kept up to date for snapshots.
+    pub progress: Mutex<Progress>,
+    /// Takes the host's socket when they come back to a room restored after
+    /// a server restart.
+    ///
+    /// This is empty once the host is back, and for rooms that weren't
+    /// restored.
+    pub host_slot: Mutex<Option<oneshot::Sender<Box<dyn Socket>>>>,
+    /// Set once the room is removed, so it isn't snapshotted again.
+    pub closed: AtomicBool,
+}
+
+/// The tokens players rejoin a room with.
+pub struct Tokens {
+    /// Picks new tokens.
+    ///
+    /// This has its own seed, since hosts can pick the room's seed and
+    /// shouldn't be able to work out anyone's token.
+    rng: Mutex<StdRng>,
+    /// Maps tokens to the name of the player they were given to.
+    names: Mutex<HashMap<String, String>>,
 }
 
 pub struct Users {
     pub users: Arc<Mutex<UserMap>>,
     event_stream: mpsc::Sender<PlayerEvent>,
+    /// Picks friendly names, so they only depend on the room's seed and the
+    /// order players join in.
+    rng: Mutex<StdRng>,
+    /// Maps the names held by rejoin tokens to their skeletons, so nobody
+    /// else can take them while their player is away.
+    reserved: Mutex<HashMap<String, String>>,
+    /// How many times each player has rerolled their friendly name, kept
+    /// across rejoins.
+    rerolls: Mutex<HashMap<String, u8>>,
 }
 
-type UserMap = HashSet<String>;
+/// The name a user joins with.
+pub enum Naming {
+    /// The name they typed, or were given by a roster or rejoin token.
+    Typed(String),
+    /// A placeholder name (eg. `Player 3`), for names the filter caught.
+    Placeholder,
+    /// A random friendly name.
+    Friendly,
+}
+
+/// Maps display names to their player info.
+type UserMap = HashMap<String, Player>;
+
+/// Information kept about each player in a room.
+pub struct Player {
+    /// The name's confusable skeleton, used to detect look-alike names.
+    skeleton: String,
+    /// Whether the player has been let into the room.
+    ///
+    /// Players waiting for the host's approval aren't counted as being in the
+    /// room, but their name is still reserved.
+    active: bool,
+    /// Used to tell a waiting player whether the host approved them.
+    approval: Option<oneshot::Sender<bool>>,
+    /// Sends messages straight to the player's socket.
+    messages: mpsc::Sender<Message>,
+}
 
-pub struct UserPresence(String, Arc<Mutex<UserMap>>, Option<oneshot::Sender<()>>);
+/// Keeps a user in the user map for as long as it is alive.
+pub struct UserPresence {
+    name: String,
+    user_map: Arc<Mutex<UserMap>>,
+    leave_tx: Option<oneshot::Sender<()>>,
+    approval: Option<oneshot::Receiver<bool>>,
+}
 
 pub struct PlayerAnswer {
     pub username: String,
     pub choice: usize,
 }
 
+pub struct PlayerWager {
+    pub username: String,
+    pub amount: u32,
+}
+
 #[derive(Clone)]
 pub enum GameEvent {
     InLobby,
     RoundBegin {
+        /// The 0-indexed position of the round.
+        round: usize,
         choices: Vec<String>,
+        /// The usual time to answer, before time multipliers.
+        time: u16,
+        /// How long the round has been going, for rounds picked back up
+        /// after a server restart.
+        elapsed: Duration,
     },
     RoundEnd {
-        point_gains: Arc<HashMap<String, u32>>,
+        point_gains: Arc<HashMap<String, i64>>,
+        /// Players knocked out this round, for elimination games.
+        eliminated: Arc<HashSet<String>>,
     },
     GameEnd,
 }
 
 pub enum PlayerEvent {
     Joined(String),
+    JoinRequested(String, JoinRequestReason),
+    Renamed { old: String, new: String },
+    TeamAssigned(String, String),
     Left(String),
 }
 
 impl State {
-    pub fn insert_room(&self, room: Arc<Room>) -> RoomId {
-        let mut rooms = self.rooms.lock().unwrap();
-        let id: RoomId = rand::random();
-
-        rooms.insert(id, room);
+    /// Creates an empty state which checks usernames with the given filter,
+    /// and keeps quizzes and game results in the given stores.
+    ///
+    /// Rooms are only recorded if `recordings` is given, and only kept across
+    /// restarts if `snapshots` is given.
+    pub fn new(
+        name_filter: impl NameFilter + 'static,
+        quizzes: QuizStore,
+        results: impl ResultStore + 'static,
+        recordings: Option<Recordings>,
+        snapshots: Option<Snapshots>,
+    ) -> SharedState {
+        Arc::new(State {
+            rooms: Mutex::new(HashMap::new()),
+            challenges: Mutex::new(HashMap::new()),
+            name_filter: Box::new(name_filter),
+            quizzes,
+            results: Box::new(results),
+            recordings,
+            snapshots,
+        })
+    }
 
-        id
+    pub fn insert_room(&self, id: RoomId, room: Arc<Room>) {
+        self.rooms.lock().unwrap().insert(id, room);
     }
 
-    pub async fn remove_room(&self, room_id: &RoomId) {
-        let mut rooms = self.rooms.lock().unwrap();
-        if rooms.remove(&room_id).is_none() {
-            tracing::debug!("Room `{room_id}` doesn't exist");
+    pub async fn remove_room(self: &Arc<Self>, room_id: &RoomId) {
+        let room = self.rooms.lock().unwrap().remove(room_id);
+        match room {
+            Some(room) => room.closed.store(true, Ordering::SeqCst),
+            None => tracing::debug!("Room `{room_id}` doesn't exist"),
+        }
+
+        if self.snapshots.is_none() {
             return;
         }
+
+        // Rooms are marked closed first, so a snapshot being written right
+        // now can't bring the room back
+        let state = Arc::clone(self);
+        let room_id = *room_id;
+        let removed = tokio::task::spawn_blocking(move || match &state.snapshots {
+            Some(snapshots) => snapshots.remove(room_id),
+            None => Ok(()),
+        });
+        if let Err(e) = removed.await.unwrap_or_else(|e| Err(io::Error::other(e))) {
+            tracing::error!("Couldn't remove the snapshot of room `{room_id}`: {e}");
+        }
     }
 
     pub fn find_room(&self, room_id: &RoomId) -> Option<Arc<Room>> {
         self.rooms.lock().unwrap().get(room_id).map(Arc::clone)
     }
+
+    pub fn insert_challenge(&self, challenge: Arc<Challenge>) -> ChallengeId {
+        let mut challenges = self.challenges.lock().unwrap();
+        let id: ChallengeId = rand::random();
+
+        // Old results are only thrown away as new challenges come in, which
+        // is enough to keep the map from growing forever
+        challenges.retain(|_, challenge| !challenge.is_expired());
+
+        challenges.insert(id, challenge);
+
+        id
+    }
+
+    pub fn find_challenge(&self, challenge_id: &ChallengeId) -> Option<Arc<Challenge>> {
+        self.challenges
+            .lock()
+            .unwrap()
+            .get(challenge_id)
+            .filter(|challenge| !challenge.is_expired())
+            .map(Arc::clone)
+    }
+}
+
+impl Room {
+    /// The current time in milliseconds since the Unix epoch, as far as the
+    /// room is concerned.
+    ///
+    /// This only moves with the clock timers use, so it doesn't change
+    /// between a game and its replay.
+    pub fn now_millis(&self) -> u64 {
+        self.created_at + self.created.elapsed().as_millis() as u64
+    }
+
+    /// Keeps track of how far the game has got, for snapshots.
+    pub fn save_progress(&self, phase: Phase, game: &Game) {
+        *self.progress.lock().unwrap() = Progress { phase, game: Some(game.clone()) };
+    }
+
+    /// Changes what the room is doing, without touching the rest of its
+    /// progress.
+    pub fn update_phase(&self, update: impl FnOnce(&mut Phase)) {
+        update(&mut self.progress.lock().unwrap().phase);
+    }
+
+    /// How much longer than usual a player gets to answer.
+    pub fn time_multiplier(&self, name: &str) -> f64 {
+        self.time_multipliers.lock().unwrap().get(name).copied().unwrap_or(1.0)
+    }
+
+    /// Gives a player more (or less) time to answer, from the next round on.
+    ///
+    /// Returns `false` if the multiplier isn't a positive number.
+    pub fn set_time_multiplier(&self, name: &str, multiplier: f64) -> bool {
+        if !multiplier.is_finite() || multiplier <= 0.0 {
+            return false;
+        }
+
+        self.time_multipliers.lock().unwrap().insert(String::from(name), multiplier);
+
+        true
+    }
+}
+
+impl Tokens {
+    /// Creates an empty set of tokens, which are picked from the seed.
+    pub fn new(seed: u64) -> Self {
+        Self { rng: Mutex::new(StdRng::seed_from_u64(seed)), names: Mutex::new(HashMap::new()) }
+    }
+
+    /// Takes back the tokens from a snapshot.
+    pub fn restore(names: HashMap<String, String>) -> Self {
+        Self { rng: Mutex::new(StdRng::from_entropy()), names: Mutex::new(names) }
+    }
+
+    /// Picks a new token, without giving it to anyone.
+    pub fn generate(&self) -> String {
+        format!("{:032x}", self.rng.lock().unwrap().gen::<u128>())
+    }
+
+    /// Gives a player a token.
+    ///
+    /// Returns `None` if the name already has a token, which only the player
+    /// holding it can come back with.
+    pub fn issue(&self, name: &str) -> Option<String> {
+        let mut names = self.names.lock().unwrap();
+        if names.values().any(|given| given == name) {
+            return None;
+        }
+
+        let token = self.generate();
+        names.insert(token.clone(), String::from(name));
+
+        Some(token)
+    }
+
+    /// The name of the player a token was given to.
+    pub fn name_of(&self, token: &str) -> Option<String> {
+        self.names.lock().unwrap().get(token).cloned()
+    }
+
+    /// Moves a player's token over to their new name.
+    pub fn rename(&self, old: &str, new: &str) {
+        for name in self.names.lock().unwrap().values_mut() {
+            if name == old {
+                *name = String::from(new);
+            }
+        }
+    }
+
+    /// Every token given out, and who to.
+    pub fn names(&self) -> HashMap<String, String> {
+        self.names.lock().unwrap().clone()
+    }
 }
 
 impl Users {
-    pub fn new() -> (Self, mpsc::Receiver<PlayerEvent>) {
+    pub fn new(seed: u64) -> (Self, mpsc::Receiver<PlayerEvent>) {
         let (tx, rx) = mpsc::channel(30);
 
-        let users = Arc::new(Mutex::new(HashSet::new()));
+        let users = Arc::new(Mutex::new(HashMap::new()));
 
         let users = Self {
             users,
             event_stream: tx,
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+            reserved: Mutex::new(HashMap::new()),
+            rerolls: Mutex::new(HashMap::new()),
         };
 
         (users, rx)
     }
 
+    /// The number of players that have been let into the room.
     pub fn player_count(&self) -> usize {
-        self.users.lock().unwrap().len()
+        self.users.lock().unwrap().values().filter(|player| player.active).count()
+    }
+
+    /// Whether a player has been let into the room.
+    pub fn is_present(&self, name: &str) -> bool {
+        self.users.lock().unwrap().get(name).is_some_and(|player| player.active)
+    }
+
+    /// The names of the players that have been let into the room.
+    pub fn names(&self) -> Vec<String> {
+        self.users
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, player)| player.active)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Sends a message straight to a player.
+    ///
+    /// Returns `false` if the player isn't in the room.
+    pub fn send_to(&self, name: &str, message: Message) -> bool {
+        self.users
+            .lock()
+            .unwrap()
+            .get(name)
+            .is_some_and(|player| player.messages.try_send(message).is_ok())
+    }
+
+    /// Tells a player and the host which team the player is on.
+    pub async fn announce_team(&self, name: &str, team: &str) {
+        let event = UserEvent::TeamAssigned { team: String::from(team) };
+        self.send_to(name, event.to_message());
+
+        let _ = self
+            .event_stream
+            .send(PlayerEvent::TeamAssigned(String::from(name), String::from(team)))
+            .await;
+    }
+
+    /// Checks whether a condition holds for every player in the room.
+    ///
+    /// Players waiting for approval are skipped.
+    pub fn all_players(&self, mut f: impl FnMut(&str) -> bool) -> bool {
+        self.users
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, player)| player.active)
+            .all(|(name, _)| f(name))
+    }
+
+    /// Keeps a name for the player holding a rejoin token for it, even
+    /// after they leave.
+    pub fn reserve(&self, name: &str) {
+        self.reserved
+            .lock()
+            .unwrap()
+            .insert(String::from(name), username::skeleton(name));
+    }
+
+    /// Finds a placeholder name (eg. `Player 3`) that isn't taken.
+    ///
+    /// The name is only free for as long as the locks are held.
+    fn placeholder_name(users: &UserMap, reserved: &HashMap<String, String>) -> String {
+        (1..)
+            .map(|n| format!("Player {n}"))
+            .find(|name| !users.contains_key(name) && !reserved.contains_key(name))
+            .unwrap()
+    }
+
+    /// Picks a random friendly name that nobody in the room has.
+    ///
+    /// The name is only free for as long as the locks are held.
+    fn friendly_name(&self, users: &UserMap, reserved: &HashMap<String, String>) -> String {
+        names::friendly_name(&mut *self.rng.lock().unwrap(), |name| {
+            let skeleton = username::skeleton(name);
+            users
+                .iter()
+                .map(|(taken, player)| (taken, &player.skeleton))
+                .chain(reserved.iter())
+                .any(|(taken, taken_skeleton)| username::check_clash(name, &skeleton, taken, taken_skeleton).is_err())
+        })
+    }
+
+    /// Gives a user a new random friendly name, if they haven't used up
+    /// their `limit` of rerolls.
+    ///
+    /// Returns the new name and how many rerolls the user has left.
+    pub async fn reroll_name(&self, presence: &mut UserPresence, limit: u8) -> Option<(String, u8)> {
+        let (new, rerolls_left) = {
+            let mut users = self.users.lock().unwrap();
+            let mut reserved = self.reserved.lock().unwrap();
+            let mut rerolls = self.rerolls.lock().unwrap();
+
+            let used = rerolls.remove(&presence.name).unwrap_or(0);
+            if used >= limit {
+                rerolls.insert(presence.name.clone(), used);
+                return None;
+            }
+
+            // The name is picked and taken under the same locks, so nobody
+            // else can get it in between
+            let new = self.friendly_name(&users, &reserved);
+            if let Some(mut player) = users.remove(&presence.name) {
+                player.skeleton = username::skeleton(&new);
+                users.insert(new.clone(), player);
+            }
+
+            // The player's token moves over to the new name
+            if reserved.remove(&presence.name).is_some() {
+                reserved.insert(new.clone(), username::skeleton(&new));
+            }
+
+            rerolls.insert(new.clone(), used + 1);
+            (new, limit - used - 1)
+        };
+
+        let old = std::mem::replace(&mut presence.name, new.clone());
+        tracing::debug!("Renamed `{old}` to `{new}`");
+
+        let _ = self.event_stream.send(PlayerEvent::Renamed { old, new: new.clone() }).await;
+
+        Some((new, rerolls_left))
     }
 
     /// Tries to add a user to the user map.
-    /// Returns a `Some(UserPresence)` on success and `None` on failure.
-    pub async fn join_user(&self, name: String) -> Option<UserPresence> {
-        {
+    ///
+    /// Typed names are normalized first (see `username::normalize`), so the
+    /// name stored in the returned `UserPresence` may differ from the one
+    /// given. Other names are picked while the user is added, so two users
+    /// joining at once can't be given the same one.
+    ///
+    /// If a request reason is given, the user isn't let in until the host
+    /// approves them (see `UserPresence::approval`).
+    ///
+    /// Messages for the user from `send_to` are sent to `messages`.
+    ///
+    /// Names held by rejoin tokens (see `reserve`) are taken, unless the user
+    /// is `reclaiming` their own.
+    ///
+    /// Returns a `UserPresence` on success and the reason on failure.
+    pub async fn join_user(
+        &self,
+        naming: Naming,
+        request: Option<JoinRequestReason>,
+        messages: mpsc::Sender<Message>,
+        reclaiming: bool,
+    ) -> Result<UserPresence, JoinFailCode> {
+        let naming = match naming {
+            Naming::Typed(name) => Naming::Typed(username::normalize(&name)?),
+            naming => naming,
+        };
+
+        // Set up oneshot channel for the host's approval
+        let (approval_tx, approval_rx) = match request {
+            Some(_) => {
+                let (tx, rx) = oneshot::channel();
+                (Some(tx), Some(rx))
+            }
+            None => (None, None),
+        };
+
+        let name = {
             tracing::debug!("Accquiring users lock to add new user...");
             let mut users = self.users.lock().unwrap();
+            let reserved = self.reserved.lock().unwrap();
             tracing::debug!("Lock accquired.");
 
-            if users.contains(&name) {
-                return None;
+            let name = match naming {
+                Naming::Typed(name) => name,
+                Naming::Placeholder => Self::placeholder_name(&users, &reserved),
+                Naming::Friendly => self.friendly_name(&users, &reserved),
+            };
+            let skeleton = username::skeleton(&name);
+
+            for (taken, player) in users.iter() {
+                username::check_clash(&name, &skeleton, taken, &player.skeleton)?;
+            }
+
+            for (taken, taken_skeleton) in reserved.iter() {
+                if !(reclaiming && *taken == name) {
+                    username::check_clash(&name, &skeleton, taken, taken_skeleton)?;
+                }
             }
 
             tracing::debug!("Adding `{name}`...");
-            let name = name.clone();
-            users.insert(name);
+            users.insert(name.clone(), Player { skeleton, active: request.is_none(), approval: approval_tx, messages });
 
             tracing::debug!("User added.");
-        }
+            name
+        };
 
-        // Emitting join event
-        let _ = self
-            .event_stream
-            .send(PlayerEvent::Joined(name.clone()))
-            .await;
+        // Emitting join (or join request) event
+        let event = match request {
+            Some(reason) => PlayerEvent::JoinRequested(name.clone(), reason),
+            None => PlayerEvent::Joined(name.clone()),
+        };
+        let _ = self.event_stream.send(event).await;
 
         // Copy the necessary values
         let user_map = Arc::clone(&self.users);
         let event_stream = self.event_stream.clone();
         let username = name.clone();
-        
+
         // Set up oneshot channel for leave message
         let (leave_tx, leave_rx) = oneshot::channel();
         tokio::spawn(async move {
-            // Wait for oneshot leave message
-            let _ = leave_rx.await;
-            // Emit player event to host
-            let _ = event_stream.send(PlayerEvent::Left(username)).await;
+            // Wait for oneshot leave message, which isn't sent for users who
+            // were never let in
+            if leave_rx.await.is_ok() {
+                // Emit player event to host
+                let _ = event_stream.send(PlayerEvent::Left(username)).await;
+            }
         });
 
-        Some(UserPresence(name, user_map, Some(leave_tx)))
+        Ok(UserPresence { name, user_map, leave_tx: Some(leave_tx), approval: approval_rx })
+    }
+
+    /// Approves every user waiting to join.
+    ///
+    /// Returns how many users were let in.
+    pub async fn approve_all(&self) -> usize {
+        let waiting: Vec<String> = self
+            .users
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, player)| player.approval.is_some())
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let mut approved = 0;
+        for name in waiting {
+            if self.resolve_request(&name, true).await {
+                approved += 1;
+            }
+        }
+
+        approved
+    }
+
+    /// Approves or denies a user waiting to join.
+    ///
+    /// Returns `false` if there is no such user waiting.
+    pub async fn resolve_request(&self, name: &str, approved: bool) -> bool {
+        {
+            let mut users = self.users.lock().unwrap();
+
+            let player = match users.get_mut(name) {
+                Some(player) => player,
+                None => return false,
+            };
+
+            let approval = match player.approval.take() {
+                Some(approval) => approval,
+                None => return false,
+            };
+
+            // If the user already left, there is nothing to do
+            if approval.send(approved).is_err() {
+                return false;
+            }
+
+            player.active = approved;
+        }
+
+        if approved {
+            let _ = self.event_stream.send(PlayerEvent::Joined(String::from(name))).await;
+        }
+
+        true
+    }
+}
+
+impl UserPresence {
+    /// The player's display name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Takes the receiver for the host's decision, if the user needs to be
+    /// approved.
+    pub fn approval(&mut self) -> Option<oneshot::Receiver<bool>> {
+        self.approval.take()
     }
 }
 
 impl Drop for UserPresence {
     /// Removes user from user map and emits a signal.
     fn drop(&mut self) {
-        let UserPresence(name, user_map, leave_tx) = self;
+        // Remove from user map
+        let mut user_map = self.user_map.lock().unwrap();
+        let player = user_map.remove(&self.name);
 
-        // Emit event and ignore any errors
-        if let Some(tx) = leave_tx.take() {
+        // Emit event if the user was let in, and ignore any errors
+        if let (Some(Player { active: true, .. }), Some(tx)) = (player, self.leave_tx.take()) {
             let _ = tx.send(());
         }
-
-        // Remove from user map
-        let mut user_map = user_map.lock().unwrap();
-        user_map.remove(name);
     }
 }
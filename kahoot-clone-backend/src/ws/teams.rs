@@ -0,0 +1,205 @@
+use super::api::{TeamScoring, TeamSettings, TeamStanding};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+/// Keeps track of which team each player is on.
+pub struct Teams {
+    settings: TeamSettings,
+    /// Maps usernames to their team.
+    ///
+    /// Players stay on their team after leaving, so their points still count
+    /// and they end up on the same team if they come back.
+    members: Mutex<HashMap<String, String>>,
+}
+
+impl Teams {
+    pub fn new(settings: TeamSettings) -> Self {
+        Self { settings, members: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn settings(&self) -> &TeamSettings {
+        &self.settings
+    }
+
+    /// Whether a team with this name exists.
+    pub fn exists(&self, team: &str) -> bool {
+        self.settings.names.iter().any(|name| name == team)
+    }
+
+    /// Puts a player on a team.
+    ///
+    /// Returns `false` if the team doesn't exist.
+    pub fn assign(&self, username: &str, team: &str) -> bool {
+        if !self.exists(team) {
+            return false;
+        }
+
+        self.members.lock().unwrap().insert(String::from(username), String::from(team));
+
+        true
+    }
+
+    /// Puts a player on the team with the fewest members, unless they are
+    /// already on one.
+    ///
+    /// Returns the player's team, or `None` if there are no teams.
+    pub fn auto_assign(&self, username: &str) -> Option<String> {
+        let mut members = self.members.lock().unwrap();
+
+        if let Some(team) = members.get(username) {
+            return Some(team.clone());
+        }
+
+        // Ties go to the team listed first
+        let team = self
+            .settings
+            .names
+            .iter()
+            .min_by_key(|team| members.values().filter(|t| t == team).count())?
+            .clone();
+
+        members.insert(String::from(username), team.clone());
+
+        Some(team)
+    }
+
+    /// The team a player is on.
+    pub fn team_of(&self, username: &str) -> Option<String> {
+        self.members.lock().unwrap().get(username).cloned()
+    }
+
+    /// Every player who has been on a team, and their team.
+    pub fn members(&self) -> HashMap<String, String> {
+        self.members.lock().unwrap().clone()
+    }
+
+    /// Ranks the teams by their combined score, highest first.
+    pub fn standings(&self, totals: &HashMap<String, i64>) -> Vec<TeamStanding> {
+        let members = self.members.lock().unwrap();
+
+        let mut standings: Vec<_> = self
+            .settings
+            .names
+            .iter()
+            .map(|team| {
+                let mut names: Vec<String> = members
+                    .iter()
+                    .filter(|(_, t)| *t == team)
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                names.sort();
+
+                let scores = names.iter().map(|name| totals.get(name).copied().unwrap_or(0));
+                let score = match self.settings.scoring {
+                    TeamScoring::Sum => scores.sum(),
+                    TeamScoring::Average if names.is_empty() => 0,
+                    TeamScoring::Average => scores.sum::<i64>() / names.len() as i64,
+                    TeamScoring::Best => scores.max().unwrap_or(0),
+                };
+
+                TeamStanding { team: team.clone(), score, members: names }
+            })
+            .collect();
+
+        // Sorting is stable, so tied teams keep their listed order
+        standings.sort_by_key(|standing| std::cmp::Reverse(standing.score));
+
+        standings
+    }
+
+    /// Finds the teams where every member in the room has answered, in the
+    /// order they are listed.
+    pub fn answered_teams(&self, present: &[String], answered: &HashSet<String>) -> Vec<String> {
+        let members = self.members.lock().unwrap();
+
+        let mut done: HashSet<String> = self.settings.names.iter().cloned().collect();
+        let mut has_members = HashSet::new();
+
+        for name in present {
+            if let Some(team) = members.get(name) {
+                has_members.insert(team.clone());
+
+                if !answered.contains(name) {
+                    done.remove(team);
+                }
+            }
+        }
+
+        // Empty teams can't answer
+        self.settings
+            .names
+            .iter()
+            .filter(|team| done.contains(*team) && has_members.contains(*team))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::api::TeamAssignment;
+
+    fn teams(scoring: TeamScoring) -> Teams {
+        Teams::new(TeamSettings {
+            names: vec![String::from("Red"), String::from("Blue")],
+            assignment: TeamAssignment::Auto,
+            scoring,
+        })
+    }
+
+    #[test]
+    fn auto_balance() {
+        let teams = teams(TeamScoring::Sum);
+
+        assert_eq!(teams.auto_assign("a").unwrap(), "Red");
+        assert_eq!(teams.auto_assign("b").unwrap(), "Blue");
+        assert_eq!(teams.auto_assign("c").unwrap(), "Red");
+        // Already on a team
+        assert_eq!(teams.auto_assign("b").unwrap(), "Blue");
+        assert_eq!(teams.auto_assign("d").unwrap(), "Blue");
+
+        assert!(!teams.assign("a", "Green"));
+        assert!(teams.assign("a", "Blue"));
+        assert_eq!(teams.team_of("a").unwrap(), "Blue");
+    }
+
+    #[test]
+    fn scoring_rules() {
+        let totals = HashMap::from([(String::from("a"), 300), (String::from("b"), 100), (String::from("c"), 250)]);
+
+        let score = |scoring| {
+            let teams = teams(scoring);
+            teams.assign("a", "Red");
+            teams.assign("b", "Red");
+            teams.assign("c", "Blue");
+
+            teams
+                .standings(&totals)
+                .into_iter()
+                .map(|standing| (standing.team, standing.score))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(score(TeamScoring::Sum), [(String::from("Red"), 400), (String::from("Blue"), 250)]);
+        assert_eq!(score(TeamScoring::Average), [(String::from("Blue"), 250), (String::from("Red"), 200)]);
+        assert_eq!(score(TeamScoring::Best), [(String::from("Red"), 300), (String::from("Blue"), 250)]);
+    }
+
+    #[test]
+    fn team_answered() {
+        let teams = teams(TeamScoring::Sum);
+        teams.assign("a", "Red");
+        teams.assign("b", "Red");
+        teams.assign("c", "Blue");
+
+        let present = [String::from("a"), String::from("b"), String::from("c")];
+
+        let answered = HashSet::from([String::from("a"), String::from("c")]);
+        assert_eq!(teams.answered_teams(&present, &answered), [String::from("Blue")]);
+
+        let answered = HashSet::from([String::from("a"), String::from("b")]);
+        assert_eq!(teams.answered_teams(&present, &answered), [String::from("Red")]);
+    }
+}
@@ -0,0 +1,110 @@
+use super::api::JoinFailCode;
+
+use unicode_normalization::UnicodeNormalization;
+
+/// The maximum number of characters allowed in a username.
+pub const MAX_USERNAME_LEN: usize = 24;
+
+/// Cleans up a username typed by a player.
+///
+/// The name is converted to Unicode NFC, control and zero-width characters are
+/// removed, and whitespace is trimmed and collapsed into single spaces.
+///
+/// Returns the display name on success, or the reason it was rejected.
+pub fn normalize(name: &str) -> Result<String, JoinFailCode> {
+    let cleaned: String = name.nfc().filter(|&c| !is_invisible(c)).collect();
+
+    // `split_whitespace` also takes care of trimming
+    let name = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if name.is_empty() {
+        return Err(JoinFailCode::UsernameEmpty);
+    }
+
+    if name.chars().count() > MAX_USERNAME_LEN {
+        return Err(JoinFailCode::UsernameTooLong);
+    }
+
+    Ok(name)
+}
+
+/// Gets the key used to check if two names look the same.
+///
+/// This is the lowercase name's "skeleton", which maps look-alike characters
+/// (eg. Cyrillic `а` and Latin `a`) to the same character.
+///
+/// Relevant: https://www.unicode.org/reports/tr39/#Confusable_Detection
+pub fn skeleton(name: &str) -> String {
+    unicode_security::skeleton(&name.to_lowercase()).collect()
+}
+
+/// Checks whether a new name clashes with a name that is already taken.
+///
+/// Both names are expected to have gone through `normalize` first.
+pub fn check_clash(name: &str, name_skeleton: &str, taken: &str, taken_skeleton: &str) -> Result<(), JoinFailCode> {
+    if name.to_lowercase() == taken.to_lowercase() {
+        Err(JoinFailCode::DuplicateUser)
+    } else if name_skeleton == taken_skeleton {
+        Err(JoinFailCode::ConfusableUser)
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether a character has no visible width, such as control characters,
+/// zero-width spaces and bidirectional overrides.
+fn is_invisible(c: char) -> bool {
+    c.is_control()
+        || matches!(
+            c,
+            // Soft hyphen and combining grapheme joiner
+            '\u{00AD}' | '\u{034F}'
+            // Mongolian vowel separator
+            | '\u{180E}'
+            // Zero-width spaces/joiners and directional marks
+            | '\u{200B}'..='\u{200F}'
+            // Directional embeddings and overrides
+            | '\u{202A}'..='\u{202E}'
+            // Word joiner, invisible operators and directional isolates
+            | '\u{2060}'..='\u{206F}'
+            // Byte order mark
+            | '\u{FEFF}'
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trims_and_strips() {
+        assert_eq!(normalize("  Alice ").unwrap(), "Alice");
+        assert_eq!(normalize("Alice\u{200B}").unwrap(), "Alice");
+        assert_eq!(normalize("Al\u{0007}ice").unwrap(), "Alice");
+        assert_eq!(normalize("Mary \t  Jane").unwrap(), "Mary Jane");
+    }
+
+    #[test]
+    fn applies_nfc() {
+        // `e` followed by a combining acute accent
+        assert_eq!(normalize("Jose\u{0301}").unwrap(), "Jos\u{00E9}");
+    }
+
+    #[test]
+    fn rejects_bad_lengths() {
+        assert_eq!(normalize(""), Err(JoinFailCode::UsernameEmpty));
+        assert_eq!(normalize(" \u{200B} "), Err(JoinFailCode::UsernameEmpty));
+        assert_eq!(normalize(&"a".repeat(5000)), Err(JoinFailCode::UsernameTooLong));
+        assert!(normalize(&"a".repeat(MAX_USERNAME_LEN)).is_ok());
+    }
+
+    #[test]
+    fn detects_clashes() {
+        let clash = |a: &str, b: &str| check_clash(a, &skeleton(a), b, &skeleton(b));
+
+        assert_eq!(clash("alice", "Alice"), Err(JoinFailCode::DuplicateUser));
+        // Cyrillic `А`
+        assert_eq!(clash("\u{0410}lice", "Alice"), Err(JoinFailCode::ConfusableUser));
+        assert_eq!(clash("Bob", "Alice"), Ok(()));
+    }
+}